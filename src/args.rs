@@ -0,0 +1,81 @@
+//! The flags given on the command line, read once when the app starts.
+//!
+//! Plugins look them up in the [`Args`] resource rather than reading the command line themselves,
+//! so that every game started by the headless simulation doesn't parse it again.
+
+use bevy::prelude::*;
+
+/// The command-line flags.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct Args {
+    /// `--finesse-training`: turn finesse training on, regardless of the settings.
+    pub finesse_training: bool,
+    /// `--ai`: let the built-in AI play.
+    pub ai: bool,
+    /// `--bot <command>`: let an external bot play, started with the given command.
+    pub bot: Option<String>,
+    /// `--ai-delay <ticks>`: how many ticks the AI waits between two key presses.
+    pub ai_delay: Option<u32>,
+    /// `--fumen <data>`: start games from the position encoded in a fumen.
+    pub fumen: Option<String>,
+    /// `--mode <mode>`: the mode of the games to play.
+    pub mode: Option<String>,
+    /// `--randomizer <name>`: how the pieces of the games to play are generated.
+    pub randomizer: Option<String>,
+    /// `--hints`: start games with hints shown.
+    pub hints: bool,
+    /// `--replay <file>`: watch the given replay rather than showing the main menu.
+    pub replay: Option<String>,
+}
+
+impl Args {
+    /// Read the flags the process was started with.
+    pub fn parse() -> Self {
+        Self::from_args(std::env::args().skip(1))
+    }
+
+    fn from_args(args: impl IntoIterator<Item = String>) -> Self {
+        let args: Vec<String> = args.into_iter().collect();
+        let flag = |name: &str| args.iter().any(|arg| arg == name);
+        let value = |name: &str| args.iter().skip_while(|arg| *arg != name).nth(1).cloned();
+        Self {
+            finesse_training: flag("--finesse-training"),
+            ai: flag("--ai"),
+            bot: value("--bot"),
+            ai_delay: value("--ai-delay").and_then(|delay| delay.parse().ok()),
+            fumen: value("--fumen"),
+            mode: value("--mode"),
+            randomizer: value("--randomizer"),
+            hints: flag("--hints"),
+            replay: value("--replay"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Args {
+        Args::from_args(line.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn flags_and_values() {
+        let args = parse("--mode sprint --hints --ai-delay 8 --randomizer classic");
+        assert_eq!(args.mode.as_deref(), Some("sprint"));
+        assert_eq!(args.randomizer.as_deref(), Some("classic"));
+        assert_eq!(args.ai_delay, Some(8));
+        assert!(args.hints);
+        assert!(!args.ai && !args.finesse_training);
+        assert_eq!(args.bot, None);
+        assert_eq!(args.fumen, None);
+    }
+
+    #[test]
+    fn missing_or_invalid_values() {
+        let args = parse("--ai-delay soon --bot");
+        assert_eq!(args.ai_delay, None);
+        assert_eq!(args.bot, None);
+    }
+}
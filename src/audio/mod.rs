@@ -0,0 +1,292 @@
+//! Sound effects for what happens in the game, and music that follows how intense it gets.
+//!
+//! All the sounds are synthesized when the game starts, so there are no audio assets to load.
+
+use bevy::{
+    audio::{AddAudioSource, Volume},
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+
+use crate::{
+    game::{
+        clear::{LineClear, PieceLocked, Spin},
+        mode::GameOver,
+        score::{LevelUp, Score},
+        GameState, PieceMoved,
+    },
+    screen::{GamePaused, InGame},
+    settings::Settings,
+    AppSet,
+};
+
+use self::{
+    music::{Music, MAX_INTENSITY},
+    synth::{note, render, Sound, Tone, Wave},
+};
+
+mod music;
+mod synth;
+
+/// Stack heights from which the music gets more intense, whatever the level.
+const DANGER_HEIGHTS: [u8; 2] = [10, 15];
+/// Number of levels for the music to get more intense.
+const LEVELS_PER_INTENSITY: u64 = 4;
+
+pub fn plugin(app: &mut App) {
+    app.add_audio_source::<Sound>()
+        .add_audio_source::<Music>()
+        .add_systems(Startup, synthesize)
+        .add_systems(OnEnter(InGame), start_music)
+        .add_systems(OnEnter(GamePaused), pause_music)
+        .add_systems(OnExit(GamePaused), resume_music)
+        .add_systems(
+            Update,
+            (
+                play_sound_effects,
+                update_music.run_if(in_state(InGame)),
+                update_music_volume.run_if(resource_changed::<Settings>),
+            )
+                .in_set(AppSet::Update),
+        );
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Sfx {
+    Shift,
+    Rotate,
+    SoftDrop,
+    HardDrop,
+    Hold,
+    Lock,
+    Single,
+    Double,
+    Triple,
+    Tetris,
+    /// A T-spin that doesn't clear lines.
+    Spin,
+    SpinClear,
+    PerfectClear,
+    LevelUp,
+    TopOut,
+    Victory,
+}
+
+impl Sfx {
+    const ALL: [Sfx; 16] = [
+        Sfx::Shift,
+        Sfx::Rotate,
+        Sfx::SoftDrop,
+        Sfx::HardDrop,
+        Sfx::Hold,
+        Sfx::Lock,
+        Sfx::Single,
+        Sfx::Double,
+        Sfx::Triple,
+        Sfx::Tetris,
+        Sfx::Spin,
+        Sfx::SpinClear,
+        Sfx::PerfectClear,
+        Sfx::LevelUp,
+        Sfx::TopOut,
+        Sfx::Victory,
+    ];
+
+    fn tones(self) -> Vec<Tone> {
+        // Notes played one after the other, each lasting `length` seconds
+        let arpeggio = |wave, notes: &[u8], length: f32| {
+            notes
+                .iter()
+                .enumerate()
+                .map(|(i, midi)| Tone::new(wave, note(*midi), length * 2.0).at(i as f32 * length))
+                .collect::<Vec<_>>()
+        };
+        match self {
+            Sfx::Shift => vec![Tone::new(Wave::Square, 660.0, 0.03).volume(0.08)],
+            Sfx::Rotate => vec![Tone::new(Wave::Triangle, 880.0, 0.06)
+                .slide(1320.0)
+                .volume(0.2)],
+            Sfx::SoftDrop => vec![Tone::new(Wave::Triangle, 330.0, 0.06)
+                .slide(220.0)
+                .volume(0.15)],
+            Sfx::HardDrop => vec![
+                Tone::new(Wave::Sine, 160.0, 0.15).slide(40.0).volume(0.7),
+                Tone::new(Wave::Noise, 0.0, 0.08).volume(0.2),
+            ],
+            Sfx::Hold => vec![
+                Tone::new(Wave::Triangle, note(72), 0.08).volume(0.2),
+                Tone::new(Wave::Triangle, note(79), 0.08)
+                    .at(0.05)
+                    .volume(0.2),
+            ],
+            Sfx::Lock => vec![Tone::new(Wave::Sine, 220.0, 0.06).slide(180.0).volume(0.3)],
+            Sfx::Single => arpeggio(Wave::Square, &[72], 0.08),
+            Sfx::Double => arpeggio(Wave::Square, &[72, 76], 0.07),
+            Sfx::Triple => arpeggio(Wave::Square, &[72, 76, 79], 0.06),
+            Sfx::Tetris => {
+                let mut tones = arpeggio(Wave::Square, &[72, 76, 79, 84], 0.06);
+                tones.extend(
+                    [60, 64, 67]
+                        .map(|midi| Tone::new(Wave::Saw, note(midi), 0.6).at(0.24).volume(0.12)),
+                );
+                tones
+            }
+            Sfx::Spin => vec![Tone::new(Wave::Saw, 440.0, 0.15).slide(880.0).volume(0.15)],
+            Sfx::SpinClear => {
+                let mut tones = arpeggio(Wave::Square, &[69, 72, 76, 81], 0.05);
+                tones.push(Tone::new(Wave::Saw, 440.0, 0.3).slide(1760.0).volume(0.12));
+                tones
+            }
+            Sfx::PerfectClear => arpeggio(Wave::Triangle, &[60, 64, 67, 72, 76, 79, 84, 88], 0.05),
+            Sfx::LevelUp => arpeggio(Wave::Square, &[67, 72, 76, 79], 0.09),
+            Sfx::TopOut => {
+                let mut tones = arpeggio(Wave::Triangle, &[64, 60, 57, 52], 0.18);
+                tones.push(Tone::new(Wave::Saw, 220.0, 0.9).slide(55.0).volume(0.15));
+                tones
+            }
+            Sfx::Victory => {
+                let mut tones = arpeggio(Wave::Square, &[72, 76, 79], 0.1);
+                tones.extend([72, 76, 79, 84].map(|midi| {
+                    Tone::new(Wave::Triangle, note(midi), 1.0)
+                        .at(0.3)
+                        .volume(0.15)
+                }));
+                tones
+            }
+        }
+    }
+}
+
+#[derive(Resource)]
+struct SoundEffects(HashMap<Sfx, Handle<Sound>>);
+
+/// The music played during games.
+#[derive(Resource)]
+struct MusicTrack(Handle<Music>);
+
+#[derive(Component)]
+struct MusicPlayer;
+
+fn synthesize(
+    mut commands: Commands,
+    mut sounds: ResMut<Assets<Sound>>,
+    mut musics: ResMut<Assets<Music>>,
+) {
+    let effects = Sfx::ALL
+        .into_iter()
+        .map(|sfx| (sfx, sounds.add(render(&sfx.tones()))))
+        .collect();
+    commands.insert_resource(SoundEffects(effects));
+    commands.insert_resource(MusicTrack(musics.add(Music::default())));
+}
+
+fn effects_volume(settings: &Settings) -> f32 {
+    settings.audio.master as f32 / 100.0 * settings.audio.effects as f32 / 100.0
+}
+
+fn music_volume(settings: &Settings) -> f32 {
+    settings.audio.master as f32 / 100.0 * settings.audio.music as f32 / 100.0
+}
+
+/// Play the sounds for the events of the last simulation ticks.
+///
+/// Every sound is played at most once per frame, so that fast-forwarding a replay doesn't play a
+/// burst of them.
+fn play_sound_effects(
+    mut commands: Commands,
+    effects: Option<Res<SoundEffects>>,
+    settings: Res<Settings>,
+    mut moves: EventReader<PieceMoved>,
+    mut locks: EventReader<PieceLocked>,
+    mut clears: EventReader<LineClear>,
+    mut level_ups: EventReader<LevelUp>,
+    mut game_overs: EventReader<GameOver>,
+) {
+    let mut sounds = HashSet::new();
+    sounds.extend(moves.read().map(|event| match event {
+        PieceMoved::Shift => Sfx::Shift,
+        PieceMoved::Rotate => Sfx::Rotate,
+        PieceMoved::SoftDrop => Sfx::SoftDrop,
+        PieceMoved::HardDrop { .. } => Sfx::HardDrop,
+        PieceMoved::Hold => Sfx::Hold,
+    }));
+    sounds.extend(locks.read().map(|_| Sfx::Lock));
+    sounds.extend(clears.read().map(|clear| match (clear.spin, clear.lines) {
+        _ if clear.perfect_clear => Sfx::PerfectClear,
+        (Spin::Mini | Spin::Full, 0) => Sfx::Spin,
+        (Spin::Mini | Spin::Full, _) => Sfx::SpinClear,
+        (Spin::None, 1) => Sfx::Single,
+        (Spin::None, 2) => Sfx::Double,
+        (Spin::None, 3) => Sfx::Triple,
+        (Spin::None, _) => Sfx::Tetris,
+    }));
+    sounds.extend(level_ups.read().map(|_| Sfx::LevelUp));
+    sounds.extend(game_overs.read().map(|GameOver(result)| {
+        if result.completed {
+            Sfx::Victory
+        } else {
+            Sfx::TopOut
+        }
+    }));
+
+    let volume = effects_volume(&settings);
+    let Some(effects) = effects.filter(|_| volume > 0.0) else {
+        return;
+    };
+    for sfx in sounds {
+        commands.spawn(AudioSourceBundle {
+            source: effects.0[&sfx].clone(),
+            settings: PlaybackSettings::DESPAWN.with_volume(Volume::new(volume)),
+        });
+    }
+}
+
+fn start_music(mut commands: Commands, track: Res<MusicTrack>, settings: Res<Settings>) {
+    commands.spawn((
+        Name::new("Music"),
+        MusicPlayer,
+        StateScoped(InGame),
+        AudioSourceBundle {
+            source: track.0.clone(),
+            settings: PlaybackSettings::LOOP.with_volume(Volume::new(music_volume(&settings))),
+        },
+    ));
+}
+
+fn pause_music(music: Query<&AudioSink, With<MusicPlayer>>) {
+    for sink in &music {
+        sink.pause();
+    }
+}
+
+fn resume_music(music: Query<&AudioSink, With<MusicPlayer>>) {
+    for sink in &music {
+        sink.play();
+    }
+}
+
+/// Speed the music up with the level, and add instruments as the level goes up or the stack gets
+/// dangerously high.
+fn update_music(
+    track: Res<MusicTrack>,
+    musics: Res<Assets<Music>>,
+    score: Option<Res<Score>>,
+    state: Res<GameState>,
+) {
+    let (Some(music), Some(score)) = (musics.get(&track.0), score) else {
+        return;
+    };
+    let height = state.matrix.stack_height();
+    let by_level = 1 + (score.level().saturating_sub(1) / LEVELS_PER_INTENSITY) as u32;
+    let by_height = 1 + DANGER_HEIGHTS.iter().filter(|h| height >= **h).count() as u32;
+    music.params.set_level(score.level());
+    music
+        .params
+        .set_intensity(by_level.max(by_height).min(MAX_INTENSITY));
+}
+
+fn update_music_volume(settings: Res<Settings>, music: Query<&AudioSink, With<MusicPlayer>>) {
+    for sink in &music {
+        sink.set_volume(music_volume(&settings));
+    }
+}
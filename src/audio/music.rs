@@ -0,0 +1,194 @@
+//! The music, synthesized as it plays so that it can speed up and build up as the game gets more
+//! intense: the tempo follows the level, and more instruments join in as the level goes up or the
+//! stack gets high.
+
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
+
+use bevy::{
+    audio::{Decodable, Source},
+    prelude::*,
+};
+
+use super::synth::{note, pluck, Noise, Wave, SAMPLE_RATE};
+
+/// Tempo at level 1, in beats per minute.
+const BASE_TEMPO: u32 = 110;
+/// How much faster the music gets with every level.
+const TEMPO_PER_LEVEL: u32 = 5;
+const MAX_TEMPO: u32 = 180;
+/// The highest intensity: bass, arpeggio, drums, then lead.
+pub const MAX_INTENSITY: u32 = 3;
+
+/// A chord of the progression, as a root note and the intervals above it.
+struct Chord {
+    root: u8,
+    intervals: [u8; 3],
+}
+
+const MINOR: [u8; 3] = [0, 3, 7];
+const MAJOR: [u8; 3] = [0, 4, 7];
+/// i - VI - III - VII in A minor, one bar each.
+const PROGRESSION: [Chord; 4] = [
+    Chord {
+        root: 45,
+        intervals: MINOR,
+    },
+    Chord {
+        root: 41,
+        intervals: MAJOR,
+    },
+    Chord {
+        root: 48,
+        intervals: MAJOR,
+    },
+    Chord {
+        root: 43,
+        intervals: MAJOR,
+    },
+];
+const BEATS_PER_CHORD: f64 = 4.0;
+
+/// How the music should sound, shared with the audio thread playing it.
+#[derive(Debug)]
+pub struct MusicParams {
+    tempo: AtomicU32,
+    intensity: AtomicU32,
+}
+
+impl Default for MusicParams {
+    fn default() -> Self {
+        Self {
+            tempo: AtomicU32::new(BASE_TEMPO),
+            intensity: AtomicU32::new(0),
+        }
+    }
+}
+
+impl MusicParams {
+    pub fn set_level(&self, level: u64) {
+        let tempo = BASE_TEMPO as u64 + TEMPO_PER_LEVEL as u64 * level.saturating_sub(1);
+        self.tempo
+            .store(tempo.min(MAX_TEMPO as u64) as u32, Ordering::Relaxed);
+    }
+
+    pub fn set_intensity(&self, intensity: u32) {
+        self.intensity
+            .store(intensity.min(MAX_INTENSITY), Ordering::Relaxed);
+    }
+}
+
+/// The game music. Every time it is played, it starts from the top.
+#[derive(Asset, TypePath, Debug, Clone, Default)]
+pub struct Music {
+    pub params: Arc<MusicParams>,
+}
+
+impl Decodable for Music {
+    type DecoderItem = f32;
+    type Decoder = MusicDecoder;
+
+    fn decoder(&self) -> Self::Decoder {
+        MusicDecoder {
+            params: self.params.clone(),
+            time: 0.0,
+            beat: 0.0,
+            noise: Noise::default(),
+        }
+    }
+}
+
+pub struct MusicDecoder {
+    params: Arc<MusicParams>,
+    /// Time since the music started, in seconds.
+    time: f64,
+    /// Position in the music, in beats. This doesn't follow the time, as the tempo changes.
+    beat: f64,
+    noise: Noise,
+}
+
+impl MusicDecoder {
+    /// The phase of a continuous wave at the given frequency, in cycles. This is computed in double
+    /// precision, which a single precision time would lose track of after a few minutes.
+    fn phase(&self, frequency: f32) -> f32 {
+        (frequency as f64 * self.time).fract() as f32
+    }
+
+    /// Seconds since the start of the current note, for notes lasting the given number of beats.
+    fn since_note(&self, beats: f64, tempo: f64) -> f32 {
+        ((self.beat % beats) * 60.0 / tempo) as f32
+    }
+}
+
+impl Iterator for MusicDecoder {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let tempo = self.params.tempo.load(Ordering::Relaxed) as f64;
+        let intensity = self.params.intensity.load(Ordering::Relaxed);
+        self.time += 1.0 / SAMPLE_RATE as f64;
+        self.beat += tempo / 60.0 / SAMPLE_RATE as f64;
+
+        let loop_beats = BEATS_PER_CHORD * PROGRESSION.len() as f64;
+        let chord = &PROGRESSION[((self.beat % loop_beats) / BEATS_PER_CHORD) as usize];
+        let mut sample = 0.0;
+
+        // Bass: the root of the chord, on every eighth note
+        let bass = note(chord.root);
+        let t = self.since_note(0.5, tempo);
+        sample += 0.35 * Wave::Triangle.sample(self.phase(bass), &mut self.noise) * pluck(t, 8.0);
+
+        // Arpeggio: the notes of the chord, two octaves up, on every sixteenth note
+        if intensity >= 1 {
+            let step = (self.beat * 4.0) as usize % 4;
+            let pitch = match step {
+                3 => chord.root + 36,
+                step => chord.root + 24 + chord.intervals[step],
+            };
+            let t = self.since_note(0.25, tempo);
+            sample += 0.08
+                * Wave::Square.sample(self.phase(note(pitch)), &mut self.noise)
+                * pluck(t, 20.0);
+        }
+
+        // Drums: a kick on every beat, and a hi-hat in between
+        if intensity >= 2 {
+            let t = self.since_note(1.0, tempo);
+            let kick = 50.0 + 100.0 * (-t * 30.0).exp();
+            sample += 0.5 * (kick * t * std::f32::consts::TAU).sin() * pluck(t, 18.0);
+            if self.beat.fract() >= 0.5 {
+                let t = self.since_note(0.5, tempo);
+                sample += 0.06 * self.noise.next() * pluck(t, 60.0);
+            }
+        }
+
+        // Lead: the fifth of the chord, held over the whole bar
+        if intensity >= 3 {
+            let lead = note(chord.root + 24 + chord.intervals[2]);
+            let t = self.since_note(BEATS_PER_CHORD, tempo);
+            sample += 0.06 * Wave::Saw.sample(self.phase(lead), &mut self.noise) * pluck(t, 0.8);
+        }
+
+        Some(sample * 0.6)
+    }
+}
+
+impl Source for MusicDecoder {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<std::time::Duration> {
+        None
+    }
+}
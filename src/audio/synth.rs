@@ -0,0 +1,184 @@
+//! A tiny synthesizer: sounds are built from simple waveforms, so that the game needs no audio
+//! files.
+
+use std::{f32::consts::TAU, sync::Arc, time::Duration};
+
+use bevy::{
+    audio::{Decodable, Source},
+    prelude::*,
+};
+
+pub const SAMPLE_RATE: u32 = 44_100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wave {
+    Sine,
+    Triangle,
+    Square,
+    Saw,
+    Noise,
+}
+
+impl Wave {
+    /// The value of the waveform at the given phase (in cycles), between -1 and 1.
+    pub fn sample(self, phase: f32, noise: &mut Noise) -> f32 {
+        let phase = phase.fract();
+        match self {
+            Wave::Sine => (phase * TAU).sin(),
+            Wave::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+            Wave::Square if phase < 0.5 => 1.0,
+            Wave::Square => -1.0,
+            Wave::Saw => 2.0 * phase - 1.0,
+            Wave::Noise => noise.next(),
+        }
+    }
+}
+
+/// White noise from a xorshift generator, cheap enough to run for every sample.
+#[derive(Debug, Clone)]
+pub struct Noise(u32);
+
+impl Default for Noise {
+    fn default() -> Self {
+        Self(0x9e37_79b9)
+    }
+}
+
+impl Noise {
+    pub fn next(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0 as f32 / u32::MAX as f32 * 2.0 - 1.0
+    }
+}
+
+/// The frequency of a MIDI note number, with A4 (69) at 440 Hz.
+pub fn note(midi: u8) -> f32 {
+    440.0 * 2f32.powf((midi as f32 - 69.0) / 12.0)
+}
+
+/// A short attack followed by an exponential decay, for a note that started `t` seconds ago.
+pub fn pluck(t: f32, decay: f32) -> f32 {
+    (t * 500.0).min(1.0) * (-t * decay).exp()
+}
+
+/// A note of a sound effect: a waveform whose pitch slides linearly over its duration, fading out
+/// by the end.
+#[derive(Debug, Clone, Copy)]
+pub struct Tone {
+    wave: Wave,
+    from: f32,
+    to: f32,
+    /// When the note starts, in seconds.
+    start: f32,
+    duration: f32,
+    volume: f32,
+}
+
+impl Tone {
+    pub fn new(wave: Wave, frequency: f32, duration: f32) -> Self {
+        Self {
+            wave,
+            from: frequency,
+            to: frequency,
+            start: 0.0,
+            duration,
+            volume: 0.3,
+        }
+    }
+
+    /// Slide the pitch to the given frequency.
+    pub fn slide(self, to: f32) -> Self {
+        Self { to, ..self }
+    }
+
+    pub fn at(self, start: f32) -> Self {
+        Self { start, ..self }
+    }
+
+    pub fn volume(self, volume: f32) -> Self {
+        Self { volume, ..self }
+    }
+
+    fn end(&self) -> f32 {
+        self.start + self.duration
+    }
+}
+
+/// Mix the given notes into a sound.
+pub fn render(tones: &[Tone]) -> Sound {
+    let duration = tones.iter().map(Tone::end).fold(0.0, f32::max);
+    let mut samples = vec![0.0; (duration * SAMPLE_RATE as f32).ceil() as usize];
+    let mut noise = Noise::default();
+    for tone in tones {
+        let first = (tone.start * SAMPLE_RATE as f32) as usize;
+        let mut phase = 0.0;
+        for (i, sample) in samples[first..].iter_mut().enumerate() {
+            let t = i as f32 / SAMPLE_RATE as f32;
+            if t >= tone.duration {
+                break;
+            }
+            let progress = t / tone.duration;
+            phase += (tone.from + (tone.to - tone.from) * progress) / SAMPLE_RATE as f32;
+            let envelope = (t * 500.0).min(1.0) * (1.0 - progress).powi(2);
+            *sample += tone.wave.sample(phase, &mut noise) * envelope * tone.volume;
+        }
+    }
+    Sound {
+        samples: samples.into(),
+    }
+}
+
+/// A synthesized sound, played as is.
+#[derive(Asset, TypePath, Debug, Clone)]
+pub struct Sound {
+    samples: Arc<[f32]>,
+}
+
+impl Decodable for Sound {
+    type DecoderItem = f32;
+    type Decoder = SoundDecoder;
+
+    fn decoder(&self) -> Self::Decoder {
+        SoundDecoder {
+            samples: self.samples.clone(),
+            position: 0,
+        }
+    }
+}
+
+pub struct SoundDecoder {
+    samples: Arc<[f32]>,
+    position: usize,
+}
+
+impl Iterator for SoundDecoder {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.samples.get(self.position).copied();
+        self.position += 1;
+        sample
+    }
+}
+
+impl Source for SoundDecoder {
+    fn current_frame_len(&self) -> Option<usize> {
+        Some(self.samples.len().saturating_sub(self.position))
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        Some(Duration::from_secs_f32(
+            self.samples.len() as f32 / SAMPLE_RATE as f32,
+        ))
+    }
+}
@@ -0,0 +1,249 @@
+//! Plays many games without a window, as fast as possible, and reports how they went on average.
+//!
+//! ```text
+//! simulate [--seeds <start>..<end>] [--threads <n>] [--max-ticks <n>] [--output <file>]
+//!          [--mode <mode>] [--randomizer <name>] [--bot <command>] [--ai-delay <ticks>]
+//! ```
+//!
+//! One game is played for each seed of the range, with the built-in AI unless `--bot` is given. The
+//! summary is printed, and written to the output file if there is one: as a single CSV row if its
+//! extension is `.csv`, or as JSON along with the outcome of every game otherwise.
+
+use std::{
+    fs,
+    ops::Range,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+use betris::{
+    args::Args,
+    headless::{self, Outcome},
+};
+use serde::Serialize;
+
+/// Games still going after an hour are cut short.
+const DEFAULT_MAX_TICKS: u64 = 60 * 60 * 60;
+
+fn main() {
+    let arg = |name: &str| std::env::args().skip_while(|arg| arg != name).nth(1);
+    if std::env::args().any(|arg| arg == "--help") {
+        println!("{}", USAGE);
+        return;
+    }
+    let seeds = match arg("--seeds").map(|seeds| parse_range(&seeds)) {
+        Some(Some(seeds)) => seeds,
+        Some(None) => fail("--seeds expects a range like 0..1000"),
+        None => 0..100,
+    };
+    let threads = match arg("--threads").map(|threads| threads.parse()) {
+        Some(Ok(threads)) if threads > 0 => threads,
+        Some(_) => fail("--threads expects a positive number"),
+        None => thread::available_parallelism().map_or(1, |n| n.get()),
+    };
+    let max_ticks = match arg("--max-ticks").map(|ticks| ticks.parse()) {
+        Some(Ok(ticks)) => ticks,
+        Some(Err(_)) => fail("--max-ticks expects a number of ticks"),
+        None => DEFAULT_MAX_TICKS,
+    };
+
+    let outcomes = simulate(seeds, threads, max_ticks, &Args::parse());
+    let summary = Summary::new(&outcomes);
+    println!("{summary}");
+
+    let Some(path) = arg("--output") else {
+        return;
+    };
+    let contents = if path.ends_with(".csv") {
+        summary.to_csv()
+    } else {
+        let report = Report {
+            summary: &summary,
+            games: &outcomes,
+        };
+        serde_json::to_string_pretty(&report).expect("reports can always be serialized")
+    };
+    if let Err(e) = fs::write(&path, contents) {
+        fail(&format!("Failed to write {path}: {e}"));
+    }
+}
+
+const USAGE: &str = "\
+Usage: simulate [options]
+
+  --seeds <start>..<end>  Seeds of the games to play (default: 0..100)
+  --threads <n>           Number of games played at the same time (default: one per CPU)
+  --max-ticks <n>         Cut games short after this many ticks (default: one hour)
+  --output <file>         Write the results as CSV (.csv) or JSON (anything else)
+  --mode <mode>           Mode of the games: marathon, sprint or ultra (default: marathon)
+  --randomizer <name>     seven-bag, fourteen-bag, random or classic (default: seven-bag)
+  --bot <command>         Play with a bot speaking the Tetris Bot Protocol instead of the AI
+  --ai-delay <ticks>      Ticks to wait between key presses (default: 4)";
+
+fn fail(message: &str) -> ! {
+    eprintln!("{message}\n\n{USAGE}");
+    std::process::exit(1);
+}
+
+fn parse_range(range: &str) -> Option<Range<u64>> {
+    let (start, end) = range.split_once("..")?;
+    Some(start.parse().ok()?..end.parse().ok()?)
+}
+
+/// Play a game for every seed, spread over the given number of threads.
+fn simulate(seeds: Range<u64>, threads: usize, max_ticks: u64, args: &Args) -> Vec<Outcome> {
+    let total = seeds.end.saturating_sub(seeds.start);
+    let next = AtomicU64::new(seeds.start);
+    let outcomes = Mutex::new(Vec::with_capacity(total as usize));
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| loop {
+                let seed = next.fetch_add(1, Ordering::Relaxed);
+                if seed >= seeds.end {
+                    break;
+                }
+                let outcome = headless::play(seed, max_ticks, args);
+                let mut outcomes = outcomes.lock().unwrap();
+                outcomes.push(outcome);
+                eprint!("\r{}/{total} games played", outcomes.len());
+            });
+        }
+    });
+    eprintln!();
+
+    let mut outcomes = outcomes.into_inner().unwrap();
+    outcomes.sort_by_key(|outcome| outcome.seed);
+    outcomes
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    summary: &'a Summary,
+    games: &'a [Outcome],
+}
+
+#[derive(Serialize, Debug)]
+struct Summary {
+    games: usize,
+    completed: usize,
+    topped_out: usize,
+    /// Games that were cut short, having neither completed nor topped out.
+    cut_short: usize,
+    top_out_rate: f64,
+    lines: Stats,
+    score: Stats,
+    pieces_per_second: Stats,
+}
+
+impl Summary {
+    fn new(outcomes: &[Outcome]) -> Self {
+        let count = |f: fn(&Outcome) -> bool| outcomes.iter().filter(|outcome| f(outcome)).count();
+        let games = outcomes.len();
+        let completed = count(|outcome| outcome.completed);
+        let topped_out = count(|outcome| outcome.topped_out);
+        Self {
+            games,
+            completed,
+            topped_out,
+            cut_short: games - completed - topped_out,
+            top_out_rate: if games == 0 {
+                0.0
+            } else {
+                topped_out as f64 / games as f64
+            },
+            lines: Stats::new(outcomes.iter().map(|outcome| outcome.lines as f64)),
+            score: Stats::new(outcomes.iter().map(|outcome| outcome.score as f64)),
+            pieces_per_second: Stats::new(outcomes.iter().map(Outcome::pieces_per_second)),
+        }
+    }
+
+    fn to_csv(&self) -> String {
+        let mut header: Vec<String> = [
+            "games",
+            "completed",
+            "topped_out",
+            "cut_short",
+            "top_out_rate",
+        ]
+        .map(String::from)
+        .to_vec();
+        let mut row = vec![
+            self.games.to_string(),
+            self.completed.to_string(),
+            self.topped_out.to_string(),
+            self.cut_short.to_string(),
+            self.top_out_rate.to_string(),
+        ];
+        for (name, stats) in [
+            ("lines", &self.lines),
+            ("score", &self.score),
+            ("pieces_per_second", &self.pieces_per_second),
+        ] {
+            for (stat, value) in [("mean", stats.mean), ("min", stats.min), ("max", stats.max)] {
+                header.push(format!("{name}_{stat}"));
+                row.push(value.to_string());
+            }
+        }
+        format!("{}\n{}\n", header.join(","), row.join(","))
+    }
+}
+
+impl std::fmt::Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{} games: {} completed, {} topped out ({:.1}%), {} cut short",
+            self.games,
+            self.completed,
+            self.topped_out,
+            self.top_out_rate * 100.0,
+            self.cut_short
+        )?;
+        writeln!(f, "Lines: {}", self.lines)?;
+        writeln!(f, "Score: {}", self.score)?;
+        write!(f, "Pieces per second: {}", self.pieces_per_second)
+    }
+}
+
+/// Mean, minimum and maximum of a set of values.
+#[derive(Serialize, Debug, Default)]
+struct Stats {
+    mean: f64,
+    min: f64,
+    max: f64,
+}
+
+impl Stats {
+    fn new(values: impl Iterator<Item = f64>) -> Self {
+        let mut count = 0;
+        let mut stats = Stats {
+            mean: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        };
+        for value in values {
+            count += 1;
+            stats.mean += value;
+            stats.min = stats.min.min(value);
+            stats.max = stats.max.max(value);
+        }
+        if count == 0 {
+            return Stats::default();
+        }
+        stats.mean /= count as f64;
+        stats
+    }
+}
+
+impl std::fmt::Display for Stats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:.2} on average ({:.2} to {:.2})",
+            self.mean, self.min, self.max
+        )
+    }
+}
@@ -0,0 +1,211 @@
+//! Callouts: the names of tetrises, spins, chains and perfect clears, popping up over the matrix as
+//! they are scored.
+//!
+//! Only one callout is shown at a time. Those scored together, or while another one is still
+//! shown, wait their turn in a queue.
+
+use std::{collections::VecDeque, time::Duration};
+
+use bevy::{color::palettes, prelude::*};
+use bevy_tween::{
+    bevy_time_runner::TimeRunnerEnded,
+    combinator::{sequence, tween},
+    prelude::*,
+};
+
+use crate::{
+    game::{
+        clear::{LineClear, Spin},
+        GameState, MATRIX_WIDTH,
+    },
+    model::TetriminoKind,
+    screen::InGame,
+    skin::{CurrentSkin, Skin},
+    AppSet,
+};
+
+/// Size the text of callouts is rendered at, in pixels.
+const FONT_SIZE: f32 = 64.0;
+/// Height of the text of callouts, in cells.
+const HEIGHT: f32 = 1.5;
+/// The row callouts show up at.
+const ROW: f32 = 14.0;
+/// How far callouts rise while shown, in cells.
+const RISE: f32 = 1.0;
+const POP_IN: Duration = Duration::from_millis(150);
+const HOLD: Duration = Duration::from_millis(450);
+const POP_OUT: Duration = Duration::from_millis(150);
+/// Number of callouts that can wait their turn. Older ones are dropped to keep up with the game.
+const MAX_QUEUED: usize = 4;
+
+pub fn plugin(app: &mut App) {
+    app.init_resource::<CalloutQueue>()
+        .add_systems(
+            Update,
+            (queue_callouts, remove_finished, show_next)
+                .chain()
+                .run_if(in_state(InGame))
+                .in_set(AppSet::Update),
+        )
+        .add_systems(OnExit(InGame), clear_queue);
+}
+
+/// A callout waiting to be shown.
+#[derive(Debug, Clone)]
+struct Pending {
+    text: String,
+    color: Color,
+}
+
+#[derive(Resource, Debug, Default)]
+struct CalloutQueue {
+    pending: VecDeque<Pending>,
+    /// The callout being shown, if any.
+    shown: Option<Entity>,
+}
+
+#[derive(Component)]
+struct Callout;
+
+/// The callouts for a line clear, from the most to the least important.
+fn callouts(clear: &LineClear, skin: &Skin) -> Vec<Pending> {
+    let mut callouts = Vec::new();
+    let name = match (clear.spin, clear.lines) {
+        (Spin::None, 4) => Some("TETRIS"),
+        (Spin::None, _) => None,
+        (Spin::Mini, 0) => Some("MINI T-SPIN"),
+        (Spin::Mini, 1) => Some("MINI T-SPIN SINGLE"),
+        (Spin::Mini, _) => Some("MINI T-SPIN DOUBLE"),
+        (Spin::Full, 0) => Some("T-SPIN"),
+        (Spin::Full, 1) => Some("T-SPIN SINGLE"),
+        (Spin::Full, 2) => Some("T-SPIN DOUBLE"),
+        (Spin::Full, _) => Some("T-SPIN TRIPLE"),
+    };
+    if let Some(name) = name {
+        let kind = if clear.spin == Spin::None {
+            TetriminoKind::I
+        } else {
+            TetriminoKind::T
+        };
+        callouts.push(Pending {
+            text: name.to_string(),
+            color: skin.color(kind),
+        });
+    }
+    if clear.back_to_back > 0 {
+        let text = match clear.back_to_back {
+            1 => "B2B".to_string(),
+            chain => format!("B2B x{chain}"),
+        };
+        callouts.push(Pending {
+            text,
+            color: palettes::css::GOLD.into(),
+        });
+    }
+    if clear.combo > 0 {
+        callouts.push(Pending {
+            text: format!("{} COMBO", clear.combo),
+            color: Color::WHITE,
+        });
+    }
+    if clear.perfect_clear {
+        callouts.push(Pending {
+            text: "ALL CLEAR".to_string(),
+            color: palettes::css::AQUA.into(),
+        });
+    }
+    callouts
+}
+
+fn queue_callouts(
+    skin: Res<CurrentSkin>,
+    mut queue: ResMut<CalloutQueue>,
+    mut clears: EventReader<LineClear>,
+) {
+    for clear in clears.read() {
+        queue.pending.extend(callouts(clear, &skin));
+    }
+    let excess = queue.pending.len().saturating_sub(MAX_QUEUED);
+    queue.pending.drain(..excess);
+}
+
+fn remove_finished(
+    mut commands: Commands,
+    mut queue: ResMut<CalloutQueue>,
+    mut ended: EventReader<TimeRunnerEnded>,
+) {
+    for event in ended.read() {
+        if event.is_completed() && queue.shown == Some(event.time_runner) {
+            commands.entity(event.time_runner).despawn_recursive();
+            queue.shown = None;
+        }
+    }
+}
+
+/// Pop the next callout up over the matrix, once the previous one is gone.
+fn show_next(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    state: Res<GameState>,
+    mut queue: ResMut<CalloutQueue>,
+    callouts: Query<(), With<Callout>>,
+) {
+    // The callout may also have gone with the matrix, if the game was restarted
+    if queue
+        .shown
+        .is_some_and(|callout| callouts.contains(callout))
+    {
+        return;
+    }
+    queue.shown = None;
+    if commands.get_entity(state.matrix.root_entity).is_none() {
+        return;
+    }
+    let Some(next) = queue.pending.pop_front() else {
+        return;
+    };
+
+    // The text is laid out in pixels, and scaled down to cells like the rest of the matrix
+    let scale = Vec3::new(HEIGHT / FONT_SIZE, HEIGHT / FONT_SIZE, 1.0);
+    let start = Transform::from_xyz(MATRIX_WIDTH as f32 / 2.0, ROW, 5.0).with_scale(scale * 0.5);
+    let callout = commands
+        .spawn((
+            Name::new("Callout"),
+            Callout,
+            StateScoped(InGame),
+            Text2dBundle {
+                text: Text::from_section(
+                    next.text,
+                    TextStyle {
+                        font: assets.load("fonts/BungeeSpice-Regular.ttf"),
+                        font_size: FONT_SIZE,
+                        color: next.color,
+                    },
+                ),
+                transform: start,
+                ..default()
+            },
+        ))
+        .set_parent(state.matrix.root_entity)
+        .id();
+
+    let mut transform = callout.into_target().transform_state(start);
+    commands.entity(callout).animation().insert(sequence((
+        tween(POP_IN, EaseFunction::BackOut, transform.scale_to(scale)),
+        tween(
+            HOLD,
+            EaseFunction::QuadraticOut,
+            transform.translation_to(start.translation + Vec3::Y * RISE),
+        ),
+        tween(
+            POP_OUT,
+            EaseFunction::QuadraticIn,
+            transform.scale_to(Vec3::new(0.0, 0.0, 1.0)),
+        ),
+    )));
+    queue.shown = Some(callout);
+}
+
+fn clear_queue(mut queue: ResMut<CalloutQueue>) {
+    *queue = CalloutQueue::default();
+}
@@ -0,0 +1,425 @@
+//! A CPU player, pressing the same keys a human would.
+//!
+//! `--ai` starts games with the built-in AI playing, and `--bot <command>` with an external bot
+//! speaking the Tetris Bot Protocol. `--ai-delay <ticks>` sets how many ticks they wait between two
+//! key presses. F4 hands the game over to the AI, or takes it back.
+
+use std::collections::VecDeque;
+
+use bevy::{input::common_conditions::input_just_pressed, prelude::*};
+
+use crate::{
+    args::Args,
+    model::{
+        ai::{Decision, Weights},
+        board::Board,
+        finesse::Move,
+        movegen::{self, Placement},
+        Pos, Tetrimino,
+    },
+    screen::{InGame, Pause, Screen},
+    AppSet, SimulationSet,
+};
+
+use super::{
+    input::{Action, InputFrame, PlayerInput},
+    spawners::{piece::CurrentPiece, Positioned, INITIAL_POS},
+    tbp::TbpBot,
+    GameConfig, GameState, Phase,
+};
+
+pub fn plugin(app: &mut App) {
+    app.add_systems(
+        OnEnter(InGame),
+        new_game.run_if(resource_exists::<AiPlayer>),
+    )
+    .add_systems(
+        FixedUpdate,
+        play.run_if(in_state(Screen::Gameplay).and_then(resource_exists::<AiPlayer>))
+            .in_set(SimulationSet::RecordInput),
+    )
+    .add_systems(
+        Update,
+        toggle_ai
+            .run_if(in_state(Pause::Running).and_then(input_just_pressed(KeyCode::F4)))
+            .in_set(AppSet::RecordInput),
+    );
+
+    let args = app.world().resource::<Args>();
+    let brain = match &args.bot {
+        Some(command) => match TbpBot::spawn(command) {
+            Ok(bot) => Some(Brain::Tbp(bot)),
+            Err(e) => {
+                error!("Failed to start bot {command}: {e}");
+                Some(Brain::default())
+            }
+        },
+        None if args.ai => Some(Brain::default()),
+        None => None,
+    };
+    if let Some(brain) = brain {
+        let ai = AiPlayer::with_brain(brain, args.ai_delay);
+        app.insert_resource(ai);
+    }
+}
+
+/// Makes the AI play instead of the player, as long as it exists.
+#[derive(Resource, Debug)]
+pub struct AiPlayer {
+    pub brain: Brain,
+    /// Number of ticks to wait after each key press. Keys are always released for at least one
+    /// tick, so that pressing the same key again registers.
+    pub delay: u32,
+    /// The keys left to press for the current piece.
+    plan: Option<Plan>,
+    /// Where to put the piece coming in after holding.
+    target: Option<Placement>,
+    cooldown: u32,
+}
+
+impl Default for AiPlayer {
+    fn default() -> Self {
+        Self {
+            brain: Brain::default(),
+            delay: 4,
+            plan: None,
+            target: None,
+            cooldown: 0,
+        }
+    }
+}
+
+impl AiPlayer {
+    /// An AI player using the given brain, and the given delay between key presses if any (see
+    /// `--ai-delay`).
+    pub fn with_brain(brain: Brain, delay: Option<u32>) -> Self {
+        let mut ai = AiPlayer { brain, ..default() };
+        if let Some(delay) = delay {
+            ai.delay = delay;
+        }
+        ai
+    }
+}
+
+/// What decides where pieces go.
+#[derive(Debug)]
+pub enum Brain {
+    Heuristic(Weights),
+    Tbp(TbpBot),
+}
+
+impl Default for Brain {
+    fn default() -> Self {
+        Brain::Heuristic(Weights::default())
+    }
+}
+
+#[derive(Debug)]
+struct Plan {
+    piece: Entity,
+    /// Where the piece is headed, if anywhere.
+    target: Option<Placement>,
+    /// Where the piece should be for the next key to take it towards the target.
+    expected: (Tetrimino, Pos),
+    keys: VecDeque<Action>,
+}
+
+impl Plan {
+    /// Plan the keys to press to carry out a decision. When holding, only the hold key is pressed,
+    /// and the piece coming in is then moved to the placement.
+    fn new(
+        ai: &mut AiPlayer,
+        board: &Board,
+        piece: Entity,
+        decision: Option<Decision>,
+        tetrimino: Tetrimino,
+        pos: Pos,
+    ) -> Self {
+        let mut plan = Self {
+            piece,
+            target: None,
+            expected: (tetrimino, pos),
+            keys: VecDeque::new(),
+        };
+        match decision {
+            // Nowhere to go: let the piece fall
+            None => {}
+            Some(decision) if decision.hold => {
+                ai.target = Some(decision.placement);
+                plan.keys.push_back(Action::Hold);
+            }
+            Some(decision) => {
+                plan.target = Some(decision.placement);
+                plan.find_keys(board, tetrimino, pos);
+            }
+        }
+        plan
+    }
+
+    /// Find the keys getting the piece from where it is to the target.
+    fn find_keys(&mut self, board: &Board, tetrimino: Tetrimino, pos: Pos) {
+        self.expected = (tetrimino, pos);
+        let Some(target) = &self.target else {
+            return;
+        };
+        self.keys = match movegen::find_placement(
+            board,
+            tetrimino,
+            pos,
+            (&target.tetrimino, &target.pos),
+        ) {
+            Some(placement) => placement
+                .moves
+                .into_iter()
+                .map(Action::from)
+                .chain([Action::HardDrop])
+                .collect(),
+            None => {
+                warn!("Can't get the piece to {:?}", target.pos);
+                VecDeque::from([Action::HardDrop])
+            }
+        };
+    }
+
+    /// Take the next key to press, finding new ones first if the piece is not where the plan
+    /// expects, like when it fell while being moved.
+    fn next_key(&mut self, board: &Board, tetrimino: Tetrimino, pos: Pos) -> Option<Action> {
+        if self.expected != (tetrimino, pos) {
+            self.find_keys(board, tetrimino, pos);
+        }
+        let key = self.keys.pop_front()?;
+        let step = match key {
+            Action::Left => Some(Move::Left),
+            Action::Right => Some(Move::Right),
+            Action::RotateRight => Some(Move::RotateCw),
+            Action::RotateLeft => Some(Move::RotateCcw),
+            _ => None,
+        };
+        if let Some(moved) = step.and_then(|step| board.apply(step, tetrimino, pos)) {
+            self.expected = moved;
+        }
+        Some(key)
+    }
+}
+
+fn toggle_ai(mut commands: Commands, ai: Option<Res<AiPlayer>>) {
+    if ai.is_some() {
+        info!("Taking back control from the AI");
+        commands.remove_resource::<AiPlayer>();
+    } else {
+        info!("Handing control over to the AI");
+        commands.init_resource::<AiPlayer>();
+    }
+}
+
+fn new_game(mut ai: ResMut<AiPlayer>, config: Res<GameConfig>) {
+    ai.plan = None;
+    ai.target = None;
+    ai.cooldown = 0;
+    if let Brain::Tbp(bot) = &mut ai.brain {
+        if let Err(e) = bot.new_game(config.randomizer) {
+            error!("Bot failed: {e}. Falling back to the built-in AI");
+            ai.brain = Brain::default();
+        }
+    }
+}
+
+/// Press the next key of the plan for the current piece, making one first if needed.
+fn play(
+    mut ai: ResMut<AiPlayer>,
+    state: Res<GameState>,
+    phase: Option<Res<State<Phase>>>,
+    piece: Query<(Entity, &Tetrimino, &Positioned), With<CurrentPiece>>,
+    mut input: ResMut<PlayerInput>,
+) {
+    let mut frame = InputFrame::default();
+    let falling = phase.is_some_and(|phase| *phase.get() == Phase::Falling);
+    if ai.cooldown > 0 {
+        ai.cooldown -= 1;
+    } else if let (true, Ok((entity, tetrimino, pos))) = (falling, piece.get_single()) {
+        let board = state.matrix.to_board();
+        if ai.plan.as_ref().map(|plan| plan.piece) != Some(entity) {
+            let thought = match ai.target.take() {
+                Some(placement) => Thought::Done(Some(Decision {
+                    hold: false,
+                    placement,
+                })),
+                None => think(&mut ai, &board, &state, *tetrimino, **pos),
+            };
+            if let Thought::Done(decision) = thought {
+                let plan = Plan::new(&mut ai, &board, entity, decision, *tetrimino, **pos);
+                ai.plan = Some(plan);
+            }
+        }
+        if let Some(plan) = &mut ai.plan {
+            if plan.keys.front() == Some(&Action::SoftDrop)
+                && !state.matrix.is_on_surface(tetrimino, pos)
+            {
+                // Keep soft dropping until the piece lands
+                frame.press(Action::SoftDrop);
+            } else if let Some(key) = plan.next_key(&board, *tetrimino, **pos) {
+                if key != Action::SoftDrop {
+                    frame.press(key);
+                    ai.cooldown = ai.delay.max(1);
+                }
+            }
+        }
+    }
+    input.advance(frame);
+}
+
+enum Thought {
+    /// No decision yet: the piece keeps falling while the AI thinks.
+    Pending,
+    /// Where to put the piece, if it can go anywhere.
+    Done(Option<Decision>),
+}
+
+/// Decide what to do with the current piece.
+fn think(
+    ai: &mut AiPlayer,
+    board: &Board,
+    state: &GameState,
+    tetrimino: Tetrimino,
+    pos: Pos,
+) -> Thought {
+    let alternative = state.hold_alternative();
+    let bot = match &mut ai.brain {
+        Brain::Heuristic(weights) => {
+            return Thought::Done(weights.decide(
+                board,
+                (tetrimino, pos),
+                alternative,
+                INITIAL_POS,
+            ));
+        }
+        Brain::Tbp(bot) => bot,
+    };
+
+    let result = bot.suggest(state, tetrimino.kind).and_then(|suggestions| {
+        let Some(suggestions) = suggestions else {
+            return Ok(Thought::Pending);
+        };
+        // Play the bot's favourite move among the ones the piece can get to
+        let suggestion = suggestions.into_iter().find(|suggestion| {
+            let decision = &suggestion.decision;
+            let (start, start_pos) = if decision.hold {
+                match alternative {
+                    Some(kind) if kind == decision.placement.tetrimino.kind => {
+                        (Tetrimino::new(kind), INITIAL_POS)
+                    }
+                    _ => return false,
+                }
+            } else {
+                (tetrimino, pos)
+            };
+            let target = &decision.placement;
+            movegen::find_placement(board, start, start_pos, (&target.tetrimino, &target.pos))
+                .is_some()
+        });
+        match suggestion {
+            Some(suggestion) => {
+                bot.play(&suggestion)?;
+                Ok(Thought::Done(Some(suggestion.decision)))
+            }
+            None => {
+                warn!("None of the bot's suggestions can be played");
+                Ok(Thought::Done(None))
+            }
+        }
+    });
+    result.unwrap_or_else(|e| {
+        error!("Bot failed: {e}. Falling back to the built-in AI");
+        ai.brain = Brain::default();
+        Thought::Pending
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::TetriminoKind;
+
+    use super::*;
+
+    /// The plan for the O piece at the spawn position to go to the given position.
+    fn plan(board: &Board, target: Pos) -> Plan {
+        let tetrimino = Tetrimino::new(TetriminoKind::O);
+        let decision = Decision {
+            hold: false,
+            placement: Placement {
+                tetrimino,
+                pos: target,
+                moves: Vec::new(),
+                rotated_last: false,
+            },
+        };
+        let mut ai = AiPlayer::default();
+        Plan::new(
+            &mut ai,
+            board,
+            Entity::PLACEHOLDER,
+            Some(decision),
+            tetrimino,
+            INITIAL_POS,
+        )
+    }
+
+    /// Press the rest of the plan's keys, with the piece where the plan expects it.
+    fn remaining_keys(plan: &mut Plan, board: &Board) -> Vec<Action> {
+        let mut keys = Vec::new();
+        while let Some(key) = plan.next_key(board, plan.expected.0, plan.expected.1) {
+            keys.push(key);
+        }
+        keys
+    }
+
+    #[test]
+    fn follow_the_plan() {
+        let board = Board::default();
+        let mut plan = plan(&board, Pos::new(1, 0));
+        assert_eq!(
+            remaining_keys(&mut plan, &board),
+            [Action::Left; 4]
+                .into_iter()
+                .chain([Action::HardDrop])
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn replan_when_falling() {
+        let board = Board::default();
+        let mut plan = plan(&board, Pos::new(1, 0));
+        let tetrimino = Tetrimino::new(TetriminoKind::O);
+        assert_eq!(
+            plan.next_key(&board, tetrimino, INITIAL_POS),
+            Some(Action::Left)
+        );
+        // The piece fell while moving, and was moved again
+        let fallen = Pos::new(3, 10);
+        assert_eq!(plan.next_key(&board, tetrimino, fallen), Some(Action::Left));
+        assert_eq!(
+            remaining_keys(&mut plan, &board),
+            [Action::Left, Action::HardDrop]
+        );
+    }
+
+    #[test]
+    fn replan_under_overhang() {
+        // A roof over the bottom left corner, which the piece has to be tucked under
+        let board = Board::from_blocks([Pos::new(0, 2), Pos::new(1, 2), Pos::new(2, 2)]);
+        let mut plan = plan(&board, Pos::new(0, 0));
+        assert!(plan.keys.contains(&Action::SoftDrop));
+
+        // The piece fell next to the roof already, so it only has to be moved under it
+        let tetrimino = Tetrimino::new(TetriminoKind::O);
+        assert_eq!(
+            plan.next_key(&board, tetrimino, Pos::new(3, 0)),
+            Some(Action::Left)
+        );
+        assert_eq!(
+            remaining_keys(&mut plan, &board),
+            [Action::Left, Action::Left, Action::HardDrop]
+        );
+    }
+}
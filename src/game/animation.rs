@@ -0,0 +1,235 @@
+//! Line clear animations: cleared lines disappear with the effect picked in the settings, then the
+//! blocks above them fall into place.
+//!
+//! The animations follow the line clear timer of the simulation rather than their own clock, so
+//! they stay in step with pausing, fast-forwarding and seeking through replays.
+
+use std::f32::consts::TAU;
+
+use bevy::{color::palettes, prelude::*};
+use rand::Rng;
+
+use crate::{
+    settings::{ClearEffect, Settings},
+    AppSet,
+};
+
+use super::{
+    clear::Spin, spawners::Positioned, timers::Timers, Block, GameState, Phase, ToDelete,
+    MATRIX_WIDTH,
+};
+
+/// Part of the line clear delay taken by the effect. The blocks above fall during the rest of it.
+const EFFECT_END: f32 = 0.6;
+/// Number of shards a block bursts into, for normal and stronger clears.
+const SHARDS: [usize; 2] = [4, 9];
+/// How far shards fly, in cells, not counting gravity.
+const SHARD_DISTANCE: f32 = 3.0;
+/// How far shards fall under gravity by the end of the effect, in cells.
+const SHARD_FALL: f32 = 6.0;
+const SHARD_SIZE: f32 = 0.3;
+
+pub fn plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        (start_clear, animate_clear, collapse)
+            .chain()
+            .run_if(in_state(Phase::Animate))
+            .in_set(AppSet::Update),
+    )
+    .add_systems(OnExit(Phase::Animate), remove_shards);
+}
+
+/// A block being cleared, with the color it had before the effect started.
+#[derive(Component)]
+struct Clearing {
+    color: Color,
+}
+
+/// A piece of a shattered block.
+#[derive(Component)]
+struct Shard {
+    origin: Vec3,
+    /// Direction and distance of the flight, in cells.
+    flight: Vec2,
+    /// Rotation by the end of the flight, in radians.
+    spin: f32,
+}
+
+/// How strong the effect is: tetrises and spins get stronger effects, and perfect clears stronger
+/// still.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Strength {
+    Normal,
+    Strong,
+    Perfect,
+}
+
+impl Strength {
+    fn of(state: &GameState) -> Self {
+        let lines = state.matrix.full_lines().len();
+        if state.matrix.iter_non_empty().count() == lines * MATRIX_WIDTH as usize {
+            Strength::Perfect
+        } else if lines == 4 || state.spin != Spin::None {
+            Strength::Strong
+        } else {
+            Strength::Normal
+        }
+    }
+
+    /// The color cleared blocks light up with.
+    fn glow(self) -> Color {
+        match self {
+            Strength::Normal => Color::WHITE,
+            Strength::Strong => palettes::css::GOLD.into(),
+            Strength::Perfect => palettes::css::AQUA.into(),
+        }
+    }
+}
+
+/// Progress of the effect on the cleared lines, and of the fall of the blocks above, from 0 to 1.
+fn progress(timers: &Timers) -> (f32, f32) {
+    let t = timers.line_clear.fraction();
+    let fall = (t - EFFECT_END) / (1.0 - EFFECT_END);
+    ((t / EFFECT_END).min(1.0), fall.clamp(0.0, 1.0))
+}
+
+/// Remember the look of the blocks to clear, and break them into shards if they shatter.
+fn start_clear(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    state: Res<GameState>,
+    blocks: Query<(Entity, &Positioned, &Sprite), (With<ToDelete>, Without<Clearing>)>,
+) {
+    if blocks.is_empty() {
+        return;
+    }
+    let strength = Strength::of(&state);
+    let shatter = settings.visuals.clear_effect == ClearEffect::Shatter;
+    let mut rng = rand::thread_rng();
+    for (entity, pos, sprite) in &blocks {
+        commands.entity(entity).insert(Clearing {
+            color: sprite.color,
+        });
+        if !shatter {
+            continue;
+        }
+        let (count, distance) = match strength {
+            Strength::Normal => (SHARDS[0], SHARD_DISTANCE),
+            _ => (SHARDS[1], SHARD_DISTANCE * 1.5),
+        };
+        // Shards are centered on their position, while blocks are anchored to their bottom left
+        let origin = Transform::from(**pos).translation + Vec3::new(0.5, 0.5, 1.0);
+        commands
+            .entity(state.matrix.root_entity)
+            .with_children(|children| {
+                for _ in 0..count {
+                    let angle = rng.gen_range(0.0..TAU);
+                    children.spawn((
+                        Name::new("Shard"),
+                        Shard {
+                            origin,
+                            flight: Vec2::from_angle(angle) * distance * rng.gen_range(0.3..1.0),
+                            spin: rng.gen_range(-TAU..TAU),
+                        },
+                        SpriteBundle {
+                            sprite: Sprite {
+                                color: sprite.color,
+                                custom_size: Some(Vec2::splat(SHARD_SIZE)),
+                                ..default()
+                            },
+                            transform: Transform::from_translation(origin),
+                            ..default()
+                        },
+                    ));
+                }
+            });
+    }
+}
+
+fn animate_clear(
+    settings: Res<Settings>,
+    state: Res<GameState>,
+    timers: Res<Timers>,
+    mut blocks: Query<(&Clearing, &Positioned, &mut Sprite, &mut Transform)>,
+    mut shards: Query<(&Shard, &mut Sprite, &mut Transform), Without<Clearing>>,
+) {
+    let (progress, _) = progress(&timers);
+    let strength = Strength::of(&state);
+    let glow = strength.glow();
+
+    for (clearing, pos, mut sprite, mut transform) in &mut blocks {
+        let mut scale = 1.0;
+        sprite.color = match settings.visuals.clear_effect {
+            ClearEffect::Fade => glow.with_alpha((1.0 - progress).powi(2)),
+            ClearEffect::Flash => {
+                let blinks = if strength == Strength::Normal {
+                    2.0
+                } else {
+                    4.0
+                };
+                if progress >= 1.0 {
+                    Color::NONE
+                } else if (progress * blinks).fract() < 0.5 {
+                    glow
+                } else {
+                    clearing.color
+                }
+            }
+            ClearEffect::Dissolve => {
+                // Blocks start shrinking one after the other, in an order that looks random
+                let delay = ((pos.x as i32 * 7 + pos.y as i32 * 3) % 10) as f32 / 20.0;
+                let dissolved = ((progress - delay) * 2.0).clamp(0.0, 1.0);
+                scale = 1.0 - dissolved;
+                clearing
+                    .color
+                    .mix(&glow, dissolved)
+                    .with_alpha(1.0 - dissolved)
+            }
+            ClearEffect::Shatter => Color::NONE,
+            ClearEffect::Sweep => {
+                // Columns light up as the sweep reaches them, then fade out
+                let delay = pos.x as f32 / MATRIX_WIDTH as f32 * 0.5;
+                let swept = ((progress - delay) * 2.0).clamp(0.0, 1.0);
+                if swept < 0.3 {
+                    clearing.color.mix(&glow, swept / 0.3)
+                } else {
+                    glow.with_alpha((1.0 - swept) / 0.7)
+                }
+            }
+        };
+        // Shrink towards the center of the cell, as the sprite is anchored to its bottom left
+        transform.translation =
+            Transform::from(**pos).translation + Vec2::splat((1.0 - scale) / 2.0).extend(0.0);
+        transform.scale = Vec3::new(scale, scale, 1.0);
+    }
+
+    for (shard, mut sprite, mut transform) in &mut shards {
+        let offset = shard.flight * progress - Vec2::Y * SHARD_FALL * progress * progress;
+        transform.translation = shard.origin + offset.extend(0.0);
+        transform.rotation = Quat::from_rotation_z(shard.spin * progress);
+        sprite.color.set_alpha(1.0 - progress);
+    }
+}
+
+/// Let the blocks above the cleared lines fall into their new rows, speeding up like under gravity.
+fn collapse(
+    state: Res<GameState>,
+    timers: Res<Timers>,
+    mut blocks: Query<(&Positioned, &mut Transform), (With<Block>, Without<ToDelete>)>,
+) {
+    let (_, fall) = progress(&timers);
+    let lines = state.matrix.full_lines();
+    for (pos, mut transform) in &mut blocks {
+        let below = lines.iter().filter(|line| **line < pos.y as usize).count();
+        if below > 0 {
+            transform.translation.y = pos.y as f32 - below as f32 * fall * fall;
+        }
+    }
+}
+
+fn remove_shards(mut commands: Commands, shards: Query<Entity, With<Shard>>) {
+    for shard in &shards {
+        commands.entity(shard).despawn_recursive();
+    }
+}
@@ -0,0 +1,121 @@
+//! What happens when a piece locks: spin detection, and the chains (combos and back-to-backs) that
+//! line clears build up.
+
+use bevy::prelude::*;
+
+use crate::model::{Facing, Pos, Tetrimino, TetriminoKind};
+
+use super::{input::PieceInputs, matrix::Matrix, score::ScoreEvent, GameState};
+
+pub fn plugin(app: &mut App) {
+    app.add_event::<PieceLocked>().add_event::<LineClear>();
+}
+
+/// Whether a piece was spun into place.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+pub enum Spin {
+    #[default]
+    None,
+    Mini,
+    Full,
+}
+
+/// Sent when a piece locks into the matrix.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct PieceLocked {
+    pub tetrimino: Tetrimino,
+    pub pos: Pos,
+    pub inputs: PieceInputs,
+}
+
+/// Sent when a piece clears lines or is spun into place.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct LineClear {
+    pub lines: u8,
+    pub spin: Spin,
+    /// Number of consecutive line clears right before this one.
+    pub combo: u32,
+    /// Number of consecutive "difficult" clears (tetrises and spins) before this one, if this one
+    /// is difficult too.
+    pub back_to_back: u32,
+    /// Whether the clear left the matrix empty.
+    pub perfect_clear: bool,
+}
+
+impl LineClear {
+    /// Tetrises and spins that clear lines are "difficult", and build up back-to-back chains.
+    pub fn is_difficult(&self) -> bool {
+        self.lines == 4 || (self.lines > 0 && self.spin != Spin::None)
+    }
+
+    pub fn score_event(&self) -> Option<ScoreEvent> {
+        let event = match (self.spin, self.lines) {
+            (Spin::None, 1) => ScoreEvent::Single,
+            (Spin::None, 2) => ScoreEvent::Double,
+            (Spin::None, 3) => ScoreEvent::Triple,
+            (Spin::None, 4) => ScoreEvent::Tetris,
+            (Spin::Mini, 0) => ScoreEvent::MiniTSpin,
+            (Spin::Mini, 1) => ScoreEvent::MiniTSpinSingle,
+            (Spin::Mini, _) => ScoreEvent::MiniTSpinDouble,
+            (Spin::Full, 0) => ScoreEvent::TSpin,
+            (Spin::Full, 1) => ScoreEvent::TSpinSingle,
+            (Spin::Full, 2) => ScoreEvent::TSpinDouble,
+            (Spin::Full, _) => ScoreEvent::TSpinTriple,
+            (Spin::None, _) => return None,
+        };
+        Some(event)
+    }
+}
+
+/// Detect T-spins using the 3-corner rule: the last move must have been a rotation, and 3 of the 4
+/// corners around the center of the T must be occupied. It is only a mini T-spin if one of the two
+/// corners the T is pointing to is free.
+pub fn detect_spin(matrix: &Matrix, tetrimino: &Tetrimino, pos: Pos, rotated_last: bool) -> Spin {
+    if tetrimino.kind != TetriminoKind::T || !rotated_last {
+        return Spin::None;
+    }
+    let occupied = |x, y| matrix.is_occupied(pos + Pos::new(x, y));
+    let front = match tetrimino.facing {
+        Facing::North => [(-1, 1), (1, 1)],
+        Facing::East => [(1, 1), (1, -1)],
+        Facing::South => [(-1, -1), (1, -1)],
+        Facing::West => [(-1, 1), (-1, -1)],
+    };
+    let corners = [(-1, -1), (-1, 1), (1, -1), (1, 1)]
+        .into_iter()
+        .filter(|(x, y)| occupied(*x, *y))
+        .count();
+    if corners < 3 {
+        Spin::None
+    } else if front.into_iter().all(|(x, y)| occupied(x, y)) {
+        Spin::Full
+    } else {
+        Spin::Mini
+    }
+}
+
+/// Record a clear of the given number of lines by the last piece, extending or breaking the combo
+/// and back-to-back chains.
+pub fn register_clear(state: &mut GameState, lines: u8) -> LineClear {
+    let mut clear = LineClear {
+        lines,
+        spin: state.spin,
+        combo: 0,
+        back_to_back: 0,
+        perfect_clear: false,
+    };
+    if lines == 0 {
+        state.combo = 0;
+        return clear;
+    }
+
+    clear.combo = state.combo;
+    state.combo += 1;
+    if clear.is_difficult() {
+        clear.back_to_back = state.back_to_back;
+        state.back_to_back += 1;
+    } else {
+        state.back_to_back = 0;
+    }
+    clear
+}
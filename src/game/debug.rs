@@ -1,26 +1,32 @@
-use bevy::input::common_conditions::input_toggle_active;
+use bevy::input::common_conditions::{input_just_pressed, input_toggle_active};
 use bevy::prelude::*;
 use bevy::{color::palettes, ecs::system::lifetimeless::SRes};
 use iyes_perf_ui::{entry::PerfUiEntry, prelude::PerfUiRoot, PerfUiAppExt};
 
-use crate::{screen::Screen, AppSet};
+use crate::{
+    layout::Layout,
+    screen::{InGame, Pause},
+    AppSet,
+};
 
-use super::{Phase, SCALE};
+use super::{snapshot::Rollback, Phase, SimulationTick, MATRIX_WIDTH, TICK_RATE, VISIBLE_HEIGHT};
 
 pub fn plugin(app: &mut App) {
     app.add_perf_ui_simple_entry::<PerfUiPhase>()
         .add_systems(
             Update,
-            setup
-                .run_if(in_state(Screen::Gameplay))
-                .in_set(AppSet::Update),
+            setup.run_if(in_state(InGame)).in_set(AppSet::Update),
         )
         .add_systems(
             Update,
             (debug_grid)
-                .run_if(
-                    in_state(Screen::Gameplay).and_then(input_toggle_active(false, KeyCode::KeyG)),
-                )
+                .run_if(in_state(InGame).and_then(input_toggle_active(false, KeyCode::KeyG)))
+                .in_set(AppSet::Update),
+        )
+        .add_systems(
+            Update,
+            rollback
+                .run_if(in_state(Pause::Running).and_then(input_just_pressed(KeyCode::Backspace)))
                 .in_set(AppSet::Update),
         );
 }
@@ -32,18 +38,24 @@ fn setup(mut commands: Commands, root: Query<Entity, Added<PerfUiRoot>>) {
     }
 }
 
-fn debug_grid(mut gizmos: Gizmos) {
+fn debug_grid(mut gizmos: Gizmos, layout: Res<Layout>) {
+    let size = UVec2::new(MATRIX_WIDTH.into(), VISIBLE_HEIGHT.into());
     gizmos
         .grid_2d(
-            Vec2::new(-5.0 * SCALE, 0.0),
+            layout.matrix_origin() + size.as_vec2() / 2.0 * layout.scale,
             0.0,
-            UVec2::new(10, 22),
-            Vec2::new(SCALE, SCALE),
+            size,
+            Vec2::splat(layout.scale),
             palettes::css::HOT_PINK.with_alpha(0.5),
         )
         .outer_edges();
 }
 
+/// Roll the game back by one second
+fn rollback(mut commands: Commands, tick: Res<SimulationTick>) {
+    commands.add(Rollback(tick.saturating_sub(TICK_RATE as u64)));
+}
+
 #[derive(Component)]
 pub struct PerfUiPhase;
 
@@ -0,0 +1,137 @@
+//! Detecting pieces placed with more inputs than necessary, and training to avoid them.
+
+use bevy::{color::palettes, prelude::*};
+
+use crate::{
+    layout::HudAnchor,
+    model::{
+        finesse::{self, Move},
+        Pos, Tetrimino, TetriminoKind,
+    },
+    screen::InGame,
+    AppSet,
+};
+
+use super::{
+    clear::{PieceLocked, Spin},
+    input::{Action, PieceInputs},
+    spawners::INITIAL_POS,
+    GameConfig,
+};
+
+pub fn plugin(app: &mut App) {
+    app.add_event::<FinesseFault>()
+        .add_systems(OnEnter(InGame), spawn_indicator)
+        .add_systems(
+            Update,
+            update_indicator
+                .run_if(in_state(InGame))
+                .in_set(AppSet::Update),
+        );
+}
+
+/// Sent when a piece is placed with more moves than necessary.
+#[derive(Event, Debug, Clone)]
+pub struct FinesseFault {
+    pub kind: TetriminoKind,
+    /// Number of moves that were made.
+    pub moves: u32,
+    /// The shortest sequence of moves to the same placement.
+    pub optimal: Vec<Move>,
+}
+
+impl FinesseFault {
+    /// The keys to press to place the piece with the optimal moves.
+    pub fn optimal_keys(&self) -> impl Iterator<Item = Action> + '_ {
+        self.optimal
+            .iter()
+            .copied()
+            .map(Action::from)
+            .chain([Action::HardDrop])
+    }
+}
+
+/// Check the placement of a piece that is about to lock.
+///
+/// Soft drops and spins can get pieces where shifting and rotating alone can't, so those are never
+/// faults.
+pub(super) fn check(
+    tetrimino: &Tetrimino,
+    pos: &Pos,
+    inputs: &PieceInputs,
+    spin: Spin,
+) -> Option<FinesseFault> {
+    if inputs.soft_drop || spin != Spin::None {
+        return None;
+    }
+    let optimal = finesse::optimal_moves(tetrimino, pos, &INITIAL_POS)?;
+    if inputs.moves <= optimal.len() as u32 {
+        return None;
+    }
+    Some(FinesseFault {
+        kind: tetrimino.kind,
+        moves: inputs.moves,
+        optimal,
+    })
+}
+
+/// The row the indicator starts at, under the frame of the next zone with the most previews.
+const INDICATOR_ROW: f32 = 3.0;
+/// Width the indicator wraps at, in pixels.
+const INDICATOR_WIDTH: f32 = 200.0;
+
+#[derive(Component)]
+struct FinesseIndicator;
+
+fn spawn_indicator(mut commands: Commands, assets: Res<AssetServer>) {
+    commands.spawn((
+        Name::new("Finesse indicator"),
+        StateScoped(InGame),
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: assets.load("fonts/JetBrainsMonoNLNerdFont-Regular.ttf"),
+                font_size: 18.0,
+                color: palettes::css::ORANGE_RED.into(),
+            },
+        )
+        .with_style(Style {
+            max_width: Val::Px(INDICATOR_WIDTH),
+            ..default()
+        }),
+        HudAnchor::Right(INDICATOR_ROW),
+        FinesseIndicator,
+    ));
+}
+
+/// Show the optimal keys for the last fault, until a piece is placed cleanly.
+fn update_indicator(
+    mut locked: EventReader<PieceLocked>,
+    mut faults: EventReader<FinesseFault>,
+    config: Res<GameConfig>,
+    mut indicator: Query<&mut Text, With<FinesseIndicator>>,
+) {
+    let Ok(mut text) = indicator.get_single_mut() else {
+        return;
+    };
+    if locked.read().count() > 0 {
+        text.sections[0].value.clear();
+    }
+    if let Some(fault) = faults.read().last() {
+        let keys: Vec<String> = fault
+            .optimal_keys()
+            .map(|action| format!("{action:?}"))
+            .collect();
+        text.sections[0].value = format!(
+            "Finesse fault: {:?} in {} moves{}, optimal: {}",
+            fault.kind,
+            fault.moves,
+            if config.finesse_training {
+                " (retry)"
+            } else {
+                ""
+            },
+            keys.join(" ")
+        );
+    }
+}
@@ -0,0 +1,113 @@
+//! Sharing positions as fumen strings.
+//!
+//! The current position can be copied to the clipboard, and a game can be started from a fumen
+//! given on the command line (`--fumen <data>`) to practice a given setup.
+
+use bevy::{input::common_conditions::input_just_pressed, prelude::*};
+
+use crate::{
+    args::Args,
+    model::{
+        fumen::{self, Field, FumenError, Page, Quiz},
+        Cell, Pos, Tetrimino, TetriminoKind,
+    },
+    screen::InGame,
+    AppSet,
+};
+
+use super::{spawners::piece::CurrentPiece, Block, GameConfig, GameState, Positioned};
+
+/// Number of upcoming pieces included in an exported position.
+const EXPORTED_QUEUE_LEN: usize = 5;
+
+pub fn plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        copy_position
+            .run_if(in_state(InGame).and_then(input_just_pressed(KeyCode::F2)))
+            .in_set(AppSet::Update),
+    );
+
+    if let Some(data) = app.world().resource::<Args>().fumen.clone() {
+        match Setup::from_fumen(&data) {
+            Ok(setup) => app.world_mut().resource_mut::<GameConfig>().setup = Some(setup),
+            Err(e) => error!("Failed to load fumen {data}: {e}"),
+        }
+    }
+}
+
+/// A position to start a game from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Setup {
+    pub cells: Vec<(Pos, Cell)>,
+    pub hold: Option<TetriminoKind>,
+    /// The pieces to play first, in order. Once they've all been played, pieces are randomized as
+    /// usual.
+    pub queue: Vec<TetriminoKind>,
+}
+
+impl Setup {
+    /// Load the first page of a fumen.
+    pub fn from_fumen(data: &str) -> Result<Self, FumenError> {
+        let pages = fumen::decode(data)?;
+        let page = pages.first().ok_or(FumenError::UnexpectedEnd)?;
+        Ok(Self::from_page(page))
+    }
+
+    /// The pieces are taken from the page's quiz comment if it has one, otherwise only the page's
+    /// piece (if any) is used.
+    pub fn from_page(page: &Page) -> Self {
+        let quiz = Quiz::parse(&page.comment).unwrap_or_default();
+        let current = quiz
+            .current
+            .or(page.piece.map(|(tetrimino, _)| tetrimino.kind));
+        Self {
+            cells: page.field.cells().collect(),
+            hold: quiz.hold,
+            queue: current.into_iter().chain(quiz.queue).collect(),
+        }
+    }
+}
+
+/// Encode a position as a single page fumen, with the pieces stored as a quiz comment.
+pub fn encode_position(
+    cells: impl IntoIterator<Item = (Pos, Cell)>,
+    current: Option<(Tetrimino, Pos)>,
+    hold: Option<TetriminoKind>,
+    queue: Vec<TetriminoKind>,
+) -> String {
+    let mut field = Field::default();
+    for (pos, cell) in cells {
+        field.set(pos, Some(cell));
+    }
+    let quiz = Quiz {
+        hold,
+        current: current.map(|(tetrimino, _)| tetrimino.kind),
+        queue,
+    };
+    let mut page = Page::new(field);
+    page.piece = current;
+    page.comment = quiz.to_string();
+    fumen::encode(&[page])
+}
+
+fn copy_position(
+    blocks: Query<(&Positioned, &Block)>,
+    current: Query<(&Tetrimino, &Positioned), With<CurrentPiece>>,
+    state: Res<GameState>,
+) {
+    let data = encode_position(
+        blocks.iter().map(|(pos, block)| (**pos, block.0)),
+        current
+            .get_single()
+            .ok()
+            .map(|(tetrimino, pos)| (*tetrimino, **pos)),
+        state.hold,
+        state.bag.peek(EXPORTED_QUEUE_LEN),
+    );
+    info!("Current position: {data}");
+    match arboard::Clipboard::new().and_then(|mut clipboard| clipboard.set_text(data)) {
+        Ok(()) => info!("Copied position to the clipboard"),
+        Err(e) => warn!("Failed to copy position to the clipboard: {e}"),
+    }
+}
@@ -0,0 +1,97 @@
+//! An assist for newer players: a second ghost shows where the built-in AI would put the current
+//! piece, or the one holding would bring in.
+//!
+//! `--hints` starts games with hints shown, and F3 shows or hides them.
+
+use bevy::{input::common_conditions::input_just_pressed, prelude::*};
+
+use crate::{
+    args::Args,
+    model::{ai::Weights, Tetrimino},
+    screen::{InGame, Pause},
+    AppSet, SimulationSet,
+};
+
+use super::{
+    spawners::{
+        piece::{CurrentPiece, HintPiece},
+        Positioned, SpawnPiece, INITIAL_POS,
+    },
+    GameState, Phase,
+};
+
+pub fn plugin(app: &mut App) {
+    app.insert_resource(Hints {
+        shown: app.world().resource::<Args>().hints,
+        ..default()
+    })
+    .add_systems(
+        FixedUpdate,
+        update_hint
+            .run_if(in_state(Phase::Falling))
+            .in_set(SimulationSet::React),
+    )
+    .add_systems(
+        Update,
+        toggle_hints
+            .run_if(
+                in_state(InGame)
+                    .and_then(not(in_state(Pause::Paused)))
+                    .and_then(input_just_pressed(KeyCode::F3)),
+            )
+            .in_set(AppSet::RecordInput),
+    );
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct Hints {
+    pub shown: bool,
+    /// The current piece the hint was shown for.
+    piece: Option<Entity>,
+}
+
+fn toggle_hints(
+    mut commands: Commands,
+    mut hints: ResMut<Hints>,
+    hint: Query<Entity, With<HintPiece>>,
+) {
+    hints.shown = !hints.shown;
+    info!("{} hints", if hints.shown { "Showing" } else { "Hiding" });
+    hints.piece = None;
+    for entity in &hint {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Show the placement the AI recommends for each new piece.
+///
+/// Holding and locking both spawn a new piece (and clean up the hint along with the other pieces),
+/// so the hint only needs working out once per piece.
+fn update_hint(
+    mut commands: Commands,
+    mut hints: ResMut<Hints>,
+    state: Res<GameState>,
+    current: Query<(Entity, &Tetrimino, &Positioned), With<CurrentPiece>>,
+) {
+    let Ok((entity, tetrimino, pos)) = current.get_single() else {
+        return;
+    };
+    if !hints.shown || hints.piece == Some(entity) {
+        return;
+    }
+    hints.piece = Some(entity);
+
+    let decision = Weights::default().decide(
+        &state.matrix.to_board(),
+        (*tetrimino, **pos),
+        state.hold_alternative(),
+        INITIAL_POS,
+    );
+    if let Some(decision) = decision {
+        let placement = decision.placement;
+        commands.add(
+            SpawnPiece::hint(placement.tetrimino, placement.pos)
+                .with_parent(state.matrix.root_entity),
+        );
+    }
+}
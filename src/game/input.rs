@@ -1,19 +1,66 @@
-use bevy::prelude::*;
+use std::{collections::BTreeMap, fs, io, path::PathBuf};
+
+use bevy::{
+    input::gamepad::{GamepadConnection, GamepadConnectionEvent},
+    prelude::*,
+};
 use leafwing_input_manager::{
     common_conditions::action_just_pressed, plugin::InputManagerPlugin, prelude::*, Actionlike,
 };
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    model::finesse::Move,
+    screen::{Pause, Screen},
+    storage, SimulationSet,
+};
+
+use super::ai::AiPlayer;
 
 pub fn plugin(app: &mut App) {
     app.add_plugins(InputManagerPlugin::<Action>::default())
         .init_resource::<ActionState<Action>>()
-        .insert_resource(Action::make_input_map())
+        .init_resource::<PlayerInput>()
+        .init_resource::<HeldThroughPause>()
+        .init_resource::<Controls>()
+        .init_resource::<InputMap<Action>>()
+        .add_systems(
+            PreUpdate,
+            apply_controls.run_if(resource_changed::<Controls>),
+        )
         .add_systems(
             Update,
-            toggle_pause.run_if(action_just_pressed(Action::Pause)),
+            log_gamepad_connections.run_if(on_event::<GamepadConnectionEvent>()),
+        )
+        .add_systems(
+            Update,
+            toggle_pause
+                .run_if(in_state(Screen::Gameplay).and_then(action_just_pressed(Action::Pause))),
+        )
+        .add_systems(OnExit(Pause::Paused), hold_through_pause)
+        .add_systems(
+            FixedUpdate,
+            record_input
+                .run_if(in_state(Screen::Gameplay).and_then(not(resource_exists::<AiPlayer>)))
+                .in_set(SimulationSet::RecordInput),
         );
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Actionlike, Reflect)]
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Actionlike,
+    Reflect,
+    Serialize,
+    Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
 pub enum Action {
     Left,
     Right,
@@ -21,28 +68,425 @@ pub enum Action {
     RotateRight,
     SoftDrop,
     HardDrop,
+    Hold,
     Pause,
 }
 
 impl Action {
-    pub fn make_input_map() -> InputMap<Self> {
-        InputMap::new([
-            (Action::Left, KeyCode::ArrowLeft),
-            (Action::Right, KeyCode::ArrowRight),
-            (Action::RotateLeft, KeyCode::KeyZ),
-            (Action::RotateLeft, KeyCode::ArrowUp),
-            (Action::RotateRight, KeyCode::KeyX),
-            (Action::SoftDrop, KeyCode::ArrowDown),
-            (Action::HardDrop, KeyCode::Space),
-            (Action::Pause, KeyCode::KeyP),
-        ])
-    }
-}
-
-fn toggle_pause(mut time: ResMut<Time<Virtual>>) {
-    if time.is_paused() {
-        time.unpause();
-    } else {
-        time.pause();
+    /// The actions that affect the game simulation.
+    pub const GAMEPLAY: [Action; 7] = [
+        Action::Left,
+        Action::Right,
+        Action::RotateLeft,
+        Action::RotateRight,
+        Action::SoftDrop,
+        Action::HardDrop,
+        Action::Hold,
+    ];
+
+    pub const ALL: [Action; 8] = [
+        Action::Left,
+        Action::Right,
+        Action::RotateLeft,
+        Action::RotateRight,
+        Action::SoftDrop,
+        Action::HardDrop,
+        Action::Hold,
+        Action::Pause,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Action::Left => "Move left",
+            Action::Right => "Move right",
+            Action::RotateLeft => "Rotate left",
+            Action::RotateRight => "Rotate right",
+            Action::SoftDrop => "Soft drop",
+            Action::HardDrop => "Hard drop",
+            Action::Hold => "Hold",
+            Action::Pause => "Pause",
+        }
+    }
+
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+impl From<Move> for Action {
+    fn from(step: Move) -> Self {
+        match step {
+            Move::Left => Action::Left,
+            Move::Right => Action::Right,
+            Move::RotateCw => Action::RotateRight,
+            Move::RotateCcw => Action::RotateLeft,
+            Move::SoftDrop => Action::SoftDrop,
+        }
+    }
+}
+
+/// An input that an action can be bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Binding {
+    Key(KeyCode),
+    Button(GamepadButtonType),
+}
+
+impl std::fmt::Display for Binding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Binding::Key(key) => {
+                let name = format!("{key:?}");
+                // `KeyZ` and `Digit1` read better as `Z` and `1`
+                let name = name
+                    .strip_prefix("Key")
+                    .or_else(|| name.strip_prefix("Digit"))
+                    .unwrap_or(&name);
+                write!(f, "{name}")
+            }
+            Binding::Button(button) => write!(f, "Pad {button:?}"),
+        }
+    }
+}
+
+/// The keys and gamepad buttons bound to an action.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct Bindings {
+    pub keys: Vec<KeyCode>,
+    pub buttons: Vec<GamepadButtonType>,
+}
+
+impl Bindings {
+    pub fn iter(&self) -> impl Iterator<Item = Binding> + '_ {
+        let keys = self.keys.iter().copied().map(Binding::Key);
+        keys.chain(self.buttons.iter().copied().map(Binding::Button))
+    }
+
+    pub fn contains(&self, binding: Binding) -> bool {
+        self.iter().any(|b| b == binding)
+    }
+
+    pub fn push(&mut self, binding: Binding) {
+        match binding {
+            Binding::Key(key) => self.keys.push(key),
+            Binding::Button(button) => self.buttons.push(button),
+        }
+    }
+
+    pub fn remove(&mut self, binding: Binding) {
+        match binding {
+            Binding::Key(key) => self.keys.retain(|k| *k != key),
+            Binding::Button(button) => self.buttons.retain(|b| *b != button),
+        }
+    }
+}
+
+/// The bindings of every action, kept across sessions in a TOML file in the user config directory.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Controls(BTreeMap<Action, Bindings>);
+
+impl Default for Controls {
+    fn default() -> Self {
+        use GamepadButtonType::*;
+
+        let bind = |keys: &[KeyCode], buttons: &[GamepadButtonType]| Bindings {
+            keys: keys.to_vec(),
+            buttons: buttons.to_vec(),
+        };
+        Self(BTreeMap::from([
+            (Action::Left, bind(&[KeyCode::ArrowLeft], &[DPadLeft])),
+            (Action::Right, bind(&[KeyCode::ArrowRight], &[DPadRight])),
+            (
+                Action::RotateLeft,
+                bind(&[KeyCode::KeyZ, KeyCode::ArrowUp], &[South]),
+            ),
+            (Action::RotateRight, bind(&[KeyCode::KeyX], &[East])),
+            (Action::SoftDrop, bind(&[KeyCode::ArrowDown], &[DPadDown])),
+            (Action::HardDrop, bind(&[KeyCode::Space], &[DPadUp])),
+            (
+                Action::Hold,
+                bind(
+                    &[KeyCode::KeyC, KeyCode::ShiftLeft],
+                    &[LeftTrigger, RightTrigger],
+                ),
+            ),
+            (
+                Action::Pause,
+                bind(&[KeyCode::KeyP, KeyCode::Escape], &[Start]),
+            ),
+        ]))
+    }
+}
+
+impl Controls {
+    fn path() -> PathBuf {
+        storage::config_dir().join("controls.toml")
+    }
+
+    /// Load the controls, falling back to the default bindings if they can't be read.
+    ///
+    /// Actions missing from the file keep their default bindings.
+    pub fn load() -> Self {
+        let path = Self::path();
+        let controls = match fs::read_to_string(&path) {
+            Ok(contents) => toml::from_str::<Self>(&contents).map_err(|e| e.to_string()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Self::default(),
+            Err(e) => Err(e.to_string()),
+        };
+        match controls {
+            Ok(mut controls) => {
+                for (action, bindings) in Self::default().0 {
+                    controls.0.entry(action).or_insert(bindings);
+                }
+                controls
+            }
+            Err(e) => {
+                warn!("Failed to load controls from {}: {e}", path.display());
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = Self::path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let contents = toml::to_string_pretty(self).map_err(|e| io::Error::other(e.to_string()))?;
+        fs::write(path, contents)
+    }
+
+    pub fn bindings(&self, action: Action) -> &Bindings {
+        static UNBOUND: Bindings = Bindings {
+            keys: Vec::new(),
+            buttons: Vec::new(),
+        };
+        self.0.get(&action).unwrap_or(&UNBOUND)
+    }
+
+    /// The action the input is bound to, if any.
+    pub fn action_of(&self, binding: Binding) -> Option<Action> {
+        self.0
+            .iter()
+            .find(|(_, bindings)| bindings.contains(binding))
+            .map(|(action, _)| *action)
+    }
+
+    /// Bind the input to the action, unless it is already bound to another action, which is
+    /// returned instead.
+    pub fn bind(&mut self, action: Action, binding: Binding) -> Result<(), Action> {
+        match self.action_of(binding) {
+            Some(other) if other != action => Err(other),
+            Some(_) => Ok(()),
+            None => {
+                self.0.entry(action).or_default().push(binding);
+                Ok(())
+            }
+        }
+    }
+
+    /// Remove one of the bindings of the action.
+    pub fn unbind(&mut self, action: Action, binding: Binding) {
+        if let Some(bindings) = self.0.get_mut(&action) {
+            bindings.remove(binding);
+        }
+    }
+
+    fn input_map(&self) -> InputMap<Action> {
+        let mut input_map = InputMap::default();
+        for (action, bindings) in &self.0 {
+            for key in &bindings.keys {
+                input_map.insert(*action, *key);
+            }
+            for button in &bindings.buttons {
+                input_map.insert(*action, *button);
+            }
+        }
+        input_map
+    }
+}
+
+fn apply_controls(mut commands: Commands, controls: Res<Controls>) {
+    commands.insert_resource(controls.input_map());
+}
+
+/// Gamepads can be plugged in and out at any time: the bindings apply to all of them.
+fn log_gamepad_connections(mut events: EventReader<GamepadConnectionEvent>) {
+    for event in events.read() {
+        match &event.connection {
+            GamepadConnection::Connected(info) => {
+                info!("Gamepad {} connected: {}", event.gamepad.id, info.name);
+            }
+            GamepadConnection::Disconnected => {
+                info!("Gamepad {} disconnected", event.gamepad.id);
+            }
+        }
+    }
+}
+
+/// How far the left stick must be pushed for it to count as pressing a direction. Anything less is
+/// treated as the stick resting, to ignore drift.
+const STICK_THRESHOLD: f32 = 0.5;
+
+/// The direction the left stick of any gamepad is pushed in, as a unit vector along one of the
+/// axes, or zero if no stick is pushed far enough.
+///
+/// Only the main axis of a diagonal counts, so that a slightly off push to the side doesn't also
+/// soft drop.
+pub fn stick_direction(gamepads: &Gamepads, axes: &Axis<GamepadAxis>) -> IVec2 {
+    for gamepad in gamepads.iter() {
+        let axis = |axis_type| {
+            axes.get(GamepadAxis::new(gamepad, axis_type))
+                .unwrap_or_default()
+        };
+        let stick = Vec2::new(
+            axis(GamepadAxisType::LeftStickX),
+            axis(GamepadAxisType::LeftStickY),
+        );
+        if stick.length() < STICK_THRESHOLD {
+            continue;
+        }
+        return if stick.x.abs() >= stick.y.abs() {
+            IVec2::new(stick.x.signum() as i32, 0)
+        } else {
+            IVec2::new(0, stick.y.signum() as i32)
+        };
+    }
+    IVec2::ZERO
+}
+
+/// The set of actions held down during a single simulation tick.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InputFrame(u8);
+
+impl InputFrame {
+    pub fn from_action_state(action_state: &ActionState<Action>) -> Self {
+        let mut frame = Self::default();
+        for action in Action::GAMEPLAY {
+            if action_state.pressed(&action) {
+                frame.press(action);
+            }
+        }
+        frame
+    }
+
+    pub fn press(&mut self, action: Action) {
+        self.0 |= action.bit();
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        self.0 & action.bit() != 0
+    }
+
+    pub fn bits(&self) -> u8 {
+        self.0
+    }
+
+    pub fn from_bits(bits: u8) -> Self {
+        Self(bits)
+    }
+
+    /// The actions of this frame that aren't in the other one.
+    fn without(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+}
+
+/// The player input as seen by the simulation.
+///
+/// Input is sampled once per tick (rather than once per frame like `ActionState<Action>`), so that
+/// "just pressed" / "just released" are relative to the previous tick.
+#[derive(Resource, Debug, Default, Clone, Copy)]
+pub struct PlayerInput {
+    current: InputFrame,
+    previous: InputFrame,
+}
+
+impl PlayerInput {
+    /// The input of the current tick.
+    pub fn current(&self) -> InputFrame {
+        self.current
+    }
+
+    pub fn advance(&mut self, frame: InputFrame) {
+        self.previous = self.current;
+        self.current = frame;
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.current.pressed(action) && !self.previous.pressed(action)
+    }
+
+    pub fn just_released(&self, action: Action) -> bool {
+        !self.current.pressed(action) && self.previous.pressed(action)
+    }
+}
+
+/// The inputs that went into placing the current piece.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+pub struct PieceInputs {
+    /// Number of gameplay keys pressed, including the ones pressed before holding.
+    pub keys: u32,
+    /// Number of shift and rotation keys pressed since the piece spawned.
+    pub moves: u32,
+    /// Whether the piece was soft dropped.
+    pub soft_drop: bool,
+    /// Whether the last successful move of the piece was a rotation.
+    pub rotated_last: bool,
+}
+
+/// The actions that were held down when the game was resumed, and haven't been released since.
+///
+/// They are left out of the input until released, so that confirming "Resume" with the hard drop
+/// key doesn't also drop the piece. Leaving them out of the recorded frames, rather than hiding
+/// the press from the simulation, keeps the replays faithful.
+#[derive(Resource, Debug, Default)]
+struct HeldThroughPause(InputFrame);
+
+/// The actions pressed by the player, on the keyboard or any gamepad.
+fn read_frame(
+    action_state: &ActionState<Action>,
+    gamepads: &Gamepads,
+    axes: &Axis<GamepadAxis>,
+) -> InputFrame {
+    let mut frame = InputFrame::from_action_state(action_state);
+    // The left stick moves pieces like the D-pad does
+    match stick_direction(gamepads, axes) {
+        IVec2::NEG_X => frame.press(Action::Left),
+        IVec2::X => frame.press(Action::Right),
+        IVec2::NEG_Y => frame.press(Action::SoftDrop),
+        _ => {}
+    }
+    frame
+}
+
+fn record_input(
+    action_state: Res<ActionState<Action>>,
+    gamepads: Res<Gamepads>,
+    axes: Res<Axis<GamepadAxis>>,
+    mut held: ResMut<HeldThroughPause>,
+    mut input: ResMut<PlayerInput>,
+) {
+    let frame = read_frame(&action_state, &gamepads, &axes);
+    held.0 = InputFrame(held.0 .0 & frame.0);
+    input.advance(frame.without(held.0));
+}
+
+fn hold_through_pause(
+    action_state: Res<ActionState<Action>>,
+    gamepads: Res<Gamepads>,
+    axes: Res<Axis<GamepadAxis>>,
+    mut held: ResMut<HeldThroughPause>,
+) {
+    held.0 = read_frame(&action_state, &gamepads, &axes);
+}
+
+fn toggle_pause(pause: Res<State<Pause>>, mut next_pause: ResMut<NextState<Pause>>) {
+    match pause.get() {
+        Pause::Running => next_pause.set(Pause::Paused),
+        Pause::Paused => next_pause.set(Pause::Running),
+        // The settings go back to the pause menu by themselves
+        Pause::Settings => {}
     }
 }
@@ -1,6 +1,6 @@
 use bevy::prelude::*;
 
-use crate::model::{Pos, Tetrimino};
+use crate::model::{board::Board, Pos, Tetrimino};
 
 use super::{MATRIX_HEIGHT, MATRIX_WIDTH};
 
@@ -58,6 +58,25 @@ impl Matrix {
         self.board[pos.to_index()]
     }
 
+    /// Whether the given position is taken by a block, or outside of the matrix.
+    pub fn is_occupied(&self, pos: Pos) -> bool {
+        pos.x < 0
+            || pos.x >= MATRIX_WIDTH as i8
+            || pos.y < 0
+            || pos.y >= MATRIX_HEIGHT as i8
+            || self.at_pos(pos) != Entity::PLACEHOLDER
+    }
+
+    /// Which cells are taken, without the blocks themselves.
+    pub fn to_board(&self) -> Board {
+        Board::from_blocks(self.iter_non_empty().map(|(pos, _)| pos))
+    }
+
+    /// Remove all the blocks from the matrix.
+    pub fn clear(&mut self) {
+        self.board.fill(Entity::PLACEHOLDER);
+    }
+
     pub fn insert(&mut self, pos: Pos, id: Entity) {
         info!("Inserting block at {pos}");
         self.board[pos.to_index()] = id;
@@ -95,6 +114,14 @@ impl Matrix {
         })
     }
 
+    /// Number of rows up to the highest occupied cell.
+    pub fn stack_height(&self) -> u8 {
+        self.board
+            .chunks_exact(MATRIX_WIDTH as usize)
+            .rposition(|line| line.iter().any(|e| *e != Entity::PLACEHOLDER))
+            .map_or(0, |line| line as u8 + 1)
+    }
+
     pub fn delete_line(&mut self, line: usize) {
         // Shift everything down by 1 row
         self.board.copy_within(
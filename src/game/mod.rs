@@ -1,53 +1,80 @@
-use std::time::Duration;
-
-use bevy::{
-    color::palettes::{self, css::WHITE},
-    ecs::component::StorageType,
-    prelude::*,
-    sprite::Anchor,
-};
-use bevy_tween::{
-    bevy_time_runner::TimeRunnerEnded,
-    interpolate::sprite_color_to,
-    prelude::{AnimationBuilderExt, EaseFunction},
-    tween::TargetComponent,
-};
-use input::Action;
-use leafwing_input_manager::action_state::ActionState;
+use bevy::{color::palettes, ecs::component::StorageType, prelude::*, sprite::Anchor};
+use clear::{LineClear, PieceLocked, Spin};
+use finesse::FinesseFault;
+use input::{Action, PieceInputs, PlayerInput};
 use score::ScoreEvent;
 use spawners::{
-    next_zone::NextTetriminoZone,
+    hold_zone::HoldTetriminoZone,
+    next_zone::{self, NextTetriminoZone},
     piece::{CurrentPiece, GhostPiece, Mino},
-    Positioned, SpawnMatrix, SpawnNextZone, SpawnPiece, INITIAL_POS,
+    Positioned, SpawnHoldZone, SpawnMatrix, SpawnNextZone, SpawnPiece, INITIAL_POS,
 };
 use timers::Timers;
 
 use self::matrix::Matrix;
 use crate::{
+    args::Args,
     model::Pos,
-    model::{Bag, Tetrimino},
-    screen::Screen,
+    model::{Bag, Cell, Randomizer, Tetrimino, TetriminoKind},
+    screen::InGame,
+    settings::{Handling, Settings},
+    skin::Skinned,
+    SimulationSet,
 };
 
+pub mod ai;
+mod animation;
+pub mod clear;
 #[cfg(feature = "dev")]
 mod debug;
-mod input;
+pub mod finesse;
+pub mod fumen;
+mod hint;
+pub mod input;
 mod matrix;
-mod score;
+pub mod mode;
+pub mod puzzle;
+pub mod replay;
+pub mod score;
+mod snapshot;
 pub mod spawners;
+pub mod stats;
+mod tbp;
 mod timers;
 mod ui;
 
 pub const MATRIX_WIDTH: u8 = 10;
 pub const MATRIX_HEIGHT: u8 = 40;
-pub const SCALE: f32 = 20.0;
+/// Number of rows of the matrix that are visible: the playfield, and the bottom of the buffer zone
+/// above it where pieces spawn.
+pub const VISIBLE_HEIGHT: u8 = 22;
+/// Number of rows of the playfield. Pieces locking entirely above it, in the buffer zone, end the
+/// game (a "lock out").
+pub const LOCK_OUT_HEIGHT: u8 = 20;
+/// Number of simulation ticks per second.
+pub const TICK_RATE: f64 = 60.0;
 
 pub fn plugin(app: &mut App) {
-    app.init_state::<Phase>()
+    app.configure_sets(
+        FixedUpdate,
+        (
+            SimulationSet::RecordInput,
+            SimulationSet::Update,
+            SimulationSet::React,
+        )
+            .run_if(simulation_running),
+    );
+
+    app.add_sub_state::<Phase>()
+        .add_event::<PieceMoved>()
         .init_resource::<GameState>()
         .register_type::<GameState>()
+        .init_resource::<GameConfig>()
+        .init_resource::<SimulationTick>()
+        .init_resource::<SimulationControl>()
+        .insert_resource(Time::<Fixed>::from_hz(TICK_RATE))
         .insert_resource(ClearColor(palettes::css::BLACK.into()))
-        .add_systems(OnEnter(Screen::Gameplay), game_setup)
+        .add_systems(OnEnter(InGame), game_setup)
         .add_systems(
             OnEnter(Phase::Generation),
             (clean_up_pieces, generate_piece).chain(),
@@ -55,25 +82,48 @@ pub fn plugin(app: &mut App) {
         .add_systems(OnExit(Phase::Generation), first_drop)
         .add_systems(OnEnter(Phase::Falling), start_fall_timer)
         .add_systems(
-            Update,
+            FixedUpdate,
             (
-                tick_timers,
-                handle_input,
-                update_ghost,
-                update_piece_transform,
+                (advance_tick, tick_timers).run_if(in_state(InGame)),
+                start_game.run_if(in_state(Phase::Noop)),
+                (handle_input, update_ghost, update_piece_transform)
+                    .chain()
+                    .run_if(in_state(Phase::Falling)),
+                animate_done.run_if(in_state(Phase::Animate)),
             )
                 .chain()
-                .run_if(in_state(Phase::Falling)),
+                .in_set(SimulationSet::Update),
         )
         .add_systems(OnEnter(Phase::Lock), handle_lock)
         .add_systems(OnEnter(Phase::Pattern), detect_patterns)
         .add_systems(OnEnter(Phase::Animate), animate)
-        .add_systems(Update, animate_done.run_if(in_state(Phase::Animate)))
         .add_systems(OnEnter(Phase::Eliminate), eliminate)
         .add_systems(OnExit(Phase::Eliminate), update_blocks_transform)
-        .add_systems(OnExit(Screen::Gameplay), game_cleanup);
-
-    app.add_plugins((input::plugin, spawners::plugin, score::plugin, ui::plugin));
+        .add_systems(FixedLast, consume_step.run_if(in_state(InGame)))
+        .add_systems(OnExit(InGame), game_cleanup);
+
+    app.add_plugins((
+        ai::plugin,
+        animation::plugin,
+        clear::plugin,
+        finesse::plugin,
+        fumen::plugin,
+        hint::plugin,
+        input::plugin,
+        mode::plugin,
+        replay::plugin,
+        score::plugin,
+        snapshot::plugin,
+        stats::plugin,
+        ui::plugin,
+    ));
+
+    if let Some(name) = app.world().resource::<Args>().randomizer.clone() {
+        match name.parse::<Randomizer>() {
+            Ok(randomizer) => app.world_mut().resource_mut::<GameConfig>().randomizer = randomizer,
+            Err(_) => error!("Unknown randomizer {name}"),
+        }
+    }
 
     #[cfg(feature = "dev")]
     app.add_plugins(debug::plugin);
@@ -81,7 +131,8 @@ pub fn plugin(app: &mut App) {
 }
 
 #[allow(unused)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash, States, strum::Display)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash, SubStates, strum::Display)]
+#[source(InGame = InGame)]
 pub enum Phase {
     Generation,
     Falling,
@@ -94,14 +145,109 @@ pub enum Phase {
     Noop,
 }
 
+/// Sent when the player moves the current piece.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PieceMoved {
+    Shift,
+    Rotate,
+    /// Soft drop was started.
+    SoftDrop,
+    /// The piece was dropped from `from` straight down to `to`, where it locks.
+    HardDrop {
+        tetrimino: Tetrimino,
+        from: Pos,
+        to: Pos,
+    },
+    Hold,
+}
+
 #[derive(Default, Resource, Reflect)]
 pub struct GameState {
     pub matrix: Matrix,
+    #[reflect(ignore)]
     pub bag: Bag,
+    /// The piece in the hold slot.
+    pub hold: Option<TetriminoKind>,
+    /// Whether the current piece can be swapped with the held one. This can only be done once per
+    /// piece.
+    pub can_hold: bool,
+    /// The inputs that went into the current piece.
+    pub inputs: PieceInputs,
+    /// Whether the last piece that locked was spun into place.
+    pub spin: Spin,
+    /// Number of consecutive pieces that cleared lines.
+    pub combo: u32,
+    /// Number of consecutive "difficult" line clears, not broken by any other line clear.
+    pub back_to_back: u32,
+    /// The seed the game was started with.
+    pub seed: u64,
+}
+
+impl GameState {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            matrix: Matrix::new(),
+            bag: Bag::new(seed),
+            hold: None,
+            can_hold: true,
+            inputs: PieceInputs::default(),
+            spin: Spin::None,
+            combo: 0,
+            back_to_back: 0,
+            seed,
+        }
+    }
+
+    /// The piece that holding would bring in (either the held piece or the next one), if the
+    /// current piece can be held.
+    pub fn hold_alternative(&self) -> Option<TetriminoKind> {
+        self.can_hold
+            .then(|| self.hold.unwrap_or_else(|| self.bag.peek_next()))
+    }
+}
+
+/// The parameters the next game will be started with.
+///
+/// Given the same config and the same inputs on every tick, a game always plays out the same way.
+#[derive(Default, Resource, Debug, Clone)]
+pub struct GameConfig {
+    /// Seed for the piece randomizer. A random seed is picked if none is set.
+    pub seed: Option<u64>,
+    pub mode: mode::GameMode,
+    /// Position to start the game from, instead of an empty matrix.
+    pub setup: Option<fumen::Setup>,
+    /// Whether pieces placed with finesse faults must be placed again.
+    pub finesse_training: bool,
+    /// The goal of the puzzle being played, replacing the goal of the mode.
+    pub goal: Option<puzzle::Goal>,
+    pub randomizer: Randomizer,
+    pub handling: Handling,
+}
+
+/// Number of simulation ticks completed since the start of the game.
+#[derive(Default, Resource, Debug, Clone, Copy, Deref)]
+pub struct SimulationTick(pub u64);
+
+/// Allows pausing the simulation, and stepping through it tick by tick.
+#[derive(Default, Resource, Debug)]
+pub struct SimulationControl {
+    pub paused: bool,
+    /// Number of ticks to simulate even though the simulation is paused.
+    pub steps: u32,
+}
+
+impl SimulationControl {
+    pub fn is_running(&self) -> bool {
+        !self.paused || self.steps > 0
+    }
+}
+
+pub fn simulation_running(control: Res<SimulationControl>) -> bool {
+    control.is_running()
 }
 
 /// A static block that has been committed to the matrix.
-pub struct Block;
+pub struct Block(pub Cell);
 
 impl Component for Block {
     fn register_component_hooks(hooks: &mut bevy::ecs::component::ComponentHooks) {
@@ -125,46 +271,79 @@ pub struct BlockBundle {
     sprite: SpriteBundle,
     block: Block,
     pos: Positioned,
+    skinned: Skinned,
 }
 
 impl BlockBundle {
-    pub fn new(pos: Pos) -> Self {
+    pub fn new(pos: Pos, cell: Cell) -> Self {
         Self {
             sprite: SpriteBundle {
                 sprite: Sprite {
                     custom_size: Some(Vec2::splat(1.0)),
                     anchor: Anchor::BottomLeft,
-                    color: palettes::css::GRAY.into(),
                     ..default()
                 },
                 transform: pos.into(),
                 ..default()
             },
             pos: Positioned(pos),
-            block: Block,
+            block: Block(cell),
+            skinned: Skinned::Block(cell),
         }
     }
 }
 
 fn game_setup(
     mut commands: Commands,
-    mut next_phase: ResMut<NextState<Phase>>,
-    mut event_writer: EventWriter<ScoreEvent>,
+    config: Res<GameConfig>,
+    mut state: ResMut<GameState>,
+    mut tick: ResMut<SimulationTick>,
+    mut control: ResMut<SimulationControl>,
 ) {
-    commands.init_resource::<Timers>();
+    let seed = config.seed.unwrap_or_else(rand::random);
+    info!("Starting game with seed {seed}");
+    *state = GameState::new(seed);
+    let queue = config
+        .setup
+        .as_ref()
+        .map(|setup| setup.queue.clone())
+        .unwrap_or_default();
+    state.bag = Bag::with_randomizer(config.randomizer, seed, queue);
+    *tick = SimulationTick::default();
+    *control = SimulationControl::default();
+    commands.insert_resource(Timers::new(config.handling));
+    commands.insert_resource(PlayerInput::default());
 
     commands.add(SpawnMatrix);
     commands.add(SpawnNextZone);
-
-    event_writer.send(ScoreEvent::LevelStart(1));
-
-    next_phase.set(Phase::Generation);
+    commands.add(SpawnHoldZone);
+
+    if let Some(setup) = config.setup.clone() {
+        info!("Starting from a setup of {} blocks", setup.cells.len());
+        state.hold = setup.hold;
+        commands.add(move |world: &mut World| {
+            let root = world.resource::<GameState>().matrix.root_entity;
+            world.entity_mut(root).with_children(|children| {
+                for (pos, cell) in setup.cells {
+                    children.spawn(BlockBundle::new(pos, cell));
+                }
+            });
+        });
+    }
 }
 
 fn game_cleanup(mut commands: Commands) {
     commands.remove_resource::<Timers>();
 }
 
+/// Kick off the game on the first tick.
+///
+/// This is done by the simulation rather than in `game_setup`, so that the game always starts on
+/// the same tick regardless of when the state transitions triggered by the screen change run.
+fn start_game(mut next_phase: ResMut<NextState<Phase>>) {
+    next_phase.set(Phase::Generation);
+}
+
 fn clean_up_pieces(mut commands: Commands, pieces: Query<Entity, With<Tetrimino>>) {
     for piece in pieces.into_iter() {
         info!("Despawning tetrimino");
@@ -176,11 +355,18 @@ fn generate_piece(
     mut commands: Commands,
     mut state: ResMut<GameState>,
     mut next_phase: ResMut<NextState<Phase>>,
+    settings: Res<Settings>,
     next_zone: Query<Entity, With<NextTetriminoZone>>,
+    hold_zone: Query<Entity, With<HoldTetriminoZone>>,
 ) {
     let next_zone_entity = next_zone.single();
     let tetrimino: Tetrimino = state.bag.pop_next().into();
-    let next_piece: Tetrimino = state.bag.peek_next().into();
+
+    if !state.matrix.is_pos_valid(&tetrimino, &INITIAL_POS) {
+        info!("No room to spawn {:?}: top out!", tetrimino.kind);
+        next_phase.set(Phase::Completion);
+        return;
+    }
 
     info!("Generating new tetrimino {:?}", tetrimino.kind);
 
@@ -188,7 +374,14 @@ fn generate_piece(
     let ghost_pos = state.matrix.lowest_valid_pos(&tetrimino, &INITIAL_POS);
     commands.add(SpawnPiece::ghost(tetrimino, ghost_pos).with_parent(state.matrix.root_entity));
 
-    commands.add(SpawnPiece::next(next_piece).with_parent(next_zone_entity));
+    let bag = state.bag.clone();
+    let previews = settings.visuals.previews;
+    commands.add(move |world: &mut World| {
+        next_zone::spawn_previews(world, next_zone_entity, &bag, previews);
+    });
+    if let Some(held) = state.hold {
+        commands.add(SpawnPiece::hold(held.into()).with_parent(hold_zone.single()));
+    }
 
     next_phase.set(Phase::Falling);
 }
@@ -209,8 +402,16 @@ fn first_drop(
     }
 }
 
-fn tick_timers(mut timers: ResMut<Timers>, time: Res<Time>) {
-    timers.tick(time.delta());
+fn advance_tick(mut tick: ResMut<SimulationTick>) {
+    tick.0 += 1;
+}
+
+fn consume_step(mut control: ResMut<SimulationControl>) {
+    control.steps = control.steps.saturating_sub(1);
+}
+
+fn tick_timers(mut timers: ResMut<Timers>, time: Res<Time<Fixed>>) {
+    timers.tick(time.timestep());
 }
 
 // pub struct LeftRightHandler {}
@@ -226,12 +427,25 @@ fn tick_timers(mut timers: ResMut<Timers>, time: Res<Time>) {
 
 fn handle_input(
     mut current_piece_query: Query<(&mut Tetrimino, &mut Positioned), With<CurrentPiece>>,
-    state: Res<GameState>,
-    action_state: Res<ActionState<Action>>,
+    mut state: ResMut<GameState>,
+    input: Res<PlayerInput>,
     mut timers: ResMut<Timers>,
     mut next_phase: ResMut<NextState<Phase>>,
+    mut moves: EventWriter<PieceMoved>,
 ) {
     let (mut current_piece, mut pos) = current_piece_query.single_mut();
+    let inputs = &mut state.inputs;
+    for action in Action::GAMEPLAY {
+        if input.just_pressed(action) {
+            inputs.keys += 1;
+            if matches!(
+                action,
+                Action::Left | Action::Right | Action::RotateLeft | Action::RotateRight
+            ) {
+                inputs.moves += 1;
+            }
+        }
+    }
 
     // If lock timer has expired -> move to LOCK state
     if timers.lock.times_finished_this_tick() > 0 {
@@ -239,55 +453,91 @@ fn handle_input(
         return;
     }
 
+    if input.just_pressed(Action::Hold) && state.can_hold {
+        // Swap the current piece with the held one, by putting the held piece back in the bag and
+        // generating a new piece from it.
+        info!("Holding {:?}", current_piece.kind);
+        state.can_hold = false;
+        state.inputs = PieceInputs {
+            keys: state.inputs.keys,
+            ..default()
+        };
+        if let Some(held) = state.hold.replace(current_piece.kind) {
+            state.bag.push_front(held);
+        }
+        timers.lock.pause();
+        timers.fall.unpause();
+        next_phase.set(Phase::Generation);
+        moves.send(PieceMoved::Hold);
+        return;
+    }
+
     if timers.lock.paused() {
         for _ in 0..timers.fall.times_finished_this_tick() {
             let down_pos = pos.down();
             if state.matrix.is_pos_valid(&current_piece, &down_pos) {
                 **pos = down_pos;
+                state.inputs.rotated_last = false;
             }
         }
     }
 
-    if action_state.just_pressed(&Action::RotateLeft) {
+    if input.just_pressed(Action::RotateLeft) {
         let rotated = current_piece.rotated_ccw();
         if state.matrix.is_pos_valid(&rotated, &pos) {
             *current_piece = rotated;
+            state.inputs.rotated_last = true;
+            moves.send(PieceMoved::Rotate);
         }
-    } else if action_state.just_pressed(&Action::RotateRight) {
+    } else if input.just_pressed(Action::RotateRight) {
         let rotated = current_piece.rotated_cw();
         if state.matrix.is_pos_valid(&rotated, &pos) {
             *current_piece = rotated;
+            state.inputs.rotated_last = true;
+            moves.send(PieceMoved::Rotate);
         }
     }
     // if action_state.pressed(&Action::Left) {
-    //     if action_state.just_pressed(&Action::Left) {
+    //     if input.just_pressed(Action::Left) {
     //         // start auto-repeat delay timer
     //     }
     // }
 
-    if action_state.just_pressed(&Action::Left) {
+    if input.just_pressed(Action::Left) {
         let left_pos = pos.left();
         if current_piece.min_x(&left_pos) >= 0
             && state.matrix.is_pos_valid(&current_piece, &left_pos)
         {
             **pos = left_pos;
+            state.inputs.rotated_last = false;
+            moves.send(PieceMoved::Shift);
         }
-    } else if action_state.just_pressed(&Action::Right) {
+    } else if input.just_pressed(Action::Right) {
         let right_pos = pos.right();
         if current_piece.max_x(&right_pos) <= 9
             && state.matrix.is_pos_valid(&current_piece, &right_pos)
         {
             **pos = right_pos;
+            state.inputs.rotated_last = false;
+            moves.send(PieceMoved::Shift);
         }
     }
-    if action_state.just_pressed(&Action::HardDrop) {
+    if input.just_pressed(Action::HardDrop) {
+        let from = **pos;
         **pos = state.matrix.lowest_valid_pos(&current_piece, &pos);
         next_phase.set(Phase::Lock);
+        moves.send(PieceMoved::HardDrop {
+            tetrimino: *current_piece,
+            from,
+            to: **pos,
+        });
         return;
     }
-    if action_state.just_pressed(&Action::SoftDrop) {
+    if input.just_pressed(Action::SoftDrop) {
+        state.inputs.soft_drop = true;
         timers.fall.soft_drop();
-    } else if action_state.just_released(&Action::SoftDrop) {
+        moves.send(PieceMoved::SoftDrop);
+    } else if input.just_released(Action::SoftDrop) {
         timers.fall.normal_drop();
     }
 
@@ -354,20 +604,54 @@ fn update_piece_transform(
 
 fn handle_lock(
     mut commands: Commands,
-    state: Res<GameState>,
+    config: Res<GameConfig>,
+    mut state: ResMut<GameState>,
     current_piece: Query<(&Positioned, &Tetrimino), With<CurrentPiece>>,
+    mut timers: ResMut<Timers>,
     mut next_phase: ResMut<NextState<Phase>>,
+    mut locked: EventWriter<PieceLocked>,
+    mut faults: EventWriter<FinesseFault>,
 ) {
     if let Ok((piece_pos, piece)) = current_piece.get_single() {
         info!("Locking piece");
+        if piece.min_y(piece_pos) >= LOCK_OUT_HEIGHT as i8 {
+            info!("Piece locked above the playfield: top out!");
+            next_phase.set(Phase::Completion);
+            return;
+        }
+        let spin = clear::detect_spin(&state.matrix, piece, **piece_pos, state.inputs.rotated_last);
+        if let Some(fault) = finesse::check(piece, piece_pos, &state.inputs, spin) {
+            info!("Finesse fault: {fault:?}");
+            faults.send(fault);
+            if config.finesse_training {
+                // Spawn the same piece again instead of locking it, like when holding
+                state.bag.push_front(piece.kind);
+                state.inputs = PieceInputs {
+                    keys: state.inputs.keys,
+                    ..default()
+                };
+                timers.lock.pause();
+                timers.fall.unpause();
+                next_phase.set(Phase::Generation);
+                return;
+            }
+        }
+        locked.send(PieceLocked {
+            tetrimino: *piece,
+            pos: **piece_pos,
+            inputs: state.inputs,
+        });
+        state.spin = spin;
         commands
             .entity(state.matrix.root_entity)
             .with_children(|children| {
                 for block_pos in piece.block_positions(piece_pos) {
-                    children.spawn(BlockBundle::new(block_pos));
+                    children.spawn(BlockBundle::new(block_pos, Cell::Mino(piece.kind)));
                 }
             });
     }
+    state.can_hold = true;
+    state.inputs = PieceInputs::default();
 
     next_phase.set(Phase::Pattern);
 }
@@ -377,9 +661,23 @@ pub struct ToDelete;
 
 fn detect_patterns(
     mut commands: Commands,
-    state: ResMut<GameState>,
+    mut state: ResMut<GameState>,
     mut next_phase: ResMut<NextState<Phase>>,
+    mut clears: EventWriter<LineClear>,
+    mut score_events: EventWriter<ScoreEvent>,
 ) {
+    let lines = state.matrix.full_lines().len() as u8;
+    let mut clear = clear::register_clear(&mut state, lines);
+    clear.perfect_clear = lines > 0
+        && state.matrix.iter_non_empty().count() == lines as usize * MATRIX_WIDTH as usize;
+    if lines > 0 || clear.spin != Spin::None {
+        info!("Cleared {lines} lines ({:?} spin)", clear.spin);
+        if let Some(event) = clear.score_event() {
+            score_events.send(event);
+        }
+        clears.send(clear);
+    }
+
     let mut has_deletions = false;
     for e in state.matrix.entities_to_delete() {
         info!("Marking block {e} for deletion");
@@ -395,28 +693,18 @@ fn detect_patterns(
     }
 }
 
-#[derive(Component)]
-pub struct Animator;
-
-fn animate(mut commands: Commands, to_delete: Query<Entity, With<ToDelete>>) {
+fn animate(mut timers: ResMut<Timers>) {
     info!("Start animation");
-    let entities = TargetComponent::from_iter(to_delete.iter());
-
-    commands.spawn(Animator).animation().insert_tween_here(
-        Duration::from_secs_f32(1.0),
-        EaseFunction::QuadraticOut,
-        entities
-            .state(WHITE.with_alpha(1.0).into())
-            .with(sprite_color_to(WHITE.with_alpha(0.0).into())),
-    );
+    // The animation is purely cosmetic: it follows the line clear timer, which is ticked by the
+    // simulation and drives the end of the phase.
+    timers.line_clear.start();
 }
 
-fn animate_done(mut next_phase: ResMut<NextState<Phase>>, mut ended: EventReader<TimeRunnerEnded>) {
-    for ended in ended.read() {
-        if ended.is_completed() {
-            info!("Animation completed! Moving to Eliminate phase");
-            next_phase.set(Phase::Eliminate);
-        }
+fn animate_done(mut timers: ResMut<Timers>, mut next_phase: ResMut<NextState<Phase>>) {
+    if timers.line_clear.just_finished() {
+        info!("Animation completed! Moving to Eliminate phase");
+        timers.line_clear.pause();
+        next_phase.set(Phase::Eliminate);
     }
 }
 
@@ -425,7 +713,6 @@ fn eliminate(
     to_delete: Query<Entity, With<ToDelete>>,
     mut state: ResMut<GameState>,
     mut next_phase: ResMut<NextState<Phase>>,
-    mut event_writer: EventWriter<ScoreEvent>,
 ) {
     // Despawn entities that were deleted
     for e in to_delete.iter() {
@@ -435,7 +722,6 @@ fn eliminate(
 
     // Remove lines from the matrix
     let mut lines = state.matrix.full_lines();
-    let num_lines = lines.len();
     lines.reverse();
     for line in lines {
         info!("Removing line {line}");
@@ -451,23 +737,6 @@ fn eliminate(
         }
     }
 
-    match num_lines {
-        0 => (),
-        1 => {
-            event_writer.send(ScoreEvent::Single);
-        }
-        2 => {
-            event_writer.send(ScoreEvent::Double);
-        }
-        3 => {
-            event_writer.send(ScoreEvent::Triple);
-        }
-        4 => {
-            event_writer.send(ScoreEvent::Tetris);
-        }
-        n => warn!("How did we complete {n} lines?!?"),
-    }
-
     next_phase.set(Phase::Generation);
 }
 
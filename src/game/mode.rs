@@ -0,0 +1,180 @@
+//! The different ways to play, and how a game ends.
+
+use bevy::prelude::*;
+use num_enum::TryFromPrimitive;
+
+use crate::{args::Args, screen::InGame, SimulationSet};
+
+use super::{
+    score::Score, stats::GameStats, GameConfig, GameState, Phase, SimulationTick, TICK_RATE,
+};
+
+/// Number of lines to clear in Marathon mode.
+const MARATHON_LINES: u64 = 150;
+/// Number of lines to clear in Sprint mode.
+const SPRINT_LINES: u64 = 40;
+/// Duration of an Ultra game.
+const ULTRA_TICKS: u64 = 2 * 60 * TICK_RATE as u64;
+
+pub fn plugin(app: &mut App) {
+    app.add_event::<GameOver>()
+        .add_systems(
+            FixedUpdate,
+            check_goal
+                .after(super::score::update)
+                .after(super::stats::count_pieces)
+                .after(super::stats::count_clears)
+                .run_if(
+                    in_state(InGame)
+                        .and_then(not(in_state(Phase::Noop)))
+                        .and_then(not(in_state(Phase::Completion))),
+                )
+                .in_set(SimulationSet::React),
+        )
+        .add_systems(OnEnter(Phase::Completion), finish_game);
+
+    if let Some(name) = app.world().resource::<Args>().mode.clone() {
+        match name.parse::<GameMode>() {
+            Ok(mode) => app.world_mut().resource_mut::<GameConfig>().mode = mode,
+            Err(_) => error!("Unknown game mode {name}"),
+        }
+    }
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    Default,
+    TryFromPrimitive,
+    strum::Display,
+    strum::EnumString,
+)]
+#[strum(ascii_case_insensitive)]
+#[repr(u8)]
+pub enum GameMode {
+    /// Clear 150 lines, with the pieces falling faster every 10 lines.
+    #[default]
+    Marathon,
+    /// Clear 40 lines as fast as possible.
+    Sprint,
+    /// Score as many points as possible in 2 minutes.
+    Ultra,
+    /// Reach the goal of a puzzle.
+    Puzzle,
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 3] = [GameMode::Marathon, GameMode::Sprint, GameMode::Ultra];
+
+    /// Whether the goal of the mode has been reached. Puzzles have their own goals.
+    pub fn is_complete(&self, score: &Score, tick: u64) -> bool {
+        if let Some(lines) = self.line_goal() {
+            return score.lines() >= lines;
+        }
+        self.time_limit().is_some_and(|limit| tick >= limit)
+    }
+
+    /// Number of lines to clear to finish the game, if any.
+    pub fn line_goal(&self) -> Option<u64> {
+        match self {
+            GameMode::Marathon => Some(MARATHON_LINES),
+            GameMode::Sprint => Some(SPRINT_LINES),
+            GameMode::Ultra | GameMode::Puzzle => None,
+        }
+    }
+
+    /// Duration of the game in ticks, if it is timed.
+    pub fn time_limit(&self) -> Option<u64> {
+        match self {
+            GameMode::Ultra => Some(ULTRA_TICKS),
+            _ => None,
+        }
+    }
+
+    /// Whether results are ranked by time rather than by score. Only games that reached the goal
+    /// are ranked in that case.
+    pub fn ranked_by_time(&self) -> bool {
+        matches!(self, GameMode::Sprint)
+    }
+
+    /// Whether the best results of the mode are kept in a high-score table.
+    pub fn has_high_scores(&self) -> bool {
+        GameMode::ALL.contains(self)
+    }
+}
+
+/// How a game went.
+#[derive(Debug, Clone)]
+pub struct GameResult {
+    pub mode: GameMode,
+    /// Whether the goal of the mode was reached, as opposed to topping out.
+    pub completed: bool,
+    pub score: u64,
+    pub lines: u64,
+    pub level: u64,
+    /// Duration of the game.
+    pub ticks: u64,
+    pub seed: u64,
+    pub stats: GameStats,
+}
+
+/// Sent when a game ends.
+#[derive(Event, Debug, Clone)]
+pub struct GameOver(pub GameResult);
+
+/// Whether the goal of the game, either a puzzle's or the mode's, has been reached.
+fn is_complete(config: &GameConfig, score: &Score, stats: &GameStats, tick: u64) -> bool {
+    match config.goal {
+        Some(goal) => goal.is_reached(score, stats),
+        None => config.mode.is_complete(score, tick),
+    }
+}
+
+fn check_goal(
+    config: Res<GameConfig>,
+    score: Res<Score>,
+    stats: Res<GameStats>,
+    tick: Res<SimulationTick>,
+    mut next_phase: ResMut<NextState<Phase>>,
+) {
+    if is_complete(&config, &score, &stats, **tick) {
+        info!("{} goal reached!", config.mode);
+        next_phase.set(Phase::Completion);
+        return;
+    }
+    // The lines the last piece clears are counted on the tick it locks, so they've been checked
+    // against the goal already
+    let out_of_pieces = config
+        .goal
+        .is_some_and(|goal| goal.is_out_of_pieces(config.setup.as_ref(), &stats));
+    if out_of_pieces {
+        info!("Out of pieces!");
+        next_phase.set(Phase::Completion);
+    }
+}
+
+fn finish_game(
+    config: Res<GameConfig>,
+    state: Res<GameState>,
+    score: Res<Score>,
+    stats: Res<GameStats>,
+    tick: Res<SimulationTick>,
+    mut events: EventWriter<GameOver>,
+) {
+    let result = GameResult {
+        mode: config.mode,
+        completed: is_complete(&config, &score, &stats, **tick),
+        score: score.points(),
+        lines: score.lines(),
+        level: score.level(),
+        ticks: **tick,
+        seed: state.seed,
+        stats: stats.clone(),
+    };
+    info!("Game over: {result:?}");
+    events.send(GameOver(result));
+}
@@ -0,0 +1,52 @@
+//! Goals of puzzles: hand-made positions to solve with a given set of pieces.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::{clear::Spin, fumen::Setup, score::Score, stats::GameStats};
+
+/// What must be done to solve a puzzle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Goal {
+    ClearLines(u32),
+    TSpinDouble,
+    PerfectClear,
+    /// Place the given number of pieces without topping out.
+    Survive(u32),
+}
+
+impl Goal {
+    pub fn is_reached(&self, score: &Score, stats: &GameStats) -> bool {
+        match self {
+            Goal::ClearLines(lines) => score.lines() >= *lines as u64,
+            Goal::TSpinDouble => stats.clears(Spin::Full, 2) > 0,
+            Goal::PerfectClear => stats.perfect_clears > 0,
+            Goal::Survive(pieces) => stats.pieces >= *pieces,
+        }
+    }
+
+    /// Whether all the pieces the puzzle gives have been played.
+    ///
+    /// Once the pieces of the setup run out, the game goes on with random pieces, which only
+    /// count for survival goals.
+    pub fn is_out_of_pieces(&self, setup: Option<&Setup>, stats: &GameStats) -> bool {
+        let Some(setup) = setup else {
+            return false;
+        };
+        let pieces = setup.queue.len() + setup.hold.is_some() as usize;
+        !matches!(self, Goal::Survive(_)) && stats.pieces as usize >= pieces
+    }
+}
+
+impl fmt::Display for Goal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Goal::ClearLines(1) => write!(f, "Clear a line"),
+            Goal::ClearLines(lines) => write!(f, "Clear {lines} lines"),
+            Goal::TSpinDouble => write!(f, "Make a T-spin double"),
+            Goal::PerfectClear => write!(f, "Make a perfect clear"),
+            Goal::Survive(pieces) => write!(f, "Place {pieces} pieces"),
+        }
+    }
+}
@@ -0,0 +1,589 @@
+//! Recording games, and playing them back.
+//!
+//! Since the simulation is deterministic, a replay only needs the parameters the game was started
+//! with and the input of every tick. Playback feeds the recorded inputs to the simulation instead of
+//! the player's.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use bevy::{
+    app::{AppExit, FixedMain},
+    prelude::*,
+};
+
+use crate::{
+    model::{Cell, Pos, Randomizer, TetriminoKind},
+    screen::Screen,
+    settings::Handling,
+    storage::{self, invalid_data, read_len, read_varint, take, write_varint},
+    SimulationSet,
+};
+
+use super::{
+    fumen::Setup,
+    input::{InputFrame, PlayerInput},
+    mode::GameMode,
+    puzzle::Goal,
+    simulation_running,
+    snapshot::GameSnapshot,
+    GameConfig, GameState, SimulationControl, SimulationTick, MATRIX_HEIGHT, MATRIX_WIDTH,
+    TICK_RATE,
+};
+
+const MAGIC: &[u8; 4] = b"BTRP";
+const VERSION: u8 = 1;
+/// Marks an empty hold slot, and a garbage cell.
+const NO_PIECE: u8 = 0xff;
+pub const EXTENSION: &str = "btr";
+/// How often a snapshot is taken during playback, so that seeking doesn't need to resimulate the
+/// whole game.
+const CHECKPOINT_INTERVAL: u64 = 5 * TICK_RATE as u64;
+/// Most ticks resimulated in a single frame when seeking: longer seeks are spread over several
+/// frames rather than freezing the game.
+const SEEK_TICKS_PER_FRAME: u64 = 10 * TICK_RATE as u64;
+/// Longest game that can be replayed: longer recordings are rejected as corrupt when read, rather
+/// than allocating the memory they claim to need.
+const MAX_TICKS: u64 = 6 * 60 * 60 * TICK_RATE as u64;
+/// Longest queue of pieces in a setup.
+const MAX_QUEUE_LEN: usize = 1024;
+
+pub fn plugin(app: &mut App) {
+    app.init_resource::<ReplayRecorder>()
+        .add_systems(OnEnter(Screen::Gameplay), start_recording)
+        .add_systems(
+            FixedUpdate,
+            record_frame
+                .run_if(in_state(Screen::Gameplay))
+                .in_set(SimulationSet::React),
+        )
+        .add_systems(OnExit(Screen::Gameplay), save_recording)
+        .add_systems(
+            Last,
+            save_recording.run_if(in_state(Screen::Gameplay).and_then(on_event::<AppExit>())),
+        )
+        .add_systems(OnEnter(Screen::Replay), start_playback)
+        .add_systems(OnExit(Screen::Replay), stop_playback)
+        .add_systems(
+            FixedPreUpdate,
+            initial_checkpoint.run_if(in_state(Screen::Replay)),
+        )
+        .add_systems(
+            FixedUpdate,
+            feed_input
+                .run_if(in_state(Screen::Replay))
+                .in_set(SimulationSet::RecordInput),
+        )
+        .add_systems(
+            FixedPostUpdate,
+            checkpoint.run_if(in_state(Screen::Replay).and_then(simulation_running)),
+        )
+        .add_systems(Update, seek.run_if(in_state(Screen::Replay)));
+}
+
+/// Directory where replays are saved.
+pub fn replay_dir() -> PathBuf {
+    storage::data_dir().join("replays")
+}
+
+/// A recorded game.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+    pub seed: u64,
+    pub tick_rate: u16,
+    pub mode: GameMode,
+    pub setup: Option<Setup>,
+    pub finesse_training: bool,
+    pub goal: Option<Goal>,
+    pub randomizer: Randomizer,
+    pub handling: Handling,
+    /// The input of every tick of the game.
+    pub inputs: Vec<InputFrame>,
+}
+
+impl Replay {
+    /// Number of ticks in the replay.
+    pub fn len(&self) -> u64 {
+        self.inputs.len() as u64
+    }
+
+    /// The config to start the game with in order to play it back.
+    pub fn config(&self) -> GameConfig {
+        GameConfig {
+            seed: Some(self.seed),
+            mode: self.mode,
+            setup: self.setup.clone(),
+            finesse_training: self.finesse_training,
+            goal: self.goal,
+            randomizer: self.randomizer,
+            handling: self.handling,
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        if let Some(dir) = path.as_ref().parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_bytes())
+    }
+
+    /// Encode the replay. The inputs are run-length encoded, as they rarely change from one tick
+    /// to the next.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut runs: Vec<(InputFrame, u64)> = Vec::new();
+        for frame in &self.inputs {
+            match runs.last_mut() {
+                Some((last, count)) if last == frame => *count += 1,
+                _ => runs.push((*frame, 1)),
+            }
+        }
+
+        let mut bytes = Vec::with_capacity(32 + runs.len() * 2);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.extend_from_slice(&self.tick_rate.to_le_bytes());
+        bytes.push(self.mode as u8);
+        write_setup(&mut bytes, self.setup.as_ref());
+        bytes.push(self.finesse_training as u8);
+        write_goal(&mut bytes, self.goal);
+        bytes.push(self.randomizer as u8);
+        bytes.extend_from_slice(&self.handling.lock_delay.to_le_bytes());
+        bytes.push(self.handling.soft_drop_factor);
+        bytes.extend_from_slice(&self.handling.line_clear_delay.to_le_bytes());
+        write_varint(&mut bytes, runs.len() as u64);
+        for (frame, count) in runs {
+            bytes.push(frame.bits());
+            write_varint(&mut bytes, count);
+        }
+        bytes
+    }
+
+    pub fn from_bytes(mut bytes: &[u8]) -> io::Result<Self> {
+        if take(&mut bytes, MAGIC.len())? != MAGIC {
+            return Err(invalid_data("not a replay file"));
+        }
+        let version = take(&mut bytes, 1)?[0];
+        if version != VERSION {
+            return Err(invalid_data(format!(
+                "unsupported replay version {version}"
+            )));
+        }
+        let seed = u64::from_le_bytes(take(&mut bytes, 8)?.try_into().unwrap());
+        let tick_rate = u16::from_le_bytes(take(&mut bytes, 2)?.try_into().unwrap());
+        let mode = take(&mut bytes, 1)?[0];
+        let mode = GameMode::try_from(mode)
+            .map_err(|_| invalid_data(format!("invalid game mode {mode}")))?;
+        let setup = read_setup(&mut bytes)?;
+        let finesse_training = take(&mut bytes, 1)?[0] != 0;
+        let goal = read_goal(&mut bytes)?;
+        let randomizer = take(&mut bytes, 1)?[0];
+        let randomizer = Randomizer::try_from(randomizer)
+            .map_err(|_| invalid_data(format!("invalid randomizer {randomizer}")))?;
+        let handling = Handling {
+            lock_delay: u16::from_le_bytes(take(&mut bytes, 2)?.try_into().unwrap()),
+            soft_drop_factor: take(&mut bytes, 1)?[0],
+            line_clear_delay: u16::from_le_bytes(take(&mut bytes, 2)?.try_into().unwrap()),
+        };
+
+        let mut inputs = Vec::new();
+        for _ in 0..read_varint(&mut bytes)? {
+            let frame = InputFrame::from_bits(take(&mut bytes, 1)?[0]);
+            let count = read_varint(&mut bytes)?;
+            if (inputs.len() as u64).saturating_add(count) > MAX_TICKS {
+                return Err(invalid_data("replay is too long"));
+            }
+            inputs.extend(std::iter::repeat_n(frame, count as usize));
+        }
+
+        Ok(Self {
+            seed,
+            tick_rate,
+            mode,
+            setup,
+            finesse_training,
+            goal,
+            randomizer,
+            handling,
+            inputs,
+        })
+    }
+}
+
+fn write_setup(bytes: &mut Vec<u8>, setup: Option<&Setup>) {
+    let Some(setup) = setup else {
+        bytes.push(0);
+        return;
+    };
+    bytes.push(1);
+    write_varint(bytes, setup.cells.len() as u64);
+    for (pos, cell) in &setup.cells {
+        bytes.push(pos.x as u8);
+        bytes.push(pos.y as u8);
+        bytes.push(match cell {
+            Cell::Mino(kind) => *kind as u8,
+            Cell::Garbage => NO_PIECE,
+        });
+    }
+    bytes.push(setup.hold.map_or(NO_PIECE, |kind| kind as u8));
+    write_varint(bytes, setup.queue.len() as u64);
+    bytes.extend(setup.queue.iter().map(|kind| *kind as u8));
+}
+
+fn read_setup(bytes: &mut &[u8]) -> io::Result<Option<Setup>> {
+    if take(bytes, 1)?[0] == 0 {
+        return Ok(None);
+    }
+    let mut setup = Setup::default();
+    let max_cells = MATRIX_WIDTH as usize * MATRIX_HEIGHT as usize;
+    for _ in 0..read_len(bytes, max_cells)? {
+        let [x, y, cell] = take(bytes, 3)?.try_into().unwrap();
+        let cell = match cell {
+            NO_PIECE => Cell::Garbage,
+            kind => Cell::Mino(read_kind(kind)?),
+        };
+        setup.cells.push((Pos::new(x as i8, y as i8), cell));
+    }
+    setup.hold = match take(bytes, 1)?[0] {
+        NO_PIECE => None,
+        kind => Some(read_kind(kind)?),
+    };
+    for _ in 0..read_len(bytes, MAX_QUEUE_LEN)? {
+        setup.queue.push(read_kind(take(bytes, 1)?[0])?);
+    }
+    Ok(Some(setup))
+}
+
+fn write_goal(bytes: &mut Vec<u8>, goal: Option<Goal>) {
+    match goal {
+        None => bytes.push(0),
+        Some(Goal::ClearLines(lines)) => {
+            bytes.push(1);
+            write_varint(bytes, lines as u64);
+        }
+        Some(Goal::TSpinDouble) => bytes.push(2),
+        Some(Goal::PerfectClear) => bytes.push(3),
+        Some(Goal::Survive(pieces)) => {
+            bytes.push(4);
+            write_varint(bytes, pieces as u64);
+        }
+    }
+}
+
+fn read_goal(bytes: &mut &[u8]) -> io::Result<Option<Goal>> {
+    let goal = match take(bytes, 1)?[0] {
+        0 => return Ok(None),
+        1 => Goal::ClearLines(read_varint(bytes)? as u32),
+        2 => Goal::TSpinDouble,
+        3 => Goal::PerfectClear,
+        4 => Goal::Survive(read_varint(bytes)? as u32),
+        tag => return Err(invalid_data(format!("invalid puzzle goal {tag}"))),
+    };
+    Ok(Some(goal))
+}
+
+fn read_kind(byte: u8) -> io::Result<TetriminoKind> {
+    TetriminoKind::try_from(byte).map_err(|_| invalid_data(format!("invalid piece {byte}")))
+}
+
+/// Records the input of the game being played.
+#[derive(Resource, Default)]
+struct ReplayRecorder {
+    recording: bool,
+    inputs: Vec<InputFrame>,
+}
+
+fn start_recording(mut recorder: ResMut<ReplayRecorder>) {
+    recorder.recording = true;
+    recorder.inputs.clear();
+}
+
+fn record_frame(
+    mut recorder: ResMut<ReplayRecorder>,
+    input: Res<PlayerInput>,
+    tick: Res<SimulationTick>,
+) {
+    // The game might have been rolled back, in which case the inputs after the current tick are
+    // overwritten.
+    recorder.inputs.truncate(tick.saturating_sub(1) as usize);
+    recorder.inputs.push(input.current());
+}
+
+fn save_recording(
+    mut recorder: ResMut<ReplayRecorder>,
+    state: Res<GameState>,
+    config: Res<GameConfig>,
+) {
+    if !recorder.recording {
+        return;
+    }
+    recorder.recording = false;
+    if recorder.inputs.is_empty() {
+        return;
+    }
+
+    if recorder.inputs.len() as u64 > MAX_TICKS {
+        warn!("The game is too long to be replayed, not saving it");
+        recorder.inputs.clear();
+        return;
+    }
+
+    let replay = Replay {
+        seed: state.seed,
+        tick_rate: TICK_RATE as u16,
+        mode: config.mode,
+        setup: config.setup.clone(),
+        finesse_training: config.finesse_training,
+        goal: config.goal,
+        randomizer: config.randomizer,
+        handling: config.handling,
+        inputs: std::mem::take(&mut recorder.inputs),
+    };
+    let path = storage::timestamped_path(&replay_dir(), EXTENSION);
+    match replay.save(&path) {
+        Ok(()) => info!("Saved replay to {}", path.display()),
+        Err(e) => warn!("Failed to save replay to {}: {e}", path.display()),
+    }
+}
+
+/// The replay being watched, and the state of its playback.
+#[derive(Resource)]
+pub struct ReplayPlayer {
+    pub replay: Replay,
+    /// Snapshots taken during playback, ordered by tick.
+    checkpoints: Vec<GameSnapshot>,
+    seek: Option<u64>,
+    /// The config that was in place before starting playback.
+    previous_config: Option<GameConfig>,
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            checkpoints: Vec::new(),
+            seek: None,
+            previous_config: None,
+        }
+    }
+
+    /// Jump to the given tick of the replay.
+    pub fn seek_to(&mut self, tick: u64) {
+        self.seek = Some(tick.min(self.replay.len()));
+    }
+
+    fn add_checkpoint(&mut self, snapshot: GameSnapshot) {
+        if let Err(index) = self
+            .checkpoints
+            .binary_search_by_key(&snapshot.tick, |checkpoint| checkpoint.tick)
+        {
+            self.checkpoints.insert(index, snapshot);
+        }
+    }
+
+    /// The most recent checkpoint at or before the given tick.
+    fn checkpoint_before(&self, tick: u64) -> Option<&GameSnapshot> {
+        self.checkpoints
+            .iter()
+            .rev()
+            .find(|checkpoint| checkpoint.tick <= tick)
+    }
+}
+
+fn start_playback(
+    player: Option<ResMut<ReplayPlayer>>,
+    mut config: ResMut<GameConfig>,
+    mut next_screen: ResMut<NextState<Screen>>,
+) {
+    let Some(mut player) = player else {
+        warn!("No replay to watch!");
+        next_screen.set(Screen::MainMenu);
+        return;
+    };
+    if player.replay.tick_rate != TICK_RATE as u16 {
+        warn!(
+            "Replay was recorded at {} ticks per second, it will not play back correctly",
+            player.replay.tick_rate
+        );
+    }
+    info!("Watching replay of {} ticks", player.replay.len());
+    player.previous_config = Some(std::mem::replace(&mut *config, player.replay.config()));
+    player.checkpoints.clear();
+}
+
+fn stop_playback(
+    mut commands: Commands,
+    player: Option<ResMut<ReplayPlayer>>,
+    mut config: ResMut<GameConfig>,
+) {
+    if let Some(previous) = player.and_then(|mut player| player.previous_config.take()) {
+        *config = previous;
+    }
+    commands.remove_resource::<ReplayPlayer>();
+}
+
+fn feed_input(
+    player: Res<ReplayPlayer>,
+    tick: Res<SimulationTick>,
+    mut input: ResMut<PlayerInput>,
+    mut control: ResMut<SimulationControl>,
+) {
+    match player.replay.inputs.get(tick.0 as usize) {
+        Some(frame) => input.advance(*frame),
+        None => {
+            // End of the replay: stop here
+            control.paused = true;
+            control.steps = 0;
+        }
+    }
+}
+
+/// Take a snapshot of the game before its first tick, so that seeking can always go back to the
+/// start.
+fn initial_checkpoint(world: &mut World) {
+    if world.resource::<SimulationTick>().0 == 0
+        && world.resource::<ReplayPlayer>().checkpoints.is_empty()
+    {
+        let snapshot = GameSnapshot::capture(world);
+        world
+            .resource_mut::<ReplayPlayer>()
+            .add_checkpoint(snapshot);
+    }
+}
+
+fn checkpoint(world: &mut World) {
+    if world
+        .resource::<SimulationTick>()
+        .0
+        .is_multiple_of(CHECKPOINT_INTERVAL)
+    {
+        let snapshot = GameSnapshot::capture(world);
+        world
+            .resource_mut::<ReplayPlayer>()
+            .add_checkpoint(snapshot);
+    }
+}
+
+/// Jump to the requested tick, by restoring the closest checkpoint and resimulating from there.
+///
+/// At most [`SEEK_TICKS_PER_FRAME`] ticks are resimulated per frame, the seek carrying on over the
+/// next frames until the tick is reached.
+fn seek(world: &mut World) {
+    let Some(target) = world.resource_mut::<ReplayPlayer>().seek.take() else {
+        return;
+    };
+    let current = world.resource::<SimulationTick>().0;
+
+    let checkpoint = world
+        .resource::<ReplayPlayer>()
+        .checkpoint_before(target)
+        .filter(|checkpoint| target < current || checkpoint.tick > current)
+        .cloned();
+    if let Some(checkpoint) = checkpoint {
+        checkpoint.restore(world);
+    }
+
+    let from = world.resource::<SimulationTick>().0;
+    let ticks = target.saturating_sub(from).min(SEEK_TICKS_PER_FRAME);
+    info!("Seeking to tick {target}: resimulating {ticks} ticks from tick {from}");
+    world.resource_mut::<SimulationControl>().steps += ticks as u32;
+    for _ in 0..ticks {
+        world.run_schedule(FixedMain);
+    }
+
+    // Carry on next frame, unless another tick was requested meanwhile or the game is over
+    let reached = world.resource::<SimulationTick>().0;
+    if reached > from && reached < target {
+        world
+            .resource_mut::<ReplayPlayer>()
+            .seek
+            .get_or_insert(target);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frames(bits: &[(u8, usize)]) -> Vec<InputFrame> {
+        bits.iter()
+            .flat_map(|(bits, count)| std::iter::repeat_n(InputFrame::from_bits(*bits), *count))
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let replay = Replay {
+            seed: 0x0123_4567_89ab_cdef,
+            tick_rate: TICK_RATE as u16,
+            mode: GameMode::Sprint,
+            setup: Some(Setup {
+                cells: vec![
+                    (Pos::new(0, 0), Cell::Garbage),
+                    (Pos::new(9, 3), Cell::Mino(TetriminoKind::T)),
+                ],
+                hold: Some(TetriminoKind::I),
+                queue: vec![TetriminoKind::S, TetriminoKind::Z],
+            }),
+            finesse_training: true,
+            goal: Some(Goal::ClearLines(4)),
+            randomizer: Randomizer::Classic,
+            handling: Handling {
+                lock_delay: 300,
+                soft_drop_factor: 40,
+                line_clear_delay: 250,
+            },
+            inputs: frames(&[(0, 200), (0b101, 3), (0, 1000)]),
+        };
+        assert_eq!(Replay::from_bytes(&replay.to_bytes()).unwrap(), replay);
+    }
+
+    #[test]
+    fn reject_unknown_version() {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION + 1);
+        bytes.extend_from_slice(&42u64.to_le_bytes());
+        let error = Replay::from_bytes(&bytes).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn reject_corrupt_lengths() {
+        let replay = Replay {
+            seed: 1,
+            tick_rate: TICK_RATE as u16,
+            mode: GameMode::Marathon,
+            setup: None,
+            finesse_training: false,
+            goal: None,
+            randomizer: Randomizer::SevenBag,
+            handling: Handling::default(),
+            inputs: Vec::new(),
+        };
+        // A single run of inputs claiming to last forever
+        let mut bytes = replay.to_bytes();
+        bytes.pop();
+        write_varint(&mut bytes, 1);
+        bytes.push(0);
+        write_varint(&mut bytes, u64::MAX);
+        let error = Replay::from_bytes(&bytes).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        // Runs adding up to too many ticks
+        let mut bytes = replay.to_bytes();
+        bytes.pop();
+        write_varint(&mut bytes, 2);
+        for _ in 0..2 {
+            bytes.push(0);
+            write_varint(&mut bytes, MAX_TICKS / 2 + 1);
+        }
+        let error = Replay::from_bytes(&bytes).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
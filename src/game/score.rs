@@ -1,28 +1,69 @@
 use bevy::prelude::*;
 
-use crate::{screen::Screen, AppSet};
+use crate::{screen::InGame, SimulationSet};
+
+use super::timers::Timers;
+
+/// Number of lines to clear to go up one level.
+const LINES_PER_LEVEL: u64 = 10;
 
 pub fn plugin(app: &mut App) {
     app.init_resource::<Score>()
         .register_type::<Score>()
         .add_event::<ScoreEvent>()
-        .add_systems(OnEnter(Screen::Gameplay), setup)
+        .add_event::<LevelUp>()
+        .add_systems(OnEnter(InGame), setup)
         .add_systems(
-            Update,
-            update
-                .run_if(in_state(Screen::Gameplay))
-                .in_set(AppSet::Update),
+            FixedUpdate,
+            (update, update_gravity)
+                .chain()
+                .run_if(in_state(InGame))
+                .in_set(SimulationSet::React),
         )
-        .add_systems(OnExit(Screen::Gameplay), cleanup);
+        .add_systems(OnExit(InGame), cleanup);
 }
 
-#[derive(Default, Resource, Reflect)]
+#[derive(Default, Clone, Resource, Reflect)]
 pub struct Score {
+    start_level: u64,
     level: u64,
     score: u64,
+    lines: u64,
 }
 
 impl Score {
+    pub fn new(level: u64) -> Self {
+        Self {
+            start_level: level,
+            level,
+            score: 0,
+            lines: 0,
+        }
+    }
+
+    pub fn points(&self) -> u64 {
+        self.score
+    }
+
+    pub fn level(&self) -> u64 {
+        self.level
+    }
+
+    pub fn lines(&self) -> u64 {
+        self.lines
+    }
+
+    /// Number of lines left to clear before going up a level.
+    pub fn lines_to_next_level(&self) -> u64 {
+        LINES_PER_LEVEL - self.lines % LINES_PER_LEVEL
+    }
+
+    /// Count cleared lines, going up one level every `LINES_PER_LEVEL` lines.
+    fn add_lines(&mut self, lines: u64) {
+        self.lines += lines;
+        self.level = self.start_level + self.lines / LINES_PER_LEVEL;
+    }
+
     /// Add the given number of points applying the level factor
     pub fn add_with_mult(&mut self, points: u64) {
         self.score += points * self.level;
@@ -31,19 +72,46 @@ impl Score {
     pub fn handle_event(&mut self, event: &ScoreEvent) {
         match event {
             ScoreEvent::LevelStart(level) => {
-                self.level = *level as u64;
-                self.score = 0;
+                *self = Self::new(*level as u64);
+            }
+            ScoreEvent::Single => {
+                self.add_with_mult(100);
+                self.add_lines(1);
+            }
+            ScoreEvent::Double => {
+                self.add_with_mult(300);
+                self.add_lines(2);
+            }
+            ScoreEvent::Triple => {
+                self.add_with_mult(500);
+                self.add_lines(3);
+            }
+            ScoreEvent::Tetris => {
+                self.add_with_mult(800);
+                self.add_lines(4);
             }
-            ScoreEvent::Single => self.add_with_mult(100),
-            ScoreEvent::Double => self.add_with_mult(300),
-            ScoreEvent::Triple => self.add_with_mult(500),
-            ScoreEvent::Tetris => self.add_with_mult(800),
             ScoreEvent::MiniTSpin => self.add_with_mult(100),
-            ScoreEvent::MiniTSpinSingle => self.add_with_mult(200),
+            ScoreEvent::MiniTSpinSingle => {
+                self.add_with_mult(200);
+                self.add_lines(1);
+            }
+            ScoreEvent::MiniTSpinDouble => {
+                self.add_with_mult(400);
+                self.add_lines(2);
+            }
             ScoreEvent::TSpin => self.add_with_mult(400),
-            ScoreEvent::TSpinSingle => self.add_with_mult(800),
-            ScoreEvent::TSpinDouble => self.add_with_mult(1200),
-            ScoreEvent::TSpinTriple => self.add_with_mult(1600),
+            ScoreEvent::TSpinSingle => {
+                self.add_with_mult(800);
+                self.add_lines(1);
+            }
+            ScoreEvent::TSpinDouble => {
+                self.add_with_mult(1200);
+                self.add_lines(2);
+            }
+            ScoreEvent::TSpinTriple => {
+                self.add_with_mult(1600);
+                self.add_lines(3);
+            }
             ScoreEvent::SoftDrop(n) => self.score += *n as u64,
             ScoreEvent::HardDrop(n) => self.score += *n as u64 * 2,
         }
@@ -64,6 +132,7 @@ pub enum ScoreEvent {
     Tetris,
     MiniTSpin,
     MiniTSpinSingle,
+    MiniTSpinDouble,
     TSpin,
     TSpinSingle,
     TSpinDouble,
@@ -72,14 +141,35 @@ pub enum ScoreEvent {
     HardDrop(u8),
 }
 
-fn setup() {}
+/// Sent when enough lines have been cleared to go up a level.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct LevelUp;
+
+fn setup(mut commands: Commands) {
+    commands.insert_resource(Score::new(1));
+}
 
 fn cleanup(mut commands: Commands) {
     commands.remove_resource::<Score>();
 }
 
-fn update(mut score: ResMut<Score>, mut events: EventReader<ScoreEvent>) {
+pub(super) fn update(
+    mut score: ResMut<Score>,
+    mut events: EventReader<ScoreEvent>,
+    mut level_ups: EventWriter<LevelUp>,
+) {
     for event in events.read() {
-        score.handle_event(event)
+        let level = score.level();
+        score.handle_event(event);
+        if !matches!(event, ScoreEvent::LevelStart(_)) && score.level() > level {
+            level_ups.send(LevelUp);
+        }
+    }
+}
+
+/// Make pieces fall faster as the level goes up.
+fn update_gravity(score: Res<Score>, mut timers: ResMut<Timers>) {
+    if score.is_changed() {
+        timers.fall.set_level(score.level());
     }
 }
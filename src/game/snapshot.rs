@@ -0,0 +1,311 @@
+//! Snapshots of the full simulation state.
+//!
+//! A snapshot contains everything needed to resume the game from a given tick: restoring it and
+//! feeding the same inputs again resimulates exactly the same game. This is what rollback (e.g. for
+//! netcode) is built on.
+
+use std::collections::VecDeque;
+
+use bevy::{ecs::world::Command, prelude::*};
+
+use crate::{
+    model::{Bag, Cell, Pos, Tetrimino, TetriminoKind},
+    screen::InGame,
+    settings::Settings,
+};
+
+use super::{
+    clear::Spin,
+    input::{PieceInputs, PlayerInput},
+    score::Score,
+    simulation_running,
+    spawners::{
+        hold_zone::HoldTetriminoZone,
+        next_zone::{spawn_previews, NextTetriminoZone},
+        piece::CurrentPiece,
+        Positioned, SpawnPiece,
+    },
+    stats::GameStats,
+    timers::Timers,
+    Block, BlockBundle, GameState, Phase, SimulationTick, ToDelete, TICK_RATE,
+};
+
+/// How many ticks worth of snapshots are kept around for rolling back.
+const HISTORY_LEN: usize = 2 * TICK_RATE as usize;
+
+pub fn plugin(app: &mut App) {
+    app.init_resource::<SnapshotHistory>()
+        .add_systems(OnEnter(InGame), clear_history)
+        .add_systems(
+            FixedPostUpdate,
+            record_snapshot.run_if(in_state(InGame).and_then(simulation_running)),
+        );
+}
+
+/// The state of the game at the end of a given tick.
+#[derive(Clone)]
+pub struct GameSnapshot {
+    pub tick: u64,
+    phase: Phase,
+    /// The phase the tick ended up queuing, which is entered at the start of the next one.
+    next_phase: Option<Phase>,
+    blocks: Vec<(Pos, Cell)>,
+    current: Option<(Tetrimino, Pos)>,
+    bag: Bag,
+    hold: Option<TetriminoKind>,
+    can_hold: bool,
+    inputs: PieceInputs,
+    spin: Spin,
+    combo: u32,
+    back_to_back: u32,
+    timers: Timers,
+    score: Score,
+    stats: GameStats,
+    input: PlayerInput,
+}
+
+impl GameSnapshot {
+    pub fn capture(world: &mut World) -> Self {
+        let current = world
+            .query_filtered::<(&Tetrimino, &Positioned), With<CurrentPiece>>()
+            .get_single(world)
+            .ok()
+            .map(|(tetrimino, pos)| (*tetrimino, **pos));
+        let state = world.resource::<GameState>();
+        let blocks = state
+            .matrix
+            .iter_non_empty()
+            .filter_map(|(pos, entity)| Some((pos, world.get::<Block>(entity)?.0)))
+            .collect();
+
+        Self {
+            tick: world.resource::<SimulationTick>().0,
+            phase: *world.resource::<State<Phase>>().get(),
+            next_phase: match world.resource::<NextState<Phase>>() {
+                NextState::Pending(phase) => Some(*phase),
+                NextState::Unchanged => None,
+            },
+            blocks,
+            current,
+            bag: state.bag.clone(),
+            hold: state.hold,
+            can_hold: state.can_hold,
+            inputs: state.inputs,
+            spin: state.spin,
+            combo: state.combo,
+            back_to_back: state.back_to_back,
+            timers: world.resource::<Timers>().clone(),
+            score: world.resource::<Score>().clone(),
+            stats: world.resource::<GameStats>().clone(),
+            input: *world.resource::<PlayerInput>(),
+        }
+    }
+
+    pub fn restore(&self, world: &mut World) {
+        info!("Restoring snapshot of tick {}", self.tick);
+
+        // Throw away all the pieces and blocks, and rebuild them from the snapshot
+        let entities: Vec<Entity> = world
+            .query_filtered::<Entity, Or<(With<Tetrimino>, With<Block>)>>()
+            .iter(world)
+            .collect();
+        for entity in entities {
+            world.entity_mut(entity).despawn_recursive();
+        }
+
+        let root = {
+            let mut state = world.resource_mut::<GameState>();
+            state.matrix.clear();
+            state.bag = self.bag.clone();
+            state.hold = self.hold;
+            state.can_hold = self.can_hold;
+            state.inputs = self.inputs;
+            state.spin = self.spin;
+            state.combo = self.combo;
+            state.back_to_back = self.back_to_back;
+            state.matrix.root_entity
+        };
+        world.entity_mut(root).with_children(|children| {
+            for (pos, cell) in &self.blocks {
+                children.spawn(BlockBundle::new(*pos, *cell));
+            }
+        });
+        if self.phase == Phase::Animate {
+            let to_delete: Vec<Entity> = world
+                .resource::<GameState>()
+                .matrix
+                .entities_to_delete()
+                .collect();
+            for entity in to_delete {
+                world.entity_mut(entity).insert(ToDelete);
+            }
+        }
+
+        if let Some((tetrimino, pos)) = self.current {
+            SpawnPiece::current(tetrimino)
+                .with_pos(pos)
+                .with_parent(root)
+                .apply(world);
+            let ghost_pos = world
+                .resource::<GameState>()
+                .matrix
+                .lowest_valid_pos(&tetrimino, &pos);
+            SpawnPiece::ghost(tetrimino, ghost_pos)
+                .with_parent(root)
+                .apply(world);
+        }
+        let next_zone = world
+            .query_filtered::<Entity, With<NextTetriminoZone>>()
+            .single(world);
+        let previews = world.resource::<Settings>().visuals.previews;
+        spawn_previews(world, next_zone, &self.bag, previews);
+        if let Some(hold) = self.hold {
+            let hold_zone = world
+                .query_filtered::<Entity, With<HoldTetriminoZone>>()
+                .single(world);
+            SpawnPiece::hold(hold.into())
+                .with_parent(hold_zone)
+                .apply(world);
+        }
+
+        world.insert_resource(self.timers.clone());
+        world.insert_resource(self.score.clone());
+        world.insert_resource(self.stats.clone());
+        world.insert_resource(self.input);
+        world.insert_resource(SimulationTick(self.tick));
+        // Entering the phase would re-run its side effects (spawning pieces, resetting timers...),
+        // which are already accounted for in the snapshot, so the state is set directly. The phase
+        // that was queued still has to be entered though.
+        world.insert_resource(State::new(self.phase));
+        world.insert_resource(match self.next_phase {
+            Some(phase) => NextState::Pending(phase),
+            None => NextState::Unchanged,
+        });
+    }
+}
+
+/// The snapshots of the last few ticks.
+#[derive(Resource, Default)]
+pub struct SnapshotHistory(VecDeque<GameSnapshot>);
+
+#[cfg_attr(not(feature = "dev"), allow(dead_code))]
+impl SnapshotHistory {
+    pub fn get(&self, tick: u64) -> Option<&GameSnapshot> {
+        self.0.iter().find(|snapshot| snapshot.tick == tick)
+    }
+
+    fn push(&mut self, snapshot: GameSnapshot) {
+        if self.0.len() == HISTORY_LEN {
+            self.0.pop_front();
+        }
+        self.0.push_back(snapshot);
+    }
+
+    /// Forget about all the snapshots after the given tick.
+    fn truncate_after(&mut self, tick: u64) {
+        self.0.retain(|snapshot| snapshot.tick <= tick);
+    }
+}
+
+/// Roll the game back to the state it was in at the end of the given tick.
+///
+/// Only the last few ticks can be rolled back to (see [`SnapshotHistory`]).
+#[cfg_attr(not(feature = "dev"), allow(dead_code))]
+pub struct Rollback(pub u64);
+
+impl Command for Rollback {
+    fn apply(self, world: &mut World) {
+        let Some(snapshot) = world.resource::<SnapshotHistory>().get(self.0).cloned() else {
+            warn!("No snapshot for tick {} to roll back to", self.0);
+            return;
+        };
+        snapshot.restore(world);
+        world
+            .resource_mut::<SnapshotHistory>()
+            .truncate_after(self.0);
+    }
+}
+
+fn clear_history(mut history: ResMut<SnapshotHistory>) {
+    history.0.clear();
+}
+
+fn record_snapshot(world: &mut World) {
+    let snapshot = GameSnapshot::capture(world);
+    world.resource_mut::<SnapshotHistory>().push(snapshot);
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::app::FixedMain;
+
+    use crate::{args::Args, headless};
+
+    use super::*;
+
+    /// A game nobody plays, the pieces falling down on their own.
+    fn new_game() -> App {
+        let mut app = headless::new_app(0, &Args::default());
+        // Without the AI, the game reads the (absent) player's input
+        app.init_resource::<Gamepads>()
+            .init_resource::<Axis<GamepadAxis>>();
+        app
+    }
+
+    fn run(world: &mut World, ticks: u32) {
+        for _ in 0..ticks {
+            world.run_schedule(FixedMain);
+        }
+    }
+
+    /// What the game looks like, to tell whether two runs went the same way.
+    fn outline(
+        world: &mut World,
+    ) -> (
+        u64,
+        Phase,
+        Option<Phase>,
+        Vec<(Pos, Cell)>,
+        Option<(Tetrimino, Pos)>,
+        u32,
+    ) {
+        let snapshot = GameSnapshot::capture(world);
+        let mut blocks = snapshot.blocks;
+        blocks.sort_by_key(|(pos, _)| (pos.y, pos.x));
+        (
+            snapshot.tick,
+            snapshot.phase,
+            snapshot.next_phase,
+            blocks,
+            snapshot.current,
+            snapshot.stats.pieces,
+        )
+    }
+
+    #[test]
+    fn round_trip_in_every_phase() {
+        let phases = [
+            Phase::Generation,
+            Phase::Falling,
+            Phase::Lock,
+            Phase::Pattern,
+            Phase::Animate,
+            Phase::Eliminate,
+        ];
+        for phase in phases {
+            let mut app = new_game();
+            let world = app.world_mut();
+            // Let a few pieces fall, then end the tick with a transition queued
+            run(world, 300);
+            world.resource_mut::<NextState<Phase>>().set(phase);
+            let snapshot = GameSnapshot::capture(world);
+            assert_eq!(snapshot.next_phase, Some(phase));
+
+            run(world, 300);
+            let expected = outline(world);
+            snapshot.restore(world);
+            run(world, 300);
+            assert_eq!(outline(world), expected, "after restoring in {phase:?}");
+        }
+    }
+}
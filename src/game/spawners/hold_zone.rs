@@ -0,0 +1,30 @@
+use bevy::{
+    ecs::{system::RunSystemOnce, world::Command},
+    prelude::*,
+};
+
+use crate::{layout::LayoutSlot, screen::InGame};
+
+#[derive(Debug)]
+pub struct SpawnHoldZone;
+
+impl Command for SpawnHoldZone {
+    fn apply(self, world: &mut World) {
+        world.run_system_once_with(self, spawn);
+    }
+}
+
+fn spawn(In(_): In<SpawnHoldZone>, mut commands: Commands) {
+    // Held piece display zone
+    commands.spawn((
+        Name::new("Hold tetrimino zone"),
+        StateScoped(InGame),
+        LayoutSlot::HoldZone,
+        SpatialBundle::default(),
+        HoldTetriminoZone,
+    ));
+}
+
+/// The parent component of where the held piece is displayed
+#[derive(Component)]
+pub struct HoldTetriminoZone;
@@ -4,7 +4,12 @@ use bevy::{
     sprite::Anchor,
 };
 
-use crate::game::{GameState, MATRIX_WIDTH, SCALE};
+use crate::{
+    game::{GameState, MATRIX_WIDTH},
+    layout::LayoutSlot,
+    screen::InGame,
+    skin::Skinned,
+};
 
 #[derive(Debug)]
 pub struct SpawnMatrix;
@@ -20,16 +25,15 @@ fn spawn(In(_): In<SpawnMatrix>, mut commands: Commands, mut state: ResMut<GameS
     state.matrix.root_entity = commands
         .spawn((
             Name::new("Matrix"),
-            SpatialBundle {
-                transform: Transform::from_xyz(-10.0 * SCALE, -11.0 * SCALE, 1.0)
-                    .with_scale(Vec3::new(SCALE, SCALE, 1.0)),
-                ..default()
-            },
+            StateScoped(InGame),
+            LayoutSlot::Matrix,
+            SpatialBundle::default(),
         ))
         .with_children(|children| {
             // "floor"
             children.spawn((
                 Name::new("Bottom wall"),
+                Skinned::Wall,
                 SpriteBundle {
                     sprite: Sprite {
                         custom_size: Some(Vec2::new(MATRIX_WIDTH as f32, 1.0)),
@@ -44,6 +48,7 @@ fn spawn(In(_): In<SpawnMatrix>, mut commands: Commands, mut state: ResMut<GameS
             // "ceiling"
             children.spawn((
                 Name::new("Top wall"),
+                Skinned::Wall,
                 SpriteBundle {
                     sprite: Sprite {
                         custom_size: Some(Vec2::new(MATRIX_WIDTH as f32, 1.0)),
@@ -57,6 +62,7 @@ fn spawn(In(_): In<SpawnMatrix>, mut commands: Commands, mut state: ResMut<GameS
             // "left wall"
             children.spawn((
                 Name::new("Left wall"),
+                Skinned::Wall,
                 SpriteBundle {
                     sprite: Sprite {
                         custom_size: Some(Vec2::new(1.0, 24.0)),
@@ -70,6 +76,7 @@ fn spawn(In(_): In<SpawnMatrix>, mut commands: Commands, mut state: ResMut<GameS
             // "Right wall"
             children.spawn((
                 Name::new("Right wall"),
+                Skinned::Wall,
                 SpriteBundle {
                     sprite: Sprite {
                         custom_size: Some(Vec2::new(1.0, 24.0)),
@@ -3,7 +3,16 @@ use bevy::{
     prelude::*,
 };
 
-use crate::game::SCALE;
+use crate::{
+    layout::LayoutSlot,
+    model::{Bag, Pos},
+    screen::InGame,
+};
+
+use super::SpawnPiece;
+
+/// Vertical distance between the upcoming pieces, in cells.
+pub const PREVIEW_SPACING: i8 = 3;
 
 #[derive(Debug)]
 pub struct SpawnNextZone;
@@ -18,11 +27,9 @@ fn spawn(In(_): In<SpawnNextZone>, mut commands: Commands) {
     // Next-piece display zone
     commands.spawn((
         Name::new("Next tetrimino zone"),
-        SpatialBundle {
-            transform: Transform::from_xyz(100.0, 100.0, 1.0)
-                .with_scale(Vec3::new(SCALE, SCALE, 1.0)),
-            ..default()
-        },
+        StateScoped(InGame),
+        LayoutSlot::NextZone,
+        SpatialBundle::default(),
         NextTetriminoZone,
     ));
 }
@@ -30,3 +37,13 @@ fn spawn(In(_): In<SpawnNextZone>, mut commands: Commands) {
 /// The parent component of where the next piece is displayed
 #[derive(Component)]
 pub struct NextTetriminoZone;
+
+/// Spawn the given number of upcoming pieces in the next zone, the first one at the top.
+pub fn spawn_previews(world: &mut World, zone: Entity, bag: &Bag, count: u8) {
+    for (index, kind) in bag.peek(count.into()).into_iter().enumerate() {
+        SpawnPiece::next(kind.into())
+            .with_pos(Pos::new(0, -(index as i8) * PREVIEW_SPACING))
+            .with_parent(zone)
+            .apply(world);
+    }
+}
@@ -4,7 +4,10 @@ use bevy::{
     sprite::Anchor,
 };
 
-use crate::model::{Pos, Tetrimino};
+use crate::{
+    model::{Pos, Tetrimino},
+    skin::Skinned,
+};
 
 use super::{Positioned, INITIAL_POS};
 
@@ -12,7 +15,9 @@ use super::{Positioned, INITIAL_POS};
 pub enum PieceType {
     Current,
     Ghost,
+    Hint,
     Next,
+    Hold,
 }
 
 #[derive(Debug)]
@@ -32,13 +37,25 @@ impl SpawnPiece {
         Self(Entity::PLACEHOLDER, tetrimino, pos, PieceType::Ghost)
     }
 
+    pub fn hint(tetrimino: Tetrimino, pos: Pos) -> Self {
+        Self(Entity::PLACEHOLDER, tetrimino, pos, PieceType::Hint)
+    }
+
     pub fn next(tetrimino: Tetrimino) -> Self {
         Self(Entity::PLACEHOLDER, tetrimino, Pos::ZERO, PieceType::Next)
     }
 
+    pub fn hold(tetrimino: Tetrimino) -> Self {
+        Self(Entity::PLACEHOLDER, tetrimino, Pos::ZERO, PieceType::Hold)
+    }
+
     pub fn with_parent(self, parent: Entity) -> Self {
         Self(parent, self.1, self.2, self.3)
     }
+
+    pub fn with_pos(self, pos: Pos) -> Self {
+        Self(self.0, self.1, pos, self.3)
+    }
 }
 
 impl Command for SpawnPiece {
@@ -67,18 +84,29 @@ fn spawn(In(config): In<SpawnPiece>, mut commands: Commands) {
             PieceType::Ghost => {
                 builder.insert((Name::new("Ghost piece"), GhostPiece));
             }
+            PieceType::Hint => {
+                builder.insert((Name::new("Hint piece"), HintPiece));
+            }
             PieceType::Next => {
                 builder.insert(Name::new("Next piece"));
             }
+            PieceType::Hold => {
+                builder.insert(Name::new("Held piece"));
+            }
         }
-        let alpha = if piece_type == PieceType::Ghost {
-            0.2
+        let skinned = if piece_type == PieceType::Ghost {
+            Skinned::Ghost(piece.kind)
         } else {
-            1.0
+            Skinned::Mino(piece.kind)
         };
         builder.with_children(|children| {
             for p in piece.block_positions(&Pos::ZERO) {
-                children.spawn(MinoBundle::new(p, piece.kind.color().with_alpha(alpha)));
+                let mino = MinoBundle::new(p, skinned);
+                if piece_type == PieceType::Hint {
+                    children.spawn(mino.inset(0.4));
+                } else {
+                    children.spawn(mino);
+                }
             }
         });
     });
@@ -99,6 +127,10 @@ pub struct CurrentPiece;
 #[derive(Component)]
 pub struct GhostPiece;
 
+/// Marker component for the hint piece, showing where the AI would place the current piece
+#[derive(Component)]
+pub struct HintPiece;
+
 /// A mino (i.e block) which is part of a piece
 #[derive(Component)]
 pub struct Mino;
@@ -107,22 +139,32 @@ pub struct Mino;
 pub struct MinoBundle {
     sprite: SpriteBundle,
     mino: Mino,
+    skinned: Skinned,
 }
 
 impl MinoBundle {
-    pub fn new(pos: Pos, color: Color) -> Self {
+    pub fn new(pos: Pos, skinned: Skinned) -> Self {
         Self {
             sprite: SpriteBundle {
                 sprite: Sprite {
                     custom_size: Some(Vec2::splat(1.0)),
                     anchor: Anchor::BottomLeft,
-                    color,
                     ..default()
                 },
                 transform: pos.into(),
                 ..default()
             },
             mino: Mino,
+            skinned,
         }
     }
+
+    /// Shrink the mino to the given size, keeping it centered in its cell.
+    pub fn inset(mut self, size: f32) -> Self {
+        self.sprite.sprite.custom_size = Some(Vec2::splat(size));
+        // The anchor is relative to the size of the sprite, while its transform stays at the
+        // bottom left corner of the cell
+        self.sprite.sprite.anchor = Anchor::Custom(Vec2::splat(-0.5 / size));
+        self
+    }
 }
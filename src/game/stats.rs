@@ -0,0 +1,85 @@
+//! Statistics about how a game is played, shown once it's over.
+
+use bevy::prelude::*;
+
+use crate::{screen::InGame, SimulationSet};
+
+use super::{
+    clear::{LineClear, PieceLocked, Spin},
+    finesse::FinesseFault,
+    TICK_RATE,
+};
+
+pub fn plugin(app: &mut App) {
+    app.init_resource::<GameStats>()
+        .add_systems(OnEnter(InGame), reset)
+        .add_systems(
+            FixedUpdate,
+            (count_pieces, count_clears, count_faults)
+                .run_if(in_state(InGame))
+                .in_set(SimulationSet::React),
+        );
+}
+
+#[derive(Resource, Debug, Default, Clone)]
+pub struct GameStats {
+    /// Number of pieces that locked.
+    pub pieces: u32,
+    /// Number of gameplay keys pressed for the pieces that locked.
+    pub keys: u32,
+    /// Number of clears, by spin and number of lines.
+    clears: [[u32; 5]; 3],
+    pub max_combo: u32,
+    pub max_back_to_back: u32,
+    pub perfect_clears: u32,
+    /// Number of pieces of each kind that locked, indexed by `TetriminoKind`.
+    pub distribution: [u32; 7],
+    /// Number of pieces placed with more moves than necessary, including retried ones.
+    pub finesse_faults: u32,
+}
+
+impl GameStats {
+    /// Number of clears of the given number of lines (which can be 0 for spins).
+    pub fn clears(&self, spin: Spin, lines: u8) -> u32 {
+        self.clears[spin as usize][lines.min(4) as usize]
+    }
+
+    pub fn keys_per_piece(&self) -> f64 {
+        if self.pieces == 0 {
+            return 0.0;
+        }
+        self.keys as f64 / self.pieces as f64
+    }
+
+    pub fn pieces_per_second(&self, ticks: u64) -> f64 {
+        if ticks == 0 {
+            return 0.0;
+        }
+        self.pieces as f64 * TICK_RATE / ticks as f64
+    }
+}
+
+fn reset(mut stats: ResMut<GameStats>) {
+    *stats = GameStats::default();
+}
+
+pub(super) fn count_pieces(mut stats: ResMut<GameStats>, mut events: EventReader<PieceLocked>) {
+    for event in events.read() {
+        stats.pieces += 1;
+        stats.keys += event.inputs.keys;
+        stats.distribution[event.tetrimino.kind as usize] += 1;
+    }
+}
+
+fn count_faults(mut stats: ResMut<GameStats>, mut events: EventReader<FinesseFault>) {
+    stats.finesse_faults += events.read().count() as u32;
+}
+
+pub(super) fn count_clears(mut stats: ResMut<GameStats>, mut events: EventReader<LineClear>) {
+    for event in events.read() {
+        stats.clears[event.spin as usize][event.lines.min(4) as usize] += 1;
+        stats.max_combo = stats.max_combo.max(event.combo);
+        stats.max_back_to_back = stats.max_back_to_back.max(event.back_to_back);
+        stats.perfect_clears += event.perfect_clear as u32;
+    }
+}
@@ -0,0 +1,417 @@
+//! Letting external bots (e.g. Cold Clear) play, through the Tetris Bot Protocol.
+//!
+//! The bot runs as a child process, and exchanges one JSON message per line with the game over its
+//! standard input and output. See <https://github.com/tetris-bot-protocol/tbp-spec>.
+
+use std::{
+    collections::VecDeque,
+    fmt,
+    io::{self, BufRead, BufReader, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::{
+        mpsc::{self, Receiver, TryRecvError},
+        Mutex,
+    },
+    thread,
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::model::{
+    ai::Decision, movegen::Placement, Facing, Pos, Randomizer, Tetrimino, TetriminoKind,
+};
+
+use super::{GameState, MATRIX_HEIGHT, MATRIX_WIDTH};
+
+/// Number of upcoming pieces the bot is told about, on top of the current one.
+const PREVIEWS: usize = 5;
+
+/// Messages sent to the bot.
+#[derive(Serialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum FrontendMessage {
+    Rules {
+        randomizer: &'static str,
+    },
+    Start {
+        hold: Option<char>,
+        /// Starts with the piece to place.
+        queue: Vec<char>,
+        combo: u32,
+        back_to_back: bool,
+        /// Rows from the bottom up.
+        board: Vec<[Option<char>; MATRIX_WIDTH as usize]>,
+    },
+    Stop,
+    Suggest,
+    Play {
+        #[serde(rename = "move")]
+        mv: Move,
+    },
+    NewPiece {
+        piece: char,
+    },
+    Quit,
+}
+
+/// Messages received from the bot.
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum BotMessage {
+    Info {
+        name: String,
+        version: String,
+        author: String,
+    },
+    Ready,
+    Error {
+        reason: String,
+    },
+    Suggestion {
+        moves: Vec<Move>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+struct Move {
+    location: Location,
+    spin: Spin,
+}
+
+/// Where a piece goes, given by the position of its center as in SRS.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+struct Location {
+    #[serde(rename = "type")]
+    kind: char,
+    orientation: Orientation,
+    x: i8,
+    y: i8,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum Orientation {
+    North,
+    East,
+    South,
+    West,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum Spin {
+    None,
+    Mini,
+    Full,
+}
+
+impl From<Orientation> for Facing {
+    fn from(orientation: Orientation) -> Self {
+        match orientation {
+            Orientation::North => Facing::North,
+            Orientation::East => Facing::East,
+            Orientation::South => Facing::South,
+            Orientation::West => Facing::West,
+        }
+    }
+}
+
+/// The name of a randomizer in TBP, which has none for the ones it can't describe.
+fn randomizer_name(randomizer: Randomizer) -> &'static str {
+    match randomizer {
+        Randomizer::SevenBag => "seven_bag",
+        Randomizer::FourteenBag | Randomizer::Random | Randomizer::Classic => "unknown",
+    }
+}
+
+/// The cells of a piece relative to its center in TBP, which rotates the spawn orientation around
+/// the center without any offset.
+fn tbp_offsets(tetrimino: &Tetrimino) -> [Pos; 4] {
+    Tetrimino::new(tetrimino.kind)
+        .block_offsets()
+        .map(|Pos { x, y }| match tetrimino.facing {
+            Facing::North => Pos::new(x, y),
+            Facing::East => Pos::new(y, -x),
+            Facing::South => Pos::new(-x, -y),
+            Facing::West => Pos::new(-y, x),
+        })
+}
+
+/// The lowest, then leftmost cell.
+fn first_cell(cells: [Pos; 4]) -> Pos {
+    cells
+        .into_iter()
+        .min_by_key(|pos| (pos.y, pos.x))
+        .unwrap_or(Pos::ZERO)
+}
+
+impl Location {
+    fn to_placement(self) -> Option<(Tetrimino, Pos)> {
+        let tetrimino = Tetrimino {
+            kind: TetriminoKind::from_letter(self.kind)?,
+            facing: self.orientation.into(),
+        };
+        // Both ways of placing a piece only differ by a translation
+        let center = Pos::new(self.x, self.y);
+        let cell = first_cell(tbp_offsets(&tetrimino).map(|offset| center + offset));
+        let offset = first_cell(*tetrimino.block_offsets());
+        Some((tetrimino, Pos::new(cell.x - offset.x, cell.y - offset.y)))
+    }
+}
+
+/// A move suggested by the bot.
+#[derive(Debug, Clone)]
+pub struct Suggestion {
+    pub decision: Decision,
+    mv: Move,
+}
+
+#[derive(Debug)]
+pub enum TbpError {
+    Io(io::Error),
+    /// The bot exited, or closed its output.
+    Crashed,
+    /// The bot reported an error.
+    Rejected(String),
+}
+
+impl fmt::Display for TbpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TbpError::Io(e) => write!(f, "{e}"),
+            TbpError::Crashed => write!(f, "the bot stopped responding"),
+            TbpError::Rejected(reason) => write!(f, "the bot reported an error: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for TbpError {}
+
+/// A running bot, and what it knows about the game.
+#[derive(Debug)]
+pub struct TbpBot {
+    child: Child,
+    stdin: ChildStdin,
+    /// Wrapped in a mutex to be shared with the systems, even though it's only ever used by one.
+    messages: Mutex<Receiver<BotMessage>>,
+    /// The randomizer the bot is told about when it asks for the rules.
+    randomizer: Randomizer,
+    /// Whether the bot accepted the rules.
+    ready: bool,
+    /// Whether the bot is playing a game, started with a `start` message.
+    started: bool,
+    /// Whether a suggestion was asked for and hasn't arrived yet.
+    suggesting: bool,
+    /// The hold piece the bot knows about.
+    hold: Option<TetriminoKind>,
+    /// The pieces the bot knows about, starting with the one it is placing.
+    queue: VecDeque<TetriminoKind>,
+}
+
+impl TbpBot {
+    /// Start the bot with the given command line.
+    pub fn spawn(command: &str) -> io::Result<Self> {
+        let mut args = command.split_whitespace();
+        let program = args
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "empty bot command"))?;
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err(io::Error::other("missing bot pipes"));
+        };
+
+        // Read the bot's messages on the side, so that a slow bot never holds the game up
+        let (sender, messages) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                match serde_json::from_str(&line) {
+                    Ok(message) => {
+                        if sender.send(message).is_err() {
+                            break;
+                        }
+                    }
+                    // The protocol says unknown messages must be ignored
+                    Err(e) => debug!("Ignoring bot message {line}: {e}"),
+                }
+            }
+        });
+
+        Ok(Self {
+            child,
+            stdin,
+            messages: Mutex::new(messages),
+            randomizer: Randomizer::default(),
+            ready: false,
+            started: false,
+            suggesting: false,
+            hold: None,
+            queue: VecDeque::new(),
+        })
+    }
+
+    fn send(&mut self, message: &FrontendMessage) -> Result<(), TbpError> {
+        let mut line = serde_json::to_string(message).map_err(|e| TbpError::Io(e.into()))?;
+        line.push('\n');
+        self.stdin
+            .write_all(line.as_bytes())
+            .and_then(|()| self.stdin.flush())
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::BrokenPipe => TbpError::Crashed,
+                _ => TbpError::Io(e),
+            })
+    }
+
+    /// Forget about the current game: the next suggestion starts a new one, using the given
+    /// randomizer. The rules are only sent once though, so the bot keeps assuming the randomizer of
+    /// the first game.
+    pub fn new_game(&mut self, randomizer: Randomizer) -> Result<(), TbpError> {
+        if self.started {
+            self.send(&FrontendMessage::Stop)?;
+        }
+        self.randomizer = randomizer;
+        self.started = false;
+        self.suggesting = false;
+        Ok(())
+    }
+
+    /// The moves the bot suggests for the current piece, best first, once it has made up its mind.
+    pub fn suggest(
+        &mut self,
+        state: &GameState,
+        current: TetriminoKind,
+    ) -> Result<Option<Vec<Suggestion>>, TbpError> {
+        let mut moves = None;
+        loop {
+            let message = match self.messages.get_mut() {
+                Ok(messages) => messages.try_recv(),
+                Err(_) => Err(TryRecvError::Disconnected),
+            };
+            match message {
+                Ok(BotMessage::Info {
+                    name,
+                    version,
+                    author,
+                }) => {
+                    info!("Playing with bot {name} {version} by {author}");
+                    self.send(&FrontendMessage::Rules {
+                        randomizer: randomizer_name(self.randomizer),
+                    })?;
+                }
+                Ok(BotMessage::Ready) => self.ready = true,
+                Ok(BotMessage::Error { reason }) => return Err(TbpError::Rejected(reason)),
+                Ok(BotMessage::Suggestion { moves: suggested }) if self.suggesting => {
+                    moves = Some(suggested);
+                }
+                Ok(BotMessage::Suggestion { .. }) => {}
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return Err(TbpError::Crashed),
+            }
+        }
+        if !self.ready {
+            return Ok(None);
+        }
+
+        if let Some(moves) = moves {
+            self.suggesting = false;
+            let suggestions = moves
+                .into_iter()
+                .filter_map(|mv| {
+                    let (tetrimino, pos) = mv.location.to_placement()?;
+                    Some(Suggestion {
+                        decision: Decision {
+                            hold: tetrimino.kind != current,
+                            placement: Placement {
+                                tetrimino,
+                                pos,
+                                moves: Vec::new(),
+                                rotated_last: false,
+                            },
+                        },
+                        mv,
+                    })
+                })
+                .collect();
+            return Ok(Some(suggestions));
+        }
+        if !self.suggesting {
+            self.sync(state, current)?;
+            self.send(&FrontendMessage::Suggest)?;
+            self.suggesting = true;
+        }
+        Ok(None)
+    }
+
+    /// Tell the bot about the pieces it hasn't seen yet, or start over from the current state of
+    /// the game if it got out of sync.
+    fn sync(&mut self, state: &GameState, current: TetriminoKind) -> Result<(), TbpError> {
+        let queue: Vec<TetriminoKind> = std::iter::once(current)
+            .chain(state.bag.peek(PREVIEWS))
+            .collect();
+        let in_sync = self.started
+            && self.hold == state.hold
+            && self.queue.len() <= queue.len()
+            && self.queue.iter().eq(&queue[..self.queue.len()]);
+        if in_sync {
+            for &piece in &queue[self.queue.len()..] {
+                self.send(&FrontendMessage::NewPiece {
+                    piece: piece.letter(),
+                })?;
+                self.queue.push_back(piece);
+            }
+            return Ok(());
+        }
+
+        if self.started {
+            self.send(&FrontendMessage::Stop)?;
+        }
+        let board = (0..MATRIX_HEIGHT as i8)
+            .map(|y| {
+                std::array::from_fn(|x| {
+                    let occupied = state.matrix.is_occupied(Pos::new(x as i8, y));
+                    occupied.then_some('G')
+                })
+            })
+            .collect();
+        self.send(&FrontendMessage::Start {
+            hold: state.hold.map(|kind| kind.letter()),
+            queue: queue.iter().map(TetriminoKind::letter).collect(),
+            combo: state.combo,
+            back_to_back: state.back_to_back > 0,
+            board,
+        })?;
+        self.started = true;
+        self.hold = state.hold;
+        self.queue = queue.into();
+        Ok(())
+    }
+
+    /// Tell the bot which of its suggestions is being played.
+    pub fn play(&mut self, suggestion: &Suggestion) -> Result<(), TbpError> {
+        self.send(&FrontendMessage::Play { mv: suggestion.mv })?;
+        let current = self.queue.pop_front();
+        if suggestion.decision.hold {
+            // Holding with an empty hold brings in the next piece
+            if self.hold.is_none() {
+                self.queue.pop_front();
+            }
+            self.hold = current;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for TbpBot {
+    fn drop(&mut self) {
+        let _ = self.send(&FrontendMessage::Quit);
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
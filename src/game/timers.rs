@@ -2,41 +2,67 @@ use std::time::Duration;
 
 use bevy::prelude::*;
 
-#[derive(Default, Resource)]
+use crate::settings::Handling;
+
+#[derive(Resource, Clone)]
 pub struct Timers {
     pub fall: FallTimer,
     pub lock: LockTimer,
+    pub line_clear: LineClearTimer,
 }
 
 impl Timers {
+    pub fn new(handling: Handling) -> Self {
+        Self {
+            fall: FallTimer::new(handling.soft_drop_factor),
+            lock: LockTimer::new(Duration::from_millis(handling.lock_delay.into())),
+            line_clear: LineClearTimer::new(Duration::from_millis(
+                handling.line_clear_delay.into(),
+            )),
+        }
+    }
+
     pub fn tick(&mut self, delta: Duration) {
         self.fall.tick(delta);
         self.lock.tick(delta);
+        self.line_clear.tick(delta);
     }
 }
 
-#[derive(Deref, DerefMut)]
+#[derive(Deref, DerefMut, Clone)]
 pub(super) struct FallTimer {
     #[deref]
     timer: Timer,
     normal_duration: Duration,
     softdrop_duration: Duration,
+    /// How many times faster pieces fall during soft drop.
+    softdrop_factor: u32,
 }
 
 impl FallTimer {
-    pub fn new() -> Self {
-        // TODO make it depend on the current game level
+    pub fn new(softdrop_factor: u8) -> Self {
         let normal_duration = Duration::from_millis(1000);
-        // Pieces fall down 20x faster during soft drop
-        let softdrop_duration = Duration::from_millis(1000 / 20);
+        let softdrop_factor = softdrop_factor.max(1).into();
+        let softdrop_duration = normal_duration / softdrop_factor;
         let timer = Timer::new(normal_duration, TimerMode::Repeating);
         Self {
             timer,
             normal_duration,
             softdrop_duration,
+            softdrop_factor,
         }
     }
 
+    /// Use the fall speed of the given level. This takes effect the next time the piece starts
+    /// falling.
+    pub fn set_level(&mut self, level: u64) {
+        // Guideline gravity: the time it takes for a piece to fall down by one row
+        let n = level.saturating_sub(1) as i32;
+        let secs = (0.8 - n as f64 * 0.007).max(0.0).powi(n).max(0.001);
+        self.normal_duration = Duration::from_secs_f64(secs);
+        self.softdrop_duration = self.normal_duration / self.softdrop_factor;
+    }
+
     pub fn normal_drop(&mut self) {
         self.timer.set_duration(self.normal_duration);
         self.timer.reset();
@@ -49,25 +75,30 @@ impl FallTimer {
     }
 }
 
-impl Default for FallTimer {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
-#[derive(Deref, DerefMut)]
+#[derive(Deref, DerefMut, Clone)]
 pub(super) struct LockTimer(Timer);
 
 impl LockTimer {
-    pub fn new() -> Self {
-        let mut timer = Self(Timer::new(Duration::from_millis(500), TimerMode::Once));
+    pub fn new(delay: Duration) -> Self {
+        let mut timer = Self(Timer::new(delay, TimerMode::Once));
         timer.pause();
         timer
     }
 }
 
-impl Default for LockTimer {
-    fn default() -> Self {
-        Self::new()
+/// How long the line clear animation lasts before the cleared lines are actually removed.
+#[derive(Deref, DerefMut, Clone)]
+pub(super) struct LineClearTimer(Timer);
+
+impl LineClearTimer {
+    pub fn new(delay: Duration) -> Self {
+        let mut timer = Self(Timer::new(delay, TimerMode::Once));
+        timer.pause();
+        timer
+    }
+
+    pub fn start(&mut self) {
+        self.reset();
+        self.unpause();
     }
 }
@@ -1,43 +1,253 @@
+//! The HUD around the matrix: score, level, lines, time and chains on the left, and frames around
+//! the next and hold zones.
+
 use bevy::prelude::*;
 
-use crate::screen::Screen;
+use crate::{
+    layout::{HudAnchor, LayoutSlot},
+    screen::{format_ticks, InGame},
+    settings::Settings,
+    AppSet,
+};
+
+use super::{
+    score::Score, spawners::next_zone::PREVIEW_SPACING, stats::GameStats, GameConfig, GameState,
+    SimulationTick,
+};
 
-use super::score::Score;
+/// Space between the pieces shown in the next and hold zones and their frames, in cells.
+const FRAME_MARGIN: f32 = 0.5;
+/// Room for the label at the top of the frames, in cells.
+const FRAME_LABEL: f32 = 1.0;
+const LABEL_COLOR: Color = Color::srgb(0.6, 0.6, 0.7);
+const FRAME_COLOR: Color = Color::srgba(0.6, 0.6, 0.7, 0.5);
 
 pub fn plugin(app: &mut App) {
-    app.add_systems(OnEnter(Screen::Gameplay), setup)
-        .add_systems(Update, update);
+    app.add_systems(OnEnter(InGame), (setup, spawn_frames))
+        .add_systems(
+            Update,
+            (
+                update.run_if(in_state(InGame)),
+                resize_next_frame.run_if(resource_changed::<Settings>),
+            )
+                .in_set(AppSet::Update),
+        );
+}
+
+/// A value shown in the HUD.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+enum HudValue {
+    Score,
+    Level,
+    Lines,
+    NextLevel,
+    Time,
+    PiecesPerSecond,
+    Combo,
+    BackToBack,
 }
 
-fn setup(mut commands: Commands, assets: Res<AssetServer>) {
-    let text_style = TextStyle {
+impl HudValue {
+    fn label(self, config: &GameConfig) -> &'static str {
+        match self {
+            HudValue::Score => "SCORE",
+            HudValue::Level => "LEVEL",
+            HudValue::Lines => "LINES",
+            HudValue::NextLevel => "NEXT LEVEL IN",
+            HudValue::Time if config.mode.time_limit().is_some() => "TIME LEFT",
+            HudValue::Time => "TIME",
+            HudValue::PiecesPerSecond => "PPS",
+            HudValue::Combo => "COMBO",
+            HudValue::BackToBack => "BACK-TO-BACK",
+        }
+    }
+
+    fn value(
+        self,
+        config: &GameConfig,
+        state: &GameState,
+        score: &Score,
+        stats: &GameStats,
+        tick: u64,
+    ) -> String {
+        match self {
+            HudValue::Score => score.formatted(),
+            HudValue::Level => score.level().to_string(),
+            HudValue::Lines => match config.mode.line_goal() {
+                Some(goal) if config.goal.is_none() => format!("{}/{goal}", score.lines()),
+                _ => score.lines().to_string(),
+            },
+            HudValue::NextLevel => format!("{} lines", score.lines_to_next_level()),
+            HudValue::Time => match config.mode.time_limit() {
+                Some(limit) => format_ticks(limit.saturating_sub(tick)),
+                None => format_ticks(tick),
+            },
+            HudValue::PiecesPerSecond => format!("{:.2}", stats.pieces_per_second(tick)),
+            // The first clear starts the combo, the following ones extend it
+            HudValue::Combo => match state.combo {
+                0 | 1 => "-".to_string(),
+                combo => format!("x{}", combo - 1),
+            },
+            // Likewise, the first difficult clear only makes the next one a back-to-back
+            HudValue::BackToBack => match state.back_to_back {
+                0 => "-".to_string(),
+                1 => "Ready".to_string(),
+                chain => format!("x{}", chain - 1),
+            },
+        }
+    }
+}
+
+fn setup(mut commands: Commands, assets: Res<AssetServer>, config: Res<GameConfig>) {
+    let mono = assets.load("fonts/JetBrainsMonoNLNerdFont-Regular.ttf");
+    let label_style = TextStyle {
+        font: mono.clone(),
+        font_size: 14.0,
+        color: LABEL_COLOR,
+    };
+    let value_style = TextStyle {
+        font: mono.clone(),
+        font_size: 22.0,
+        color: Color::WHITE,
+    };
+    let score_style = TextStyle {
         font: assets.load("fonts/BungeeSpice-Regular.ttf"),
-        font_size: 40.0,
+        font_size: 32.0,
         color: Color::WHITE,
     };
 
-    commands.spawn((
-        Name::new("Score"),
+    let mut values = vec![HudValue::Score, HudValue::Level, HudValue::Lines];
+    // Levels don't mean much in puzzles, which have their own goals
+    if config.goal.is_none() {
+        values.push(HudValue::NextLevel);
+    }
+    values.extend([
+        HudValue::Time,
+        HudValue::PiecesPerSecond,
+        HudValue::Combo,
+        HudValue::BackToBack,
+    ]);
+
+    let entry = |label: String, value: String, style: &TextStyle| {
         TextBundle::from_sections([
-            TextSection::new("Score: ", text_style.clone()),
-            TextSection::new("", text_style),
+            TextSection::new(label + "\n", label_style.clone()),
+            TextSection::new(value, style.clone()),
         ])
+        .with_text_justify(JustifyText::Right)
         .with_no_wrap()
-        .with_style(Style {
-            position_type: PositionType::Absolute,
-            top: Val::Px(5.0),
-            left: Val::Px(5.0),
-            ..default()
-        }),
-        ScoreText,
-    ));
+    };
+
+    commands
+        .spawn((
+            Name::new("HUD"),
+            StateScoped(InGame),
+            HudAnchor::Left(13.0),
+            NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::End,
+                    row_gap: Val::Px(6.0),
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .with_children(|panel| {
+            for value in values {
+                let style = if value == HudValue::Score {
+                    &score_style
+                } else {
+                    &value_style
+                };
+                panel.spawn((
+                    Name::new(value.label(&config)),
+                    value,
+                    entry(value.label(&config).to_string(), String::new(), style),
+                ));
+            }
+            if let Some(goal) = config.goal {
+                panel.spawn((
+                    Name::new("Goal"),
+                    entry("GOAL".to_string(), goal.to_string(), &value_style),
+                ));
+            }
+        });
+}
+
+/// Refresh the values of the HUD, only touching the texts that actually change.
+fn update(
+    config: Res<GameConfig>,
+    state: Res<GameState>,
+    score: Res<Score>,
+    stats: Res<GameStats>,
+    tick: Res<SimulationTick>,
+    mut texts: Query<(&HudValue, &mut Text)>,
+) {
+    for (value, mut text) in &mut texts {
+        let value = value.value(&config, &state, &score, &stats, **tick);
+        if text.sections[1].value != value {
+            text.sections[1].value = value;
+        }
+    }
 }
 
+/// Marks the frame around the next zone, which grows with the number of previews.
 #[derive(Component)]
-struct ScoreText;
+struct NextFrame;
+
+/// The area taken by the pieces shown in the given zone, with their frame and its label.
+fn frame_area(slot: LayoutSlot, previews: u8) -> Rect {
+    let cell = slot.cell();
+    let below = (previews.max(1) - 1) as f32 * PREVIEW_SPACING as f32;
+    // Pieces span 4 cells from one cell left of their position, and 2 rows up from it
+    Rect::new(
+        cell.x - 1.0 - FRAME_MARGIN,
+        cell.y - below - FRAME_MARGIN,
+        cell.x + 3.0 + FRAME_MARGIN,
+        cell.y + 2.0 + FRAME_MARGIN + FRAME_LABEL,
+    )
+}
+
+fn spawn_frames(mut commands: Commands, assets: Res<AssetServer>, settings: Res<Settings>) {
+    let label_style = TextStyle {
+        font: assets.load("fonts/JetBrainsMonoNLNerdFont-Regular.ttf"),
+        font_size: 14.0,
+        color: LABEL_COLOR,
+    };
+    let frames = [
+        ("NEXT", LayoutSlot::NextZone, settings.visuals.previews),
+        ("HOLD", LayoutSlot::HoldZone, 1),
+    ];
+    for (label, slot, previews) in frames {
+        let mut frame = commands.spawn((
+            Name::new(format!("{label} frame")),
+            StateScoped(InGame),
+            HudAnchor::Area(frame_area(slot, previews)),
+            NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    border: UiRect::all(Val::Px(2.0)),
+                    padding: UiRect::top(Val::Px(4.0)),
+                    ..default()
+                },
+                border_color: FRAME_COLOR.into(),
+                border_radius: BorderRadius::all(Val::Px(6.0)),
+                ..default()
+            },
+        ));
+        frame.with_children(|frame| {
+            frame.spawn(TextBundle::from_section(label, label_style.clone()));
+        });
+        if slot == LayoutSlot::NextZone {
+            frame.insert(NextFrame);
+        }
+    }
+}
 
-fn update(score: Res<Score>, mut text: Query<&mut Text, With<ScoreText>>) {
-    if let Ok(mut score_text) = text.get_single_mut() {
-        score_text.sections[1].value = score.formatted();
+fn resize_next_frame(settings: Res<Settings>, mut frames: Query<&mut HudAnchor, With<NextFrame>>) {
+    let area = HudAnchor::Area(frame_area(LayoutSlot::NextZone, settings.visuals.previews));
+    for mut anchor in &mut frames {
+        anchor.set_if_neq(area);
     }
 }
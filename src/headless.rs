@@ -0,0 +1,196 @@
+//! Playing games without a window, as fast as the simulation allows, with the AI at the controls.
+//!
+//! Only the simulation runs: there is no rendering, and no screen besides the gameplay one. The
+//! ticks are run back to back rather than in real time, without the state transitions Bevy applies
+//! between frames. The simulation settles its own transitions within each tick though, so the games
+//! play out exactly as they would in the game, including the command-line flags picked up by its
+//! plugins (`--mode`, `--randomizer`, `--bot`, `--ai-delay`...), which are given as [`Args`].
+
+use bevy::{
+    app::FixedMain,
+    ecs::schedule::{ExecutorKind, Schedules},
+    prelude::*,
+    state::app::StatesPlugin,
+};
+use serde::Serialize;
+
+use crate::{
+    args::Args,
+    configure_sets,
+    game::{
+        self,
+        ai::{AiPlayer, Brain},
+        mode::{GameOver, GameResult},
+        GameConfig, Phase, SimulationTick, TICK_RATE,
+    },
+    screen::{InGame, Pause, Screen},
+    settings::Settings,
+};
+
+/// How a simulated game went.
+#[derive(Debug, Clone, Serialize)]
+pub struct Outcome {
+    pub seed: u64,
+    /// Whether the goal of the mode was reached.
+    pub completed: bool,
+    /// Whether the game ended by topping out, rather than being cut short.
+    pub topped_out: bool,
+    pub score: u64,
+    pub lines: u64,
+    pub level: u64,
+    /// Duration of the game, in simulation ticks.
+    pub ticks: u64,
+    /// Number of pieces that locked.
+    pub pieces: u32,
+}
+
+impl Outcome {
+    fn new(result: &GameResult, cut_short: bool) -> Self {
+        Self {
+            seed: result.seed,
+            completed: result.completed,
+            topped_out: !result.completed && !cut_short,
+            score: result.score,
+            lines: result.lines,
+            level: result.level,
+            ticks: result.ticks,
+            pieces: result.stats.pieces,
+        }
+    }
+
+    pub fn pieces_per_second(&self) -> f64 {
+        if self.ticks == 0 {
+            return 0.0;
+        }
+        self.pieces as f64 * TICK_RATE / self.ticks as f64
+    }
+}
+
+/// Play a game with the given seed, cutting it short after `max_ticks` ticks.
+///
+/// The built-in AI plays, unless `--bot` asks for an external one.
+pub fn play(seed: u64, max_ticks: u64, args: &Args) -> Outcome {
+    let mut app = new_app(seed, args);
+    let world = app.world_mut();
+    if !world.contains_resource::<AiPlayer>() {
+        world.insert_resource(AiPlayer::with_brain(Brain::default(), args.ai_delay));
+    }
+    loop {
+        world.run_schedule(FixedMain);
+        if let Some(GameOver(result)) = world.resource_mut::<Events<GameOver>>().drain().last() {
+            return Outcome::new(&result, false);
+        }
+        if world.resource::<SimulationTick>().0 >= max_ticks {
+            // Ending the game now still reports how it went
+            world
+                .resource_mut::<NextState<Phase>>()
+                .set(Phase::Completion);
+            world.run_schedule(StateTransition);
+            if let Some(GameOver(result)) = world.resource_mut::<Events<GameOver>>().drain().last()
+            {
+                return Outcome::new(&result, true);
+            }
+        }
+    }
+}
+
+/// An app running only the simulation, with a game of the given seed about to start.
+///
+/// Nothing plays the game, unless `--ai` or `--bot` are given.
+pub(crate) fn new_app(seed: u64, args: &Args) -> App {
+    let mut app = App::new();
+    app.insert_resource(args.clone());
+    app.add_plugins((
+        MinimalPlugins,
+        StatesPlugin,
+        // The game loads its fonts when it starts, even though no text is ever shown
+        AssetPlugin {
+            watch_for_changes_override: Some(false),
+            ..default()
+        },
+    ));
+    app.init_asset::<Font>();
+    // The player's settings don't apply to simulated games
+    app.init_resource::<Settings>();
+    configure_sets(&mut app);
+    app.insert_state(Screen::Gameplay)
+        .add_computed_state::<InGame>()
+        .add_sub_state::<Pause>()
+        .add_plugins(game::plugin);
+    app.world_mut().resource_mut::<GameConfig>().seed = Some(seed);
+    app.finish();
+    app.cleanup();
+
+    let world = app.world_mut();
+    // Systems are too short-lived to be worth running in parallel, and games are run in parallel
+    // instead
+    for (_, schedule) in world.resource_mut::<Schedules>().iter_mut() {
+        schedule.set_executor_kind(ExecutorKind::SingleThreaded);
+    }
+    world.run_schedule(StateTransition);
+    app
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        game::{
+            input::{InputFrame, PlayerInput},
+            score::Score,
+            stats::GameStats,
+            GameState,
+        },
+        model::Pos,
+    };
+
+    use super::*;
+
+    /// How a game went: the input of every tick, and where it ended up.
+    type Run = (Vec<InputFrame>, Vec<Pos>, u32, String);
+
+    /// Let the AI play for the given number of ticks, running them `ticks_per_frame` at a time
+    /// between the state transitions Bevy applies once per frame, like in the game, or back to back
+    /// like `play` does without a number.
+    fn play_frames(ticks: u64, ticks_per_frame: Option<u64>) -> Run {
+        let mut app = new_app(0, &Args::default());
+        let world = app.world_mut();
+        world.insert_resource(AiPlayer::with_brain(Brain::default(), None));
+        let mut inputs = Vec::new();
+        for tick in 0..ticks {
+            if ticks_per_frame.is_some_and(|n| tick % n == 0) {
+                world.run_schedule(StateTransition);
+            }
+            world.run_schedule(FixedMain);
+            inputs.push(world.resource::<PlayerInput>().current());
+        }
+        let mut blocks: Vec<Pos> = world
+            .resource::<GameState>()
+            .matrix
+            .iter_non_empty()
+            .map(|(pos, _)| pos)
+            .collect();
+        blocks.sort_by_key(|pos| (pos.y, pos.x));
+        let pieces = world.resource::<GameStats>().pieces;
+        (
+            inputs,
+            blocks,
+            pieces,
+            world.resource::<Score>().formatted(),
+        )
+    }
+
+    #[test]
+    fn same_game_at_any_frame_rate() {
+        let one_tick_per_frame = play_frames(1200, Some(1));
+        assert!(
+            one_tick_per_frame.2 > 10,
+            "the AI should have placed pieces"
+        );
+        assert_eq!(play_frames(1200, Some(4)), one_tick_per_frame);
+    }
+
+    #[test]
+    fn same_game_headless_and_windowed() {
+        assert_eq!(play_frames(1200, None), play_frames(1200, Some(1)));
+    }
+}
@@ -0,0 +1,265 @@
+//! The best results of every game mode, kept across sessions.
+
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::prelude::*;
+
+use crate::{
+    game::mode::{GameMode, GameResult},
+    storage::{
+        self, invalid_data, read_len, read_string, read_varint, take, write_string, write_varint,
+    },
+};
+
+const MAGIC: &[u8; 4] = b"BTHS";
+const VERSION: u8 = 1;
+/// Number of results kept for each mode.
+pub const TABLE_LEN: usize = 10;
+
+pub fn plugin(app: &mut App) {
+    app.insert_resource(HighScores::load());
+}
+
+/// A result in a high-score table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HighScore {
+    pub name: String,
+    pub score: u64,
+    pub lines: u64,
+    pub level: u64,
+    pub ticks: u64,
+    /// When the game was played, as a Unix timestamp.
+    pub date: u64,
+    pub seed: u64,
+}
+
+impl HighScore {
+    pub fn new(result: &GameResult, name: String) -> Self {
+        Self {
+            name,
+            score: result.score,
+            lines: result.lines,
+            level: result.level,
+            ticks: result.ticks,
+            date: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            seed: result.seed,
+        }
+    }
+
+    /// Whether this result ranks strictly above `other`.
+    fn ranks_above(&self, other: &HighScore, mode: GameMode) -> bool {
+        if mode.ranked_by_time() {
+            self.ticks < other.ticks
+        } else {
+            self.score > other.score
+        }
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct HighScores {
+    tables: HashMap<GameMode, Vec<HighScore>>,
+    /// The name that was last entered, to suggest it for the next high score.
+    pub last_name: String,
+}
+
+impl HighScores {
+    fn path() -> PathBuf {
+        storage::data_dir().join("highscores.bin")
+    }
+
+    /// Load the high scores, starting from empty tables if they can't be read.
+    pub fn load() -> Self {
+        let path = Self::path();
+        let high_scores = match fs::read(&path) {
+            Ok(bytes) => Self::from_bytes(&bytes),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Self::default(),
+            Err(e) => Err(e),
+        };
+        high_scores.unwrap_or_else(|e| {
+            warn!("Failed to load high scores from {}: {e}", path.display());
+            Self::default()
+        })
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = Self::path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_bytes())
+    }
+
+    /// The results of the given mode, best first.
+    pub fn table(&self, mode: GameMode) -> &[HighScore] {
+        self.tables
+            .get(&mode)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// The rank the result would get in the table of its mode, if it makes it in.
+    pub fn rank(&self, result: &GameResult) -> Option<usize> {
+        if !result.mode.has_high_scores() || (result.mode.ranked_by_time() && !result.completed) {
+            return None;
+        }
+        let entry = HighScore::new(result, String::new());
+        let rank = self
+            .table(result.mode)
+            .iter()
+            .position(|other| entry.ranks_above(other, result.mode))
+            .unwrap_or(self.table(result.mode).len());
+        (rank < TABLE_LEN).then_some(rank)
+    }
+
+    /// Add a result to the table of the given mode, returning its rank.
+    pub fn insert(&mut self, mode: GameMode, entry: HighScore) -> Option<usize> {
+        let table = self.tables.entry(mode).or_default();
+        let rank = table
+            .iter()
+            .position(|other| entry.ranks_above(other, mode))
+            .unwrap_or(table.len());
+        table.insert(rank, entry);
+        table.truncate(TABLE_LEN);
+        (rank < TABLE_LEN).then_some(rank)
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        write_string(&mut bytes, &self.last_name);
+        write_varint(&mut bytes, self.tables.len() as u64);
+        for (mode, table) in &self.tables {
+            bytes.push(*mode as u8);
+            write_varint(&mut bytes, table.len() as u64);
+            for entry in table {
+                write_string(&mut bytes, &entry.name);
+                for value in [
+                    entry.score,
+                    entry.lines,
+                    entry.level,
+                    entry.ticks,
+                    entry.date,
+                    entry.seed,
+                ] {
+                    write_varint(&mut bytes, value);
+                }
+            }
+        }
+        bytes
+    }
+
+    fn from_bytes(mut bytes: &[u8]) -> io::Result<Self> {
+        if take(&mut bytes, MAGIC.len())? != MAGIC {
+            return Err(invalid_data("not a high-score file"));
+        }
+        let version = take(&mut bytes, 1)?[0];
+        if version != VERSION {
+            return Err(invalid_data(format!(
+                "unsupported high-score file version {version}"
+            )));
+        }
+
+        let mut high_scores = Self {
+            last_name: read_string(&mut bytes)?,
+            ..default()
+        };
+        for _ in 0..read_varint(&mut bytes)? {
+            let mode = take(&mut bytes, 1)?[0];
+            let mode = GameMode::try_from(mode)
+                .map_err(|_| invalid_data(format!("invalid game mode {mode}")))?;
+            // The tables never grow longer, so a longer one means the file is corrupt
+            let mut table = Vec::new();
+            for _ in 0..read_len(&mut bytes, TABLE_LEN)? {
+                table.push(HighScore {
+                    name: read_string(&mut bytes)?,
+                    score: read_varint(&mut bytes)?,
+                    lines: read_varint(&mut bytes)?,
+                    level: read_varint(&mut bytes)?,
+                    ticks: read_varint(&mut bytes)?,
+                    date: read_varint(&mut bytes)?,
+                    seed: read_varint(&mut bytes)?,
+                });
+            }
+            high_scores.tables.insert(mode, table);
+        }
+        Ok(high_scores)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, score: u64, ticks: u64) -> HighScore {
+        HighScore {
+            name: name.to_string(),
+            score,
+            lines: 40,
+            level: 5,
+            ticks,
+            date: 1_700_000_000,
+            seed: 0x0123_4567_89ab_cdef,
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut high_scores = HighScores {
+            last_name: "Alexey".to_string(),
+            ..default()
+        };
+        for i in 0..TABLE_LEN as u64 + 2 {
+            high_scores.insert(GameMode::Marathon, entry("A", 1000 * i, 0));
+        }
+        high_scores.insert(GameMode::Sprint, entry("Élodie", 0, 3600));
+
+        let read = HighScores::from_bytes(&high_scores.to_bytes()).unwrap();
+        assert_eq!(read.last_name, high_scores.last_name);
+        for mode in [GameMode::Marathon, GameMode::Sprint, GameMode::Ultra] {
+            assert_eq!(read.table(mode), high_scores.table(mode), "{mode:?}");
+        }
+        assert_eq!(read.table(GameMode::Marathon).len(), TABLE_LEN);
+    }
+
+    #[test]
+    fn reject_corrupt_input() {
+        let mut high_scores = HighScores::default();
+        high_scores.insert(GameMode::Marathon, entry("A", 1000, 0));
+        let bytes = high_scores.to_bytes();
+
+        let mut wrong_magic = bytes.clone();
+        wrong_magic[0] = b'X';
+        let mut wrong_version = bytes.clone();
+        wrong_version[MAGIC.len()] = VERSION + 1;
+        let mut wrong_mode = bytes.clone();
+        // Magic, version, empty last name, number of tables
+        wrong_mode[MAGIC.len() + 3] = 0xff;
+        for bytes in [wrong_magic, wrong_version, wrong_mode] {
+            let error = HighScores::from_bytes(&bytes).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+
+        let error = HighScores::from_bytes(&bytes[..bytes.len() - 1]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn reject_long_tables() {
+        let mut high_scores = HighScores::default();
+        high_scores
+            .tables
+            .insert(GameMode::Marathon, vec![entry("A", 1000, 0); TABLE_LEN + 1]);
+        let error = HighScores::from_bytes(&high_scores.to_bytes()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,293 @@
+//! Game feel: effects that make what happens in the game hit harder, without changing it.
+//!
+//! Hard drops leave streaks behind, locking pieces flash, and line clears shake the camera and
+//! make the screen glow. Flashes and streaks are brighter than white, so that the bloom of the
+//! camera makes them glow too.
+
+use bevy::{
+    core_pipeline::{bloom::BloomSettings, core_2d::Camera2d},
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    },
+    sprite::Anchor,
+};
+
+use crate::{
+    game::{
+        clear::{LineClear, PieceLocked},
+        GameState, PieceMoved,
+    },
+    layout::Layout,
+    screen::InGame,
+    settings::Settings,
+    skin::CurrentSkin,
+    AppSet,
+};
+
+const TRAIL_DURATION: f32 = 0.25;
+const TRAIL_WIDTH: f32 = 0.8;
+const TRAIL_BRIGHTNESS: f32 = 2.0;
+const FLASH_DURATION: f32 = 0.15;
+const FLASH_BRIGHTNESS: f32 = 4.0;
+/// How far the camera moves at most when shaking, in cells.
+const MAX_SHAKE: f32 = 0.5;
+/// How much of the shake wears off per second.
+const SHAKE_DECAY: f32 = 1.5;
+/// How much of the extra glow wears off per second.
+const GLOW_DECAY: f32 = 1.0;
+const MAX_GLOW: f32 = 0.4;
+
+pub fn plugin(app: &mut App) {
+    app.init_resource::<Shake>()
+        .init_resource::<Glow>()
+        .add_systems(Startup, create_trail_texture)
+        .add_systems(
+            Update,
+            (
+                (hard_drop_trails, lock_flashes, clear_impacts),
+                (fade_out, shake_camera, pulse_glow),
+            )
+                .chain()
+                .in_set(AppSet::Update),
+        )
+        .add_systems(OnExit(InGame), reset_camera);
+}
+
+/// How much the camera shakes, from 0 to 1. It wears off over time.
+#[derive(Resource, Debug, Default)]
+struct Shake(f32);
+
+/// Bloom intensity added on top of the camera's own, wearing off over time.
+#[derive(Resource, Debug, Default)]
+struct Glow(f32);
+
+/// A vertical gradient, opaque at the bottom, that hard drop trails are drawn with.
+#[derive(Resource)]
+struct TrailTexture(Handle<Image>);
+
+/// A sprite that fades out, and is despawned once invisible.
+#[derive(Component)]
+struct Fading {
+    timer: Timer,
+    /// The opacity the sprite starts with.
+    alpha: f32,
+}
+
+impl Fading {
+    fn new(seconds: f32, alpha: f32) -> Self {
+        Self {
+            timer: Timer::from_seconds(seconds, TimerMode::Once),
+            alpha,
+        }
+    }
+}
+
+/// A color bright enough to glow with the bloom of the camera.
+fn glowing(color: Color, brightness: f32) -> Color {
+    let color = color.to_linear();
+    LinearRgba::rgb(
+        color.red * brightness,
+        color.green * brightness,
+        color.blue * brightness,
+    )
+    .into()
+}
+
+fn create_trail_texture(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    const HEIGHT: u32 = 32;
+    let data = (0..HEIGHT)
+        .flat_map(|y| [255, 255, 255, (y * 255 / (HEIGHT - 1)) as u8])
+        .collect();
+    let image = Image::new(
+        Extent3d {
+            width: 1,
+            height: HEIGHT,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    commands.insert_resource(TrailTexture(images.add(image)));
+}
+
+/// Streak the columns a hard dropped piece went through.
+fn hard_drop_trails(
+    mut commands: Commands,
+    state: Res<GameState>,
+    skin: Res<CurrentSkin>,
+    texture: Option<Res<TrailTexture>>,
+    mut moves: EventReader<PieceMoved>,
+) {
+    let Some(texture) = texture else {
+        return;
+    };
+    for event in moves.read() {
+        let PieceMoved::HardDrop {
+            tetrimino,
+            from,
+            to,
+        } = event
+        else {
+            continue;
+        };
+        let distance = (from.y - to.y) as f32;
+        if distance <= 0.0 {
+            continue;
+        }
+        // The game may have ended during the same frame
+        let Some(mut matrix) = commands.get_entity(state.matrix.root_entity) else {
+            continue;
+        };
+        let color = glowing(skin.color(tetrimino.kind), TRAIL_BRIGHTNESS);
+        let blocks = tetrimino.block_positions(to);
+        matrix.with_children(|children| {
+            // One streak per column, going up from the top block of the piece
+            for block in &blocks {
+                if blocks.iter().any(|b| b.x == block.x && b.y > block.y) {
+                    continue;
+                }
+                children.spawn((
+                    Name::new("Hard drop trail"),
+                    Fading::new(TRAIL_DURATION, 0.6),
+                    SpriteBundle {
+                        sprite: Sprite {
+                            color,
+                            custom_size: Some(Vec2::new(TRAIL_WIDTH, distance)),
+                            anchor: Anchor::BottomCenter,
+                            ..default()
+                        },
+                        texture: texture.0.clone(),
+                        transform: Transform::from_xyz(
+                            block.x as f32 + 0.5,
+                            block.y as f32 + 1.0,
+                            0.5,
+                        ),
+                        ..default()
+                    },
+                ));
+            }
+        });
+    }
+}
+
+/// Flash the blocks of pieces as they lock.
+fn lock_flashes(
+    mut commands: Commands,
+    state: Res<GameState>,
+    mut locks: EventReader<PieceLocked>,
+) {
+    for PieceLocked { tetrimino, pos, .. } in locks.read() {
+        let Some(mut matrix) = commands.get_entity(state.matrix.root_entity) else {
+            continue;
+        };
+        matrix.with_children(|children| {
+            for block in tetrimino.block_positions(pos) {
+                let mut transform = Transform::from(block);
+                // Over the block
+                transform.translation.z += 1.0;
+                children.spawn((
+                    Name::new("Lock flash"),
+                    Fading::new(FLASH_DURATION, 0.5),
+                    SpriteBundle {
+                        sprite: Sprite {
+                            color: glowing(Color::WHITE, FLASH_BRIGHTNESS),
+                            custom_size: Some(Vec2::ONE),
+                            anchor: Anchor::BottomLeft,
+                            ..default()
+                        },
+                        transform,
+                        ..default()
+                    },
+                ));
+            }
+        });
+    }
+}
+
+/// Shake the camera and make the screen glow with line clears, the more lines the stronger.
+fn clear_impacts(
+    mut shake: ResMut<Shake>,
+    mut glow: ResMut<Glow>,
+    mut clears: EventReader<LineClear>,
+) {
+    for clear in clears.read() {
+        if clear.lines == 0 {
+            continue;
+        }
+        let mut impact = clear.lines as f32 * 0.15;
+        if clear.is_difficult() {
+            impact += 0.2;
+        }
+        if clear.perfect_clear {
+            impact += 0.4;
+        }
+        shake.0 = (shake.0 + impact).min(1.0);
+        glow.0 = (glow.0 + impact * 0.5).min(MAX_GLOW);
+    }
+}
+
+fn fade_out(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut sprites: Query<(Entity, &mut Fading, &mut Sprite)>,
+) {
+    for (entity, mut fading, mut sprite) in &mut sprites {
+        fading.timer.tick(time.delta());
+        if fading.timer.finished() {
+            commands.entity(entity).despawn_recursive();
+        } else {
+            let alpha = fading.alpha * fading.timer.fraction_remaining();
+            sprite.color.set_alpha(alpha);
+        }
+    }
+}
+
+fn shake_camera(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    layout: Res<Layout>,
+    mut shake: ResMut<Shake>,
+    mut camera: Query<&mut Transform, With<Camera2d>>,
+) {
+    let Ok(mut transform) = camera.get_single_mut() else {
+        return;
+    };
+    if shake.0 == 0.0 && transform.translation.truncate() == Vec2::ZERO {
+        return;
+    }
+    shake.0 = (shake.0 - SHAKE_DECAY * time.delta_seconds()).max(0.0);
+    // Squared, so that small shakes stay subtle
+    let amount =
+        shake.0.powi(2) * MAX_SHAKE * layout.scale * settings.visuals.screen_shake as f32 / 100.0;
+    let t = time.elapsed_seconds();
+    let offset = Vec2::new((t * 47.0).sin(), (t * 61.0 + 1.3).sin()) * amount;
+    transform.translation = offset.extend(transform.translation.z);
+}
+
+fn pulse_glow(time: Res<Time>, mut glow: ResMut<Glow>, mut bloom: Query<&mut BloomSettings>) {
+    let Ok(mut bloom) = bloom.get_single_mut() else {
+        return;
+    };
+    let intensity = BloomSettings::NATURAL.intensity + glow.0;
+    if bloom.intensity != intensity {
+        bloom.intensity = intensity;
+    }
+    glow.0 = (glow.0 - GLOW_DECAY * time.delta_seconds()).max(0.0);
+}
+
+fn reset_camera(
+    mut shake: ResMut<Shake>,
+    mut glow: ResMut<Glow>,
+    mut camera: Query<(&mut Transform, &mut BloomSettings), With<Camera2d>>,
+) {
+    shake.0 = 0.0;
+    glow.0 = 0.0;
+    for (mut transform, mut bloom) in &mut camera {
+        transform.translation = Vec3::new(0.0, 0.0, transform.translation.z);
+        bloom.intensity = BloomSettings::NATURAL.intensity;
+    }
+}
@@ -0,0 +1,171 @@
+//! Placing the playfield and the HUD so that they fit the window, whatever its size and aspect
+//! ratio.
+//!
+//! Everything is laid out in cells around the matrix, then scaled so that the whole layout fits in
+//! the window with the matrix in the middle. The UI scales along with it.
+
+use bevy::{
+    prelude::*,
+    transform::TransformSystem,
+    ui::UiSystem,
+    window::{PrimaryWindow, WindowResized},
+};
+
+use crate::game::{MATRIX_WIDTH, VISIBLE_HEIGHT};
+
+/// Size of the layout in cells, centered on the matrix: room for the hold and next zones on both
+/// sides, and a margin around the walls.
+const LAYOUT_SIZE: Vec2 = Vec2::new(28.0, 26.0);
+/// The window size the UI is designed for. It scales with the window from there.
+const REFERENCE_SIZE: Vec2 = Vec2::new(1280.0, 720.0);
+/// Where the upcoming pieces are shown, in cells from the bottom left corner of the matrix.
+const NEXT_ZONE: Vec2 = Vec2::new(15.0, 16.0);
+/// Where the held piece is shown, in cells from the bottom left corner of the matrix.
+const HOLD_ZONE: Vec2 = Vec2::new(-5.0, 16.0);
+/// Space between the walls of the matrix and the HUD, in cells.
+const HUD_GAP: f32 = 1.5;
+
+pub fn plugin(app: &mut App) {
+    app.init_resource::<Layout>()
+        .add_systems(Startup, update_layout)
+        .add_systems(PreUpdate, update_layout.run_if(on_event::<WindowResized>()))
+        .add_systems(
+            PostUpdate,
+            apply_layout
+                .before(UiSystem::Layout)
+                .before(TransformSystem::TransformPropagate),
+        );
+}
+
+/// The size of things on screen, following the size of the window.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct Layout {
+    /// Size of a cell, in pixels.
+    pub scale: f32,
+    /// Size of the window, in pixels.
+    window: Vec2,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self::new(REFERENCE_SIZE)
+    }
+}
+
+impl Layout {
+    fn new(window: Vec2) -> Self {
+        Self {
+            scale: (window / LAYOUT_SIZE).min_element(),
+            window,
+        }
+    }
+
+    /// Where the bottom left corner of the matrix is, in world coordinates.
+    pub fn matrix_origin(&self) -> Vec2 {
+        -Vec2::new(MATRIX_WIDTH as f32, VISIBLE_HEIGHT as f32) / 2.0 * self.scale
+    }
+
+    /// The scale of the UI, relative to the window size it is designed for.
+    pub fn ui_scale(&self) -> f32 {
+        (self.window / REFERENCE_SIZE).min_element()
+    }
+
+    /// The given point, in cells from the bottom left corner of the matrix, as a position in the UI
+    /// (from the top left corner of the window, in unscaled UI pixels).
+    fn ui_point(&self, cell: Vec2) -> Vec2 {
+        let world = self.matrix_origin() + cell * self.scale;
+        Vec2::new(self.window.x / 2.0 + world.x, self.window.y / 2.0 - world.y) / self.ui_scale()
+    }
+
+    fn transform(&self, slot: LayoutSlot) -> Transform {
+        let translation = self.matrix_origin() + slot.cell() * self.scale;
+        Transform::from_translation(translation.extend(1.0))
+            .with_scale(Vec3::new(self.scale, self.scale, 1.0))
+    }
+}
+
+/// Part of the playfield, drawn in cells, which is placed and scaled with the layout.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutSlot {
+    Matrix,
+    NextZone,
+    HoldZone,
+}
+
+impl LayoutSlot {
+    /// Where the slot is, in cells from the bottom left corner of the matrix.
+    pub fn cell(self) -> Vec2 {
+        match self {
+            LayoutSlot::Matrix => Vec2::ZERO,
+            LayoutSlot::NextZone => NEXT_ZONE,
+            LayoutSlot::HoldZone => HOLD_ZONE,
+        }
+    }
+}
+
+/// Where a node of the HUD goes, next to the matrix.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub enum HudAnchor {
+    /// On the left of the matrix, right-aligned, with its top at the given row.
+    Left(f32),
+    /// On the right of the matrix, left-aligned, with its top at the given row.
+    Right(f32),
+    /// Covering the given area, in cells from the bottom left corner of the matrix.
+    Area(Rect),
+}
+
+fn update_layout(
+    window: Query<&Window, With<PrimaryWindow>>,
+    mut layout: ResMut<Layout>,
+    mut ui_scale: ResMut<UiScale>,
+) {
+    let Ok(window) = window.get_single() else {
+        return;
+    };
+    let size = Vec2::new(window.width(), window.height());
+    if size.min_element() <= 0.0 {
+        // Minimized
+        return;
+    }
+    *layout = Layout::new(size);
+    ui_scale.0 = layout.ui_scale();
+}
+
+/// Place the playfield and the HUD as they are spawned, and again when the layout changes.
+fn apply_layout(
+    layout: Res<Layout>,
+    mut slots: Query<(Ref<LayoutSlot>, &mut Transform)>,
+    mut anchors: Query<(Ref<HudAnchor>, &mut Style)>,
+) {
+    for (slot, mut transform) in &mut slots {
+        if layout.is_changed() || slot.is_added() {
+            *transform = layout.transform(*slot);
+        }
+    }
+    for (anchor, mut style) in &mut anchors {
+        if !layout.is_changed() && !anchor.is_changed() {
+            continue;
+        }
+        style.position_type = PositionType::Absolute;
+        match *anchor {
+            HudAnchor::Left(row) => {
+                let point = layout.ui_point(Vec2::new(-1.0 - HUD_GAP, row));
+                style.right = Val::Px(layout.window.x / layout.ui_scale() - point.x);
+                style.top = Val::Px(point.y);
+            }
+            HudAnchor::Right(row) => {
+                let point = layout.ui_point(Vec2::new(MATRIX_WIDTH as f32 + 1.0 + HUD_GAP, row));
+                style.left = Val::Px(point.x);
+                style.top = Val::Px(point.y);
+            }
+            HudAnchor::Area(area) => {
+                let top_left = layout.ui_point(Vec2::new(area.min.x, area.max.y));
+                let bottom_right = layout.ui_point(Vec2::new(area.max.x, area.min.y));
+                style.left = Val::Px(top_left.x);
+                style.top = Val::Px(top_left.y);
+                style.width = Val::Px(bottom_right.x - top_left.x);
+                style.height = Val::Px(bottom_right.y - top_left.y);
+            }
+        }
+    }
+}
@@ -1,29 +1,51 @@
+use args::Args;
 use bevy::{
     core_pipeline::{bloom::BloomSettings, tonemapping::Tonemapping},
     prelude::*,
 };
 use bevy_tween::DefaultTweenPlugins;
+use game::Phase;
 
+pub mod args;
+mod audio;
+mod callouts;
 #[cfg(feature = "dev")]
 mod dev_tools;
 mod game;
+pub mod headless;
+mod highscores;
+mod juice;
+mod layout;
 mod model;
+mod puzzles;
 mod screen;
+mod settings;
+mod skin;
+mod storage;
 
 pub struct AppPlugin;
 
 impl Plugin for AppPlugin {
     fn build(&self, app: &mut App) {
-        // Order new `AppStep` variants by adding them here:
-        app.configure_sets(
-            Update,
-            (AppSet::TickTimers, AppSet::RecordInput, AppSet::Update).chain(),
-        );
+        app.insert_resource(Args::parse());
+        configure_sets(app);
+        puzzles::register_source(app);
 
         app.add_plugins((DefaultPlugins, DefaultTweenPlugins))
             .insert_resource(ClearColor(Color::BLACK))
             .add_systems(Startup, setup)
-            .add_plugins((game::plugin, screen::plugin));
+            .add_plugins((
+                audio::plugin,
+                callouts::plugin,
+                game::plugin,
+                highscores::plugin,
+                juice::plugin,
+                layout::plugin,
+                puzzles::plugin,
+                screen::plugin,
+                settings::plugin,
+                skin::plugin,
+            ));
 
         // TODO: disable in release mode
         #[cfg(feature = "dev")]
@@ -31,9 +53,36 @@ impl Plugin for AppPlugin {
     }
 }
 
+/// Set up the system sets shared by the game and the headless simulation.
+fn configure_sets(app: &mut App) {
+    // Order new `AppStep` variants by adding them here:
+    app.configure_sets(
+        Update,
+        (AppSet::TickTimers, AppSet::RecordInput, AppSet::Update).chain(),
+    );
+    // Order new `SimulationSet` variants by adding them here:
+    app.configure_sets(
+        FixedUpdate,
+        (
+            SimulationSet::RecordInput,
+            SimulationSet::Update,
+            SimulationSet::ApplyTransitions,
+            SimulationSet::React,
+        )
+            .chain(),
+    );
+    app.add_systems(
+        FixedUpdate,
+        (
+            apply_state_transitions.in_set(SimulationSet::ApplyTransitions),
+            // Reacting to the tick can end the game
+            apply_state_transitions.after(SimulationSet::React),
+        ),
+    );
+}
+
 /// High-level groupings of systems for the app in the `Update` schedule.
-/// When adding a new variant, make sure to order it in the `configure_sets`
-/// call above.
+/// When adding a new variant, make sure to order it in `configure_sets`.
 #[derive(SystemSet, Debug, Clone, Copy, Eq, PartialEq, Hash)]
 enum AppSet {
     /// Tick timers.
@@ -44,6 +93,39 @@ enum AppSet {
     Update,
 }
 
+/// High-level groupings of systems for the app in the `FixedUpdate` schedule, which drives the
+/// (deterministic) game simulation. When adding a new variant, make sure to order it in
+/// `configure_sets`.
+#[derive(SystemSet, Debug, Clone, Copy, Eq, PartialEq, Hash)]
+enum SimulationSet {
+    /// Sample the player input for this tick.
+    RecordInput,
+    /// Advance the simulation by one tick.
+    Update,
+    /// Apply pending state transitions.
+    ApplyTransitions,
+    /// React to the events sent during the tick (e.g. update the score).
+    React,
+}
+
+/// Run the `StateTransition` schedule during every simulation tick, until no phase is left queued.
+///
+/// By default Bevy only applies state transitions once per frame, which would make the number of
+/// ticks spent in each `Phase` depend on the frame rate. Entering a phase often queues the next one
+/// right away (locking a piece looks for patterns, then generates the next piece), so the whole
+/// chain is applied within the tick, leaving nothing for the transitions of the frame to pick up.
+fn apply_state_transitions(world: &mut World) {
+    loop {
+        let _ = world.try_run_schedule(StateTransition);
+        let queued = world
+            .get_resource::<NextState<Phase>>()
+            .is_some_and(|next| matches!(next, NextState::Pending(_)));
+        if !queued {
+            break;
+        }
+    }
+}
+
 fn setup(mut commands: Commands) {
     commands.spawn((
         Camera2dBundle {
@@ -0,0 +1,131 @@
+//! A heuristic player: every placement of the available pieces is tried, and the one leaving the
+//! best looking matrix wins.
+
+use crate::game::MATRIX_WIDTH;
+
+use super::{
+    board::Board,
+    movegen::{placements, Placement},
+    Pos, Tetrimino, TetriminoKind,
+};
+
+/// How much each feature of a matrix counts towards its evaluation. Negative weights are penalties.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Weights {
+    /// Sum of the heights of all the columns.
+    pub height: f32,
+    /// Empty cells with a block somewhere above them.
+    pub holes: f32,
+    /// Sum of the height differences between neighbouring columns.
+    pub bumpiness: f32,
+    /// Sum of the depths of the columns lower than both their neighbours.
+    pub wells: f32,
+    /// Lines cleared by the placement.
+    pub lines: f32,
+    /// Places ready for a T-spin.
+    pub t_slots: f32,
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Self {
+            height: -0.51,
+            holes: -0.36,
+            bumpiness: -0.18,
+            wells: -0.1,
+            lines: 0.76,
+            t_slots: 0.2,
+        }
+    }
+}
+
+/// What to do with the current piece.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decision {
+    /// Whether to swap the current piece with the held one first.
+    pub hold: bool,
+    pub placement: Placement,
+}
+
+impl Weights {
+    /// Score a matrix, after a placement that cleared the given number of lines.
+    pub fn evaluate(&self, board: &Board, lines: u8) -> f32 {
+        let heights = board.heights();
+        let height: i32 = heights.iter().map(|h| *h as i32).sum();
+        let holes: i32 = (0..MATRIX_WIDTH as i8)
+            .map(|x| {
+                (0..heights[x as usize])
+                    .filter(|y| !board.is_occupied(Pos::new(x, *y)))
+                    .count() as i32
+            })
+            .sum();
+        let bumpiness: i32 = heights
+            .windows(2)
+            .map(|pair| (pair[0] - pair[1]).abs() as i32)
+            .sum();
+        let wells: i32 = (0..heights.len())
+            .map(|x| {
+                // The walls count as infinitely high neighbours
+                let left = x.checked_sub(1).map_or(i8::MAX, |x| heights[x]);
+                let right = heights.get(x + 1).copied().unwrap_or(i8::MAX);
+                (left.min(right) - heights[x]).max(0) as i32
+            })
+            .sum();
+
+        self.height * height as f32
+            + self.holes * holes as f32
+            + self.bumpiness * bumpiness as f32
+            + self.wells * wells as f32
+            + self.lines * lines as f32
+            + self.t_slots * board.t_slots() as f32
+    }
+
+    /// The best placement of a piece, and its evaluation.
+    pub fn best_placement(
+        &self,
+        board: &Board,
+        tetrimino: Tetrimino,
+        pos: Pos,
+    ) -> Option<(Placement, f32)> {
+        placements(board, tetrimino, pos)
+            .into_iter()
+            .map(|placement| {
+                let mut after = *board;
+                let lines = after.lock(&placement.tetrimino, &placement.pos);
+                let score = self.evaluate(&after, lines);
+                (placement, score)
+            })
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+    }
+
+    /// Pick between placing the current piece, or the one that holding would bring in (either the
+    /// held piece or the next one), spawning at `spawn`.
+    pub fn decide(
+        &self,
+        board: &Board,
+        current: (Tetrimino, Pos),
+        alternative: Option<TetriminoKind>,
+        spawn: Pos,
+    ) -> Option<Decision> {
+        let place = self.best_placement(board, current.0, current.1);
+        let hold =
+            alternative.and_then(|kind| self.best_placement(board, Tetrimino::new(kind), spawn));
+        match (place, hold) {
+            (Some((_, score)), Some((placement, hold_score))) if hold_score > score => {
+                Some(Decision {
+                    hold: true,
+                    placement,
+                })
+            }
+            (Some((placement, _)), _) => Some(Decision {
+                hold: false,
+                placement,
+            }),
+            (None, Some((placement, _))) => Some(Decision {
+                hold: true,
+                placement,
+            }),
+            (None, None) => None,
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! A lightweight copy of the matrix, to try out moves and placements quickly.
+
+use crate::game::{MATRIX_HEIGHT, MATRIX_WIDTH, VISIBLE_HEIGHT};
+
+use super::{finesse::Move, Pos, Tetrimino};
+
+const FULL_ROW: u16 = (1 << MATRIX_WIDTH) - 1;
+
+/// A compact copy of the matrix, with one bit per cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Board {
+    /// Rows from the bottom up, with the leftmost cell in the lowest bit.
+    rows: [u16; MATRIX_HEIGHT as usize],
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Self {
+            rows: [0; MATRIX_HEIGHT as usize],
+        }
+    }
+}
+
+impl Board {
+    pub fn from_blocks(blocks: impl IntoIterator<Item = Pos>) -> Self {
+        let mut board = Self::default();
+        for pos in blocks {
+            board.fill(pos);
+        }
+        board
+    }
+
+    /// Whether the given position is taken by a block, or outside of the matrix.
+    pub fn is_occupied(&self, pos: Pos) -> bool {
+        pos.x < 0
+            || pos.x >= MATRIX_WIDTH as i8
+            || pos.y < 0
+            || pos.y >= MATRIX_HEIGHT as i8
+            || self.rows[pos.y as usize] & (1 << pos.x) != 0
+    }
+
+    pub fn is_valid(&self, tetrimino: &Tetrimino, pos: &Pos) -> bool {
+        tetrimino
+            .block_positions(pos)
+            .iter()
+            .all(|block| !self.is_occupied(*block))
+    }
+
+    pub fn lowest_valid_pos(&self, tetrimino: &Tetrimino, pos: &Pos) -> Pos {
+        let mut pos = *pos;
+        while self.is_valid(tetrimino, &pos.down()) {
+            pos = pos.down();
+        }
+        pos
+    }
+
+    /// Lock a piece, and remove the lines it completes. Returns the number of lines cleared.
+    pub fn lock(&mut self, tetrimino: &Tetrimino, pos: &Pos) -> u8 {
+        for block in tetrimino.block_positions(pos) {
+            self.fill(block);
+        }
+        let mut lines = 0;
+        let mut y = 0;
+        while y < self.rows.len() {
+            if self.rows[y] == FULL_ROW {
+                self.rows.copy_within(y + 1.., y);
+                self.rows[self.rows.len() - 1] = 0;
+                lines += 1;
+            } else {
+                y += 1;
+            }
+        }
+        lines
+    }
+
+    /// Make a move, if the piece has room for it.
+    pub fn apply(&self, step: Move, tetrimino: Tetrimino, pos: Pos) -> Option<(Tetrimino, Pos)> {
+        let (tetrimino, pos) = match step {
+            Move::Left => (tetrimino, pos.left()),
+            Move::Right => (tetrimino, pos.right()),
+            Move::RotateCw => (tetrimino.rotated_cw(), pos),
+            Move::RotateCcw => (tetrimino.rotated_ccw(), pos),
+            Move::SoftDrop => {
+                let lowest = self.lowest_valid_pos(&tetrimino, &pos);
+                if lowest == pos {
+                    return None;
+                }
+                (tetrimino, lowest)
+            }
+        };
+        self.is_valid(&tetrimino, &pos).then_some((tetrimino, pos))
+    }
+
+    fn fill(&mut self, pos: Pos) {
+        if let Some(row) = self.rows.get_mut(pos.y as usize) {
+            *row |= 1 << pos.x;
+        }
+    }
+
+    /// The height of every column, i.e. one more than the row of its highest block.
+    pub fn heights(&self) -> [i8; MATRIX_WIDTH as usize] {
+        std::array::from_fn(|x| {
+            self.rows
+                .iter()
+                .rposition(|row| row & (1 << x) != 0)
+                .map_or(0, |y| y as i8 + 1)
+        })
+    }
+
+    /// Number of places where a T piece pointing down could be spun in to complete a T-spin.
+    pub fn t_slots(&self) -> u32 {
+        let occupied = |x, y| self.is_occupied(Pos::new(x, y));
+        let mut slots = 0;
+        for y in 1..VISIBLE_HEIGHT as i8 {
+            for x in 1..MATRIX_WIDTH as i8 - 1 {
+                let fits = !occupied(x - 1, y)
+                    && !occupied(x, y)
+                    && !occupied(x + 1, y)
+                    && !occupied(x, y - 1)
+                    && occupied(x, y - 2);
+                let corners = occupied(x - 1, y - 1) && occupied(x + 1, y - 1);
+                let overhang = occupied(x - 1, y + 1) || occupied(x + 1, y + 1);
+                if fits && corners && overhang {
+                    slots += 1;
+                }
+            }
+        }
+        slots
+    }
+}
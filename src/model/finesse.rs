@@ -0,0 +1,158 @@
+//! Finesse: placing pieces with the fewest possible inputs.
+
+use std::collections::{hash_map::Entry, HashMap, VecDeque};
+
+use super::{board::Board, Pos, Tetrimino};
+
+/// A single input moving a piece before it is dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Move {
+    Left,
+    Right,
+    RotateCw,
+    RotateCcw,
+    /// Drop the piece as far as it goes, without locking it.
+    SoftDrop,
+}
+
+impl Move {
+    pub const ALL: [Move; 5] = [
+        Move::Left,
+        Move::Right,
+        Move::RotateCw,
+        Move::RotateCcw,
+        Move::SoftDrop,
+    ];
+    /// The moves that can be made before hard dropping a piece from above.
+    pub const SHIFTS_AND_ROTATIONS: [Move; 4] =
+        [Move::Left, Move::Right, Move::RotateCw, Move::RotateCcw];
+
+    pub fn is_rotation(&self) -> bool {
+        matches!(self, Move::RotateCw | Move::RotateCcw)
+    }
+}
+
+/// The shortest sequence of moves bringing a piece from its spawn position to the given placement
+/// on an empty matrix, before hard dropping it.
+///
+/// Placements are compared by the columns their blocks end up in, so that e.g. the two vertical
+/// orientations of an I piece in the same column are equivalent. Returns `None` if the placement
+/// can't be reached by just shifting and rotating.
+pub fn optimal_moves(target: &Tetrimino, pos: &Pos, spawn: &Pos) -> Option<Vec<Move>> {
+    let goal = footprint(target, pos);
+    let start = Tetrimino::new(target.kind);
+    let empty = Board::default();
+
+    // For every state reached, the move that led to it and the state it was made from
+    let mut parents: HashMap<(u8, i8), Option<(Move, (Tetrimino, Pos))>> =
+        HashMap::from([((start.facing as u8, spawn.x), None)]);
+    let mut queue = VecDeque::from([(start, *spawn)]);
+    while let Some((tetrimino, pos)) = queue.pop_front() {
+        if footprint(&tetrimino, &pos) == goal {
+            let mut moves = Vec::new();
+            let mut key = (tetrimino.facing as u8, pos.x);
+            while let Some(Some((step, (tetrimino, pos)))) = parents.get(&key) {
+                moves.push(*step);
+                key = (tetrimino.facing as u8, pos.x);
+            }
+            moves.reverse();
+            return Some(moves);
+        }
+        for step in Move::SHIFTS_AND_ROTATIONS {
+            let Some((next, next_pos)) = empty.apply(step, tetrimino, pos) else {
+                continue;
+            };
+            let key = (next.facing as u8, next_pos.x);
+            if let Entry::Vacant(entry) = parents.entry(key) {
+                entry.insert(Some((step, (tetrimino, pos))));
+                queue.push_back((next, next_pos));
+            }
+        }
+    }
+    None
+}
+
+/// The block positions of a piece, relative to its lowest row.
+fn footprint(tetrimino: &Tetrimino, pos: &Pos) -> [(i8, i8); 4] {
+    let min_y = tetrimino.min_y(pos);
+    let mut blocks = tetrimino
+        .block_positions(pos)
+        .map(|block| (block.x, block.y - min_y));
+    blocks.sort_unstable();
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        game::spawners::INITIAL_POS,
+        model::{Facing, TetriminoKind},
+    };
+
+    use super::*;
+
+    fn moves(kind: TetriminoKind, facing: Facing, x: i8) -> Option<Vec<Move>> {
+        let target = Tetrimino { kind, facing };
+        optimal_moves(&target, &Pos::new(x, 0), &INITIAL_POS)
+    }
+
+    #[test]
+    fn shifts() {
+        use Move::*;
+        assert_eq!(moves(TetriminoKind::T, Facing::North, 5), Some(vec![]));
+        assert_eq!(
+            moves(TetriminoKind::T, Facing::North, 1),
+            Some(vec![Left; 4])
+        );
+        assert_eq!(
+            moves(TetriminoKind::T, Facing::North, 8),
+            Some(vec![Right; 3])
+        );
+        assert_eq!(
+            moves(TetriminoKind::O, Facing::North, 0),
+            Some(vec![Left; 5])
+        );
+    }
+
+    #[test]
+    fn rotations() {
+        use Move::*;
+        assert_eq!(
+            moves(TetriminoKind::T, Facing::East, 5),
+            Some(vec![RotateCw])
+        );
+        assert_eq!(
+            moves(TetriminoKind::T, Facing::West, 5),
+            Some(vec![RotateCcw])
+        );
+        let flip = moves(TetriminoKind::T, Facing::South, 5).unwrap();
+        assert_eq!(flip.len(), 2);
+        assert!(flip.iter().all(Move::is_rotation));
+        // Against the wall, in whichever order
+        let mut wall = moves(TetriminoKind::T, Facing::East, 0).unwrap();
+        wall.sort_by_key(|step| step.is_rotation());
+        assert_eq!(wall, [Left, Left, Left, Left, Left, RotateCw]);
+    }
+
+    /// Placements covering the same columns take the same moves, whichever way the piece faces.
+    #[test]
+    fn equivalent_orientations() {
+        // Both vertical I pieces cover column 6
+        assert_eq!(
+            moves(TetriminoKind::I, Facing::West, 6),
+            moves(TetriminoKind::I, Facing::East, 5)
+        );
+        assert_eq!(
+            moves(TetriminoKind::I, Facing::West, 6),
+            Some(vec![Move::RotateCw])
+        );
+        // Every orientation of the O piece is the same
+        assert_eq!(moves(TetriminoKind::O, Facing::South, 5), Some(vec![]));
+    }
+
+    #[test]
+    fn out_of_the_matrix() {
+        assert_eq!(moves(TetriminoKind::T, Facing::North, 0), None);
+        assert_eq!(moves(TetriminoKind::I, Facing::North, 8), None);
+    }
+}
@@ -0,0 +1,648 @@
+//! Encoding and decoding of [fumen](https://harddrop.com/wiki/Fumen) strings, the format commonly
+//! used by the Tetris community to share board states.
+//!
+//! A fumen is a list of pages. Each page has a field, an optional piece being placed, and a
+//! comment. Only the current version of the format (`v115`) is supported.
+
+use std::fmt;
+
+use super::{Cell, Facing, Pos, Tetrimino, TetriminoKind};
+
+const PREFIX: &str = "v115@";
+const ENCODE_TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const COMMENT_TABLE: &[u8] =
+    b" !\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`abcdefghijklmnopqrstuvwxyz{|}~";
+const MAX_COMMENT_LEN: usize = 4095;
+
+const FIELD_WIDTH: usize = 10;
+/// Number of rows in a field, not counting the garbage row below it.
+pub const FIELD_HEIGHT: usize = 23;
+const FIELD_BLOCKS: usize = FIELD_WIDTH * (FIELD_HEIGHT + 1);
+
+/// Prefix of the comment of "quiz" pages.
+const QUIZ_PREFIX: &str = "#Q=";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FumenError {
+    /// The data isn't a `v115` fumen.
+    UnsupportedVersion,
+    InvalidCharacter(char),
+    UnexpectedEnd,
+    InvalidPiece,
+}
+
+impl fmt::Display for FumenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FumenError::UnsupportedVersion => write!(f, "not a v115 fumen"),
+            FumenError::InvalidCharacter(c) => write!(f, "invalid character '{c}'"),
+            FumenError::UnexpectedEnd => write!(f, "unexpected end of data"),
+            FumenError::InvalidPiece => write!(f, "invalid piece"),
+        }
+    }
+}
+
+impl std::error::Error for FumenError {}
+
+/// The cells of a page. Row 0 is the bottom row, and row -1 is the garbage row below the field,
+/// which is pushed into the field when a page has its "rise" flag set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field([u8; FIELD_BLOCKS]);
+
+impl Default for Field {
+    fn default() -> Self {
+        Self([0; FIELD_BLOCKS])
+    }
+}
+
+impl Field {
+    pub fn get(&self, pos: Pos) -> Option<Cell> {
+        Self::index(pos).and_then(|index| number_to_cell(self.0[index]))
+    }
+
+    /// Set the content of a cell. Positions outside of the field are ignored.
+    pub fn set(&mut self, pos: Pos, cell: Option<Cell>) {
+        if let Some(index) = Self::index(pos) {
+            self.0[index] = cell_to_number(cell);
+        }
+    }
+
+    /// The non-empty cells of the field, excluding the garbage row.
+    pub fn cells(&self) -> impl Iterator<Item = (Pos, Cell)> + '_ {
+        (0..FIELD_HEIGHT as i8)
+            .flat_map(|y| (0..FIELD_WIDTH as i8).map(move |x| Pos::new(x, y)))
+            .filter_map(|pos| self.get(pos).map(|cell| (pos, cell)))
+    }
+
+    fn index(pos: Pos) -> Option<usize> {
+        if !(0..FIELD_WIDTH as i8).contains(&pos.x) || !(-1..FIELD_HEIGHT as i8).contains(&pos.y) {
+            return None;
+        }
+        Some((FIELD_HEIGHT as i8 - 1 - pos.y) as usize * FIELD_WIDTH + pos.x as usize)
+    }
+
+    fn row(&self, y: usize) -> &[u8] {
+        let start = (FIELD_HEIGHT - 1 - y) * FIELD_WIDTH;
+        &self.0[start..start + FIELD_WIDTH]
+    }
+
+    fn row_mut(&mut self, y: usize) -> &mut [u8] {
+        let start = (FIELD_HEIGHT - 1 - y) * FIELD_WIDTH;
+        &mut self.0[start..start + FIELD_WIDTH]
+    }
+
+    /// Remove the full rows, moving the rows above them down.
+    fn clear_lines(&mut self) {
+        let mut y = 0;
+        while y < FIELD_HEIGHT {
+            if self.row(y).iter().all(|n| *n != 0) {
+                for above in y..FIELD_HEIGHT - 1 {
+                    let row: [u8; FIELD_WIDTH] = self.row(above + 1).try_into().unwrap();
+                    self.row_mut(above).copy_from_slice(&row);
+                }
+                self.row_mut(FIELD_HEIGHT - 1).fill(0);
+            } else {
+                y += 1;
+            }
+        }
+    }
+
+    /// Push the garbage row into the bottom of the field.
+    fn rise(&mut self) {
+        // The garbage row is stored right after the bottom row, so this is a matter of shifting
+        // the whole field up by one row.
+        self.0.copy_within(FIELD_WIDTH.., 0);
+        self.0[FIELD_BLOCKS - FIELD_WIDTH..].fill(0);
+    }
+
+    fn mirror(&mut self) {
+        for y in 0..FIELD_HEIGHT {
+            self.row_mut(y).reverse();
+        }
+    }
+}
+
+/// A page of a fumen.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page {
+    pub field: Field,
+    /// The piece being placed, if any.
+    pub piece: Option<(Tetrimino, Pos)>,
+    pub comment: String,
+    /// Whether the piece is locked into the field (clearing the full lines) on the next page.
+    pub lock: bool,
+    /// Whether the garbage row is pushed into the field on the next page.
+    pub rise: bool,
+    /// Whether the field is mirrored on the next page.
+    pub mirror: bool,
+}
+
+impl Page {
+    pub fn new(field: Field) -> Self {
+        Self {
+            field,
+            piece: None,
+            comment: String::new(),
+            lock: true,
+            rise: false,
+            mirror: false,
+        }
+    }
+
+    /// The field of the page that follows this one.
+    fn next_field(&self) -> Field {
+        let mut field = self.field.clone();
+        if self.lock {
+            if let Some((tetrimino, pos)) = self.piece {
+                for block in tetrimino.block_positions(&pos) {
+                    field.set(block, Some(Cell::Mino(tetrimino.kind)));
+                }
+            }
+            field.clear_lines();
+            if self.rise {
+                field.rise();
+            }
+            if self.mirror {
+                field.mirror();
+            }
+        }
+        field
+    }
+}
+
+/// The pieces of a "quiz" page, which are stored in its comment as `#Q=[hold](current)queue`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Quiz {
+    pub hold: Option<TetriminoKind>,
+    pub current: Option<TetriminoKind>,
+    pub queue: Vec<TetriminoKind>,
+}
+
+impl Quiz {
+    pub fn parse(comment: &str) -> Option<Self> {
+        let rest = comment.strip_prefix(QUIZ_PREFIX)?;
+        let (hold, rest) = parse_slot(rest, '[', ']')?;
+        let (current, rest) = parse_slot(rest, '(', ')')?;
+        let queue = rest.chars().map_while(TetriminoKind::from_letter).collect();
+        Some(Self {
+            hold,
+            current,
+            queue,
+        })
+    }
+}
+
+impl fmt::Display for Quiz {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{QUIZ_PREFIX}[")?;
+        if let Some(hold) = self.hold {
+            write!(f, "{}", hold.letter())?;
+        }
+        write!(f, "](")?;
+        if let Some(current) = self.current {
+            write!(f, "{}", current.letter())?;
+        }
+        write!(f, ")")?;
+        for kind in &self.queue {
+            write!(f, "{}", kind.letter())?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse an optional piece between the given delimiters, returning the rest of the input.
+fn parse_slot(s: &str, open: char, close: char) -> Option<(Option<TetriminoKind>, &str)> {
+    let s = s.strip_prefix(open)?;
+    let end = s.find(close)?;
+    let kind = match &s[..end] {
+        "" => None,
+        piece => Some(TetriminoKind::from_letter(piece.chars().next()?)?),
+    };
+    Some((kind, &s[end + 1..]))
+}
+
+/// Decode all the pages of a fumen. The data can also be a URL containing the fumen.
+pub fn decode(data: &str) -> Result<Vec<Page>, FumenError> {
+    let start = data.find(PREFIX).ok_or(FumenError::UnsupportedVersion)?;
+    let mut values = Values::decode(&data[start + PREFIX.len()..])?;
+
+    let mut pages = Vec::new();
+    let mut field = Field::default();
+    let mut comment = String::new();
+    let mut repeat = 0;
+    while !values.is_empty() {
+        if repeat > 0 {
+            repeat -= 1;
+        } else {
+            let mut index = 0;
+            let mut changed = true;
+            while index < FIELD_BLOCKS {
+                let value = values.poll(2)?;
+                let diff = (value / FIELD_BLOCKS) as i16 - 8;
+                let count = value % FIELD_BLOCKS + 1;
+                if diff == 0 && count == FIELD_BLOCKS {
+                    changed = false;
+                }
+                for cell in field.0.iter_mut().skip(index).take(count) {
+                    *cell = (*cell as i16 + diff).clamp(0, 8) as u8;
+                }
+                index += count;
+            }
+            if !changed {
+                repeat = values.poll(1)?;
+            }
+        }
+
+        let mut action = values.poll(3)?;
+        let kind = action % 8;
+        action /= 8;
+        let rotation = action % 4;
+        action /= 4;
+        let coordinate = action % FIELD_BLOCKS;
+        action /= FIELD_BLOCKS;
+        let [rise, mirror, _colorize, has_comment, no_lock] =
+            std::array::from_fn(|bit| (action >> bit) & 1 == 1);
+
+        if has_comment {
+            let len = values.poll(2)?;
+            let mut escaped = String::with_capacity(len);
+            for _ in 0..len.div_ceil(4) {
+                let mut value = values.poll(5)?;
+                for _ in 0..4 {
+                    escaped.push(COMMENT_TABLE[value % (COMMENT_TABLE.len() + 1)] as char);
+                    value /= COMMENT_TABLE.len() + 1;
+                }
+            }
+            escaped.truncate(len);
+            comment = unescape(&escaped);
+        }
+
+        let piece = match number_to_cell(kind as u8) {
+            Some(Cell::Mino(kind)) => {
+                let facing = rotation_to_facing(rotation);
+                let anchor = Pos::new(
+                    (coordinate % FIELD_WIDTH) as i8,
+                    (FIELD_HEIGHT - 1) as i8 - (coordinate / FIELD_WIDTH) as i8,
+                );
+                Some(from_fumen_piece(kind, facing, anchor).ok_or(FumenError::InvalidPiece)?)
+            }
+            Some(Cell::Garbage) => return Err(FumenError::InvalidPiece),
+            None => None,
+        };
+
+        let page = Page {
+            field: field.clone(),
+            piece,
+            comment: comment.clone(),
+            lock: !no_lock,
+            rise,
+            mirror,
+        };
+        field = page.next_field();
+        pages.push(page);
+    }
+
+    Ok(pages)
+}
+
+/// Encode pages into a fumen.
+///
+/// Pieces that can't be represented in a fumen (e.g. because they're above the top of the field)
+/// are left out.
+pub fn encode(pages: &[Page]) -> String {
+    let mut values = Values::default();
+    let mut prev_field = Field::default();
+    let mut prev_comment = String::new();
+    // Index of the repeat count of the last run of unchanged fields, if any
+    let mut repeat_index: Option<usize> = None;
+
+    for (page_index, page) in pages.iter().enumerate() {
+        // Field, as runs of identical differences with the previous field
+        let diffs: Vec<usize> = page
+            .field
+            .0
+            .iter()
+            .zip(prev_field.0.iter())
+            .map(|(cur, prev)| (*cur as i16 - *prev as i16 + 8) as usize)
+            .collect();
+        let unchanged = diffs.iter().all(|diff| *diff == 8);
+        match repeat_index {
+            Some(index) if unchanged && values.0[index] < 63 => values.0[index] += 1,
+            _ => {
+                for run in diffs.chunk_by(|a, b| a == b) {
+                    values.push(run[0] * FIELD_BLOCKS + run.len() - 1, 2);
+                }
+                if unchanged {
+                    values.push(0, 1);
+                    repeat_index = Some(values.0.len() - 1);
+                } else {
+                    repeat_index = None;
+                }
+            }
+        }
+
+        // Action
+        let (kind, rotation, coordinate) = page
+            .piece
+            .and_then(|(tetrimino, pos)| {
+                let anchor = to_fumen_anchor(tetrimino, pos)?;
+                let index = Field::index(anchor)?;
+                Some((
+                    cell_to_number(Some(Cell::Mino(tetrimino.kind))) as usize,
+                    facing_to_rotation(tetrimino.facing),
+                    index,
+                ))
+            })
+            .unwrap_or((0, 0, 0));
+        let has_comment = if page_index == 0 {
+            !page.comment.is_empty()
+        } else {
+            page.comment != prev_comment
+        };
+        let flags = page.rise as usize
+            | (page.mirror as usize) << 1
+            | ((page_index == 0) as usize) << 2
+            | (has_comment as usize) << 3
+            | (!page.lock as usize) << 4;
+        values.push(
+            kind + 8 * (rotation + 4 * (coordinate + FIELD_BLOCKS * flags)),
+            3,
+        );
+
+        if has_comment {
+            let escaped = escape(&page.comment);
+            let escaped = &escaped.as_bytes()[..escaped.len().min(MAX_COMMENT_LEN)];
+            values.push(escaped.len(), 2);
+            for chunk in escaped.chunks(4) {
+                let value = chunk.iter().rev().fold(0, |value, c| {
+                    let index = COMMENT_TABLE.iter().position(|t| t == c).unwrap_or(0);
+                    value * (COMMENT_TABLE.len() + 1) + index
+                });
+                values.push(value, 5);
+            }
+            prev_comment.clone_from(&page.comment);
+        }
+
+        prev_field = page.next_field();
+    }
+
+    // Same layout as other encoders: the data is split with `?` so that it wraps in URLs.
+    let data = values.encode();
+    let (head, tail) = data.split_at(data.len().min(42));
+    let mut result = format!("{PREFIX}{head}");
+    for chunk in tail.as_bytes().chunks(47) {
+        result.push('?');
+        // The data is ASCII only
+        result.push_str(std::str::from_utf8(chunk).unwrap());
+    }
+    result
+}
+
+/// The base64 "digits" a fumen is made of.
+#[derive(Default)]
+struct Values(Vec<u8>);
+
+impl Values {
+    fn decode(data: &str) -> Result<Self, FumenError> {
+        let mut values = Vec::with_capacity(data.len());
+        for c in data.chars().filter(|c| *c != '?' && !c.is_whitespace()) {
+            let value = ENCODE_TABLE
+                .iter()
+                .position(|t| *t as char == c)
+                .ok_or(FumenError::InvalidCharacter(c))?;
+            values.push(value as u8);
+        }
+        // Values are consumed from the front
+        values.reverse();
+        Ok(Self(values))
+    }
+
+    fn encode(&self) -> String {
+        self.0
+            .iter()
+            .map(|value| ENCODE_TABLE[*value as usize] as char)
+            .collect()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Read a value made of `digits` digits, least significant first.
+    fn poll(&mut self, digits: u32) -> Result<usize, FumenError> {
+        let mut value = 0;
+        for digit in 0..digits {
+            let v = self.0.pop().ok_or(FumenError::UnexpectedEnd)?;
+            value += v as usize * 64usize.pow(digit);
+        }
+        Ok(value)
+    }
+
+    fn push(&mut self, mut value: usize, digits: u32) {
+        for _ in 0..digits {
+            self.0.push((value % 64) as u8);
+            value /= 64;
+        }
+    }
+}
+
+fn cell_to_number(cell: Option<Cell>) -> u8 {
+    match cell {
+        None => 0,
+        Some(Cell::Mino(TetriminoKind::I)) => 1,
+        Some(Cell::Mino(TetriminoKind::L)) => 2,
+        Some(Cell::Mino(TetriminoKind::O)) => 3,
+        Some(Cell::Mino(TetriminoKind::Z)) => 4,
+        Some(Cell::Mino(TetriminoKind::T)) => 5,
+        Some(Cell::Mino(TetriminoKind::J)) => 6,
+        Some(Cell::Mino(TetriminoKind::S)) => 7,
+        Some(Cell::Garbage) => 8,
+    }
+}
+
+fn number_to_cell(number: u8) -> Option<Cell> {
+    let kind = match number {
+        1 => TetriminoKind::I,
+        2 => TetriminoKind::L,
+        3 => TetriminoKind::O,
+        4 => TetriminoKind::Z,
+        5 => TetriminoKind::T,
+        6 => TetriminoKind::J,
+        7 => TetriminoKind::S,
+        8 => return Some(Cell::Garbage),
+        _ => return None,
+    };
+    Some(Cell::Mino(kind))
+}
+
+fn rotation_to_facing(rotation: usize) -> Facing {
+    match rotation {
+        0 => Facing::South,
+        1 => Facing::East,
+        2 => Facing::North,
+        _ => Facing::West,
+    }
+}
+
+fn facing_to_rotation(facing: Facing) -> usize {
+    match facing {
+        Facing::South => 0,
+        Facing::East => 1,
+        Facing::North => 2,
+        Facing::West => 3,
+    }
+}
+
+/// The blocks of a piece relative to its position in a fumen.
+///
+/// Fumen pieces rotate around a block (rather than SRS' true rotation), and the O, I, S and Z
+/// pieces have their position shifted in some orientations so that it stays put when they rotate.
+fn fumen_offsets(kind: TetriminoKind, facing: Facing) -> [Pos; 4] {
+    let north = match kind {
+        TetriminoKind::I => [(0, 0), (-1, 0), (1, 0), (2, 0)],
+        TetriminoKind::T => [(0, 0), (-1, 0), (1, 0), (0, 1)],
+        TetriminoKind::O => [(0, 0), (1, 0), (0, 1), (1, 1)],
+        TetriminoKind::L => [(0, 0), (-1, 0), (1, 0), (1, 1)],
+        TetriminoKind::J => [(0, 0), (-1, 0), (1, 0), (-1, 1)],
+        TetriminoKind::S => [(0, 0), (-1, 0), (0, 1), (1, 1)],
+        TetriminoKind::Z => [(0, 0), (1, 0), (0, 1), (-1, 1)],
+    };
+    let (shift_x, shift_y) = match (kind, facing) {
+        (TetriminoKind::O, Facing::North) => (0, 1),
+        (TetriminoKind::O, Facing::South) => (-1, 0),
+        (TetriminoKind::O, Facing::West) => (-1, 1),
+        (TetriminoKind::I, Facing::South) => (-1, 0),
+        (TetriminoKind::I, Facing::West) => (0, 1),
+        (TetriminoKind::S, Facing::North) => (0, 1),
+        (TetriminoKind::S, Facing::East) => (1, 0),
+        (TetriminoKind::Z, Facing::North) => (0, 1),
+        (TetriminoKind::Z, Facing::West) => (-1, 0),
+        _ => (0, 0),
+    };
+    north.map(|(x, y)| {
+        let (x, y) = match facing {
+            Facing::North => (x, y),
+            Facing::East => (y, -x),
+            Facing::South => (-x, -y),
+            Facing::West => (-y, x),
+        };
+        Pos::new(x - shift_x, y - shift_y)
+    })
+}
+
+/// Find where `offsets` must be placed so that they cover exactly `blocks`.
+fn find_anchor(blocks: [Pos; 4], offsets: [Pos; 4]) -> Option<Pos> {
+    blocks
+        .iter()
+        .map(|block| Pos::new(block.x - offsets[0].x, block.y - offsets[0].y))
+        .find(|anchor| {
+            offsets
+                .iter()
+                .all(|offset| blocks.contains(&(*anchor + *offset)))
+        })
+}
+
+fn from_fumen_piece(kind: TetriminoKind, facing: Facing, anchor: Pos) -> Option<(Tetrimino, Pos)> {
+    let tetrimino = Tetrimino { kind, facing };
+    let blocks = fumen_offsets(kind, facing).map(|offset| anchor + offset);
+    let pos = find_anchor(blocks, *tetrimino.block_offsets())?;
+    Some((tetrimino, pos))
+}
+
+fn to_fumen_anchor(tetrimino: Tetrimino, pos: Pos) -> Option<Pos> {
+    find_anchor(
+        tetrimino.block_positions(&pos),
+        fumen_offsets(tetrimino.kind, tetrimino.facing),
+    )
+}
+
+/// Equivalent of JavaScript's `escape()`, which fumen applies to comments.
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for unit in s.encode_utf16() {
+        match char::from_u32(unit as u32) {
+            Some(c) if c.is_ascii_alphanumeric() || "@*_+-./".contains(c) => escaped.push(c),
+            _ if unit < 0x100 => escaped.push_str(&format!("%{unit:02X}")),
+            _ => escaped.push_str(&format!("%u{unit:04X}")),
+        }
+    }
+    escaped
+}
+
+/// Equivalent of JavaScript's `unescape()`.
+fn unescape(s: &str) -> String {
+    let mut units = Vec::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(c) = rest.chars().next() {
+        let unit = if c != '%' {
+            None
+        } else if let Some(hex) = rest.strip_prefix("%u").and_then(|r| r.get(..4)) {
+            u16::from_str_radix(hex, 16).ok().map(|unit| (unit, 6))
+        } else {
+            rest.get(1..3)
+                .and_then(|hex| u16::from_str_radix(hex, 16).ok())
+                .map(|unit| (unit, 3))
+        };
+        match unit {
+            Some((unit, len)) => {
+                units.push(unit);
+                rest = &rest[len..];
+            }
+            None => {
+                let mut buf = [0; 2];
+                units.extend_from_slice(c.encode_utf16(&mut buf));
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    String::from_utf16_lossy(&units)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_field() {
+        let data = "v115@vhAAgH";
+        let pages = decode(data).unwrap();
+        assert_eq!(pages.len(), 1);
+        assert_eq!(pages[0].field, Field::default());
+        assert_eq!(pages[0].piece, None);
+        assert_eq!(encode(&pages), data);
+    }
+
+    #[test]
+    fn garbage_row() {
+        let data = "v115@bhJ8JeAgH";
+        let pages = decode(data).unwrap();
+        assert_eq!(pages.len(), 1);
+        let field = &pages[0].field;
+        for x in 0..FIELD_WIDTH as i8 {
+            assert_eq!(field.get(Pos::new(x, 0)), Some(Cell::Garbage));
+            assert_eq!(field.get(Pos::new(x, 1)), None);
+        }
+        assert_eq!(encode(&pages), data);
+    }
+
+    #[test]
+    fn pieces_and_comments() {
+        let mut field = Field::default();
+        field.set(Pos::new(0, 0), Some(Cell::Mino(TetriminoKind::L)));
+        field.set(Pos::new(9, 2), Some(Cell::Garbage));
+        let quiz = Quiz {
+            hold: Some(TetriminoKind::O),
+            current: Some(TetriminoKind::T),
+            queue: vec![TetriminoKind::S, TetriminoKind::Z, TetriminoKind::I],
+        };
+        let mut first = Page::new(field);
+        first.piece = Some((TetriminoKind::T.into(), Pos::new(4, 1)));
+        first.comment = quiz.to_string();
+        let mut second = Page::new(first.next_field());
+        second.comment = first.comment.clone();
+
+        let pages = decode(&encode(&[first.clone(), second.clone()])).unwrap();
+        assert_eq!(pages, [first, second]);
+        assert_eq!(Quiz::parse(&pages[0].comment), Some(quiz));
+    }
+}
@@ -3,7 +3,12 @@
 //!
 //! The code in this module should (in theory) be mostly free of any dependency on Bevy.
 
+pub mod ai;
+pub mod board;
 mod data;
+pub mod finesse;
+pub mod fumen;
+pub mod movegen;
 mod pos;
 mod tetrimino;
 
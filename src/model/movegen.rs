@@ -0,0 +1,180 @@
+//! Finding every placement a piece can reach from where it is: dropped from above, but also tucked
+//! under overhangs after a soft drop, or spun into place.
+//!
+//! Moves follow the rules of the game, where rotations that don't fit fail rather than kick the
+//! piece elsewhere.
+
+use std::collections::{HashSet, VecDeque};
+
+use super::{board::Board, finesse::Move, Pos, Tetrimino, TetriminoKind};
+
+/// A place where a piece can lock, and the moves to make to get it there.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Placement {
+    pub tetrimino: Tetrimino,
+    pub pos: Pos,
+    /// The moves to make before hard dropping the piece.
+    pub moves: Vec<Move>,
+    /// Whether the piece was rotated into place, which can make it a spin.
+    pub rotated_last: bool,
+}
+
+/// Every placement that can be reached from the given position, with the shortest moves to get
+/// there.
+///
+/// Placements covering the same cells are only returned once, except for T pieces, which can get
+/// there both with and without a rotation as their last move.
+pub fn placements(board: &Board, tetrimino: Tetrimino, pos: Pos) -> Vec<Placement> {
+    if !board.is_valid(&tetrimino, &pos) {
+        return Vec::new();
+    }
+    let mut visited = HashSet::from([(tetrimino.facing as u8, pos.x, pos.y)]);
+    let mut queue = VecDeque::from([(tetrimino, pos, Vec::new())]);
+    let mut found = HashSet::new();
+    let mut placements = Vec::new();
+    while let Some((tetrimino, pos, moves)) = queue.pop_front() {
+        let dropped = board.lowest_valid_pos(&tetrimino, &pos);
+        // Hard dropping the piece any further makes it fall rather than spin into place
+        let rotated_last = dropped == pos && moves.last().is_some_and(Move::is_rotation);
+        let spin = rotated_last && tetrimino.kind == TetriminoKind::T;
+        if found.insert((covered_cells(&tetrimino, &dropped), spin)) {
+            placements.push(Placement {
+                tetrimino,
+                pos: dropped,
+                moves: moves.clone(),
+                rotated_last,
+            });
+        }
+
+        for step in Move::ALL {
+            let Some((next, next_pos)) = board.apply(step, tetrimino, pos) else {
+                continue;
+            };
+            if visited.insert((next.facing as u8, next_pos.x, next_pos.y)) {
+                let mut moves = moves.clone();
+                moves.push(step);
+                queue.push_back((next, next_pos, moves));
+            }
+        }
+    }
+    placements
+}
+
+/// The way to get a piece from where it is to a placement covering the same cells as `target`, if
+/// there is one.
+pub fn find_placement(
+    board: &Board,
+    tetrimino: Tetrimino,
+    pos: Pos,
+    target: (&Tetrimino, &Pos),
+) -> Option<Placement> {
+    let goal = covered_cells(target.0, target.1);
+    placements(board, tetrimino, pos)
+        .into_iter()
+        .find(|placement| covered_cells(&placement.tetrimino, &placement.pos) == goal)
+}
+
+/// The cells covered by a piece, in a canonical order.
+fn covered_cells(tetrimino: &Tetrimino, pos: &Pos) -> [(i8, i8); 4] {
+    let mut blocks = tetrimino
+        .block_positions(pos)
+        .map(|block| (block.x, block.y));
+    blocks.sort_unstable();
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        game::spawners::INITIAL_POS,
+        model::{Facing, TetriminoKind},
+    };
+
+    use super::*;
+
+    /// A board with the given rows filled, except for the listed holes. Extra blocks are added on
+    /// top.
+    fn board(rows: &[&[i8]], blocks: &[(i8, i8)]) -> Board {
+        let filled = rows.iter().enumerate().flat_map(|(y, holes)| {
+            (0..10)
+                .filter(|x| !holes.contains(x))
+                .map(move |x| Pos::new(x, y as i8))
+        });
+        Board::from_blocks(filled.chain(blocks.iter().map(|&(x, y)| Pos::new(x, y))))
+    }
+
+    fn find(board: &Board, kind: TetriminoKind, (facing, pos): (Facing, Pos)) -> Option<Placement> {
+        let target = Tetrimino { kind, facing };
+        find_placement(board, Tetrimino::new(kind), INITIAL_POS, (&target, &pos))
+    }
+
+    #[test]
+    fn every_drop_on_empty_board() {
+        let empty = Board::default();
+        for &kind in TetriminoKind::all() {
+            for facing in [Facing::North, Facing::East, Facing::South, Facing::West] {
+                let tetrimino = Tetrimino { kind, facing };
+                for x in -2..12 {
+                    let above = Pos::new(x, 10);
+                    if !empty.is_valid(&tetrimino, &above) {
+                        continue;
+                    }
+                    let dropped = empty.lowest_valid_pos(&tetrimino, &above);
+                    let placement = find(&empty, kind, (facing, dropped));
+                    assert!(placement.is_some(), "{kind:?} {facing:?} at {x}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn placements_rest_on_the_stack() {
+        let board = board(&[&[4], &[3, 4, 5]], &[(3, 2)]);
+        for placement in placements(&board, Tetrimino::new(TetriminoKind::L), INITIAL_POS) {
+            assert!(board.is_valid(&placement.tetrimino, &placement.pos));
+            assert_eq!(
+                board.lowest_valid_pos(&placement.tetrimino, &placement.pos),
+                placement.pos
+            );
+        }
+    }
+
+    #[test]
+    fn tuck_under_overhang() {
+        // A roof over the bottom left corner
+        let board = board(&[], &[(0, 2), (1, 2), (2, 2)]);
+        let placement = find(&board, TetriminoKind::O, (Facing::North, Pos::new(0, 0))).unwrap();
+        let drop = placement
+            .moves
+            .iter()
+            .position(|step| *step == Move::SoftDrop)
+            .unwrap();
+        assert!(placement.moves[drop + 1..].contains(&Move::Left));
+        assert!(!placement.rotated_last);
+    }
+
+    #[test]
+    fn t_spin_into_slot() {
+        // A T-spin double slot, which the overhang closes to pieces dropped from above
+        let board = board(&[&[4], &[3, 4, 5]], &[(3, 2)]);
+        let slot = (Facing::South, Pos::new(4, 1));
+        let placement = find(&board, TetriminoKind::T, slot).unwrap();
+        assert!(placement.moves.last().is_some_and(Move::is_rotation));
+        assert!(placement.rotated_last);
+
+        let dropped = Tetrimino {
+            kind: TetriminoKind::T,
+            facing: Facing::South,
+        };
+        assert_ne!(board.lowest_valid_pos(&dropped, &Pos::new(4, 10)), slot.1);
+    }
+
+    #[test]
+    fn sealed_hole_is_unreachable() {
+        let board = board(&[&[0, 1], &[0, 1]], &[(0, 2), (1, 2)]);
+        assert_eq!(
+            find(&board, TetriminoKind::O, (Facing::North, Pos::new(0, 0))),
+            None
+        );
+    }
+}
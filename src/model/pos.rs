@@ -45,7 +45,7 @@ impl Pos {
     }
 
     pub fn to_index(self) -> usize {
-        ((self.y as u8) * MATRIX_WIDTH + self.x as u8) as usize
+        self.y as usize * MATRIX_WIDTH as usize + self.x as usize
     }
 
     pub fn from_index(index: usize) -> Self {
@@ -89,3 +89,19 @@ impl From<Pos> for Transform {
         (&value).into()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::game::MATRIX_HEIGHT;
+
+    use super::*;
+
+    #[test]
+    fn index_round_trip() {
+        // Rows above 25 used to overflow the index
+        for index in 0..MATRIX_WIDTH as usize * MATRIX_HEIGHT as usize {
+            assert_eq!(Pos::from_index(index).to_index(), index);
+        }
+        assert_eq!(Pos::new(9, 39).to_index(), 399);
+    }
+}
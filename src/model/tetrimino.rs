@@ -1,16 +1,11 @@
-use bevy::{color::palettes, prelude::*};
+use bevy::prelude::*;
 use num_enum::TryFromPrimitive;
-use rand::thread_rng;
+use std::collections::VecDeque;
 
-use super::{data::OFFSETS, pos::Pos};
+use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
 
-const YELLOW: Srgba = palettes::css::YELLOW;
-const LIGHT_BLUE: Srgba = palettes::css::BLUE;
-const PURPLE: Srgba = palettes::css::PURPLE;
-const ORANGE: Srgba = palettes::css::ORANGE;
-const DARK_BLUE: Srgba = palettes::css::MIDNIGHT_BLUE;
-const GREEN: Srgba = palettes::css::GREEN;
-const RED: Srgba = palettes::css::RED;
+use super::{data::OFFSETS, pos::Pos};
 
 #[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Tetrimino {
@@ -98,18 +93,23 @@ pub enum TetriminoKind {
 }
 
 impl TetriminoKind {
-    pub fn color(&self) -> Color {
+    /// The letter the tetrimino is usually named after.
+    pub fn letter(&self) -> char {
         match self {
-            TetriminoKind::O => YELLOW.into(),
-            TetriminoKind::I => LIGHT_BLUE.into(),
-            TetriminoKind::T => PURPLE.into(),
-            TetriminoKind::L => ORANGE.into(),
-            TetriminoKind::J => DARK_BLUE.into(),
-            TetriminoKind::S => GREEN.into(),
-            TetriminoKind::Z => RED.into(),
+            TetriminoKind::O => 'O',
+            TetriminoKind::I => 'I',
+            TetriminoKind::T => 'T',
+            TetriminoKind::L => 'L',
+            TetriminoKind::J => 'J',
+            TetriminoKind::S => 'S',
+            TetriminoKind::Z => 'Z',
         }
     }
 
+    pub fn from_letter(c: char) -> Option<Self> {
+        Self::all().iter().copied().find(|kind| kind.letter() == c)
+    }
+
     pub fn all() -> &'static [Self] {
         &[
             TetriminoKind::O,
@@ -123,6 +123,15 @@ impl TetriminoKind {
     }
 }
 
+/// The content of a non-empty cell of the matrix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cell {
+    /// A block left by a tetrimino when it locked.
+    Mino(TetriminoKind),
+    /// A block that wasn't part of any tetrimino, e.g. from a pre-built setup.
+    Garbage,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Component)]
 #[repr(u8)]
 pub enum Facing {
@@ -152,29 +161,109 @@ impl Facing {
     }
 }
 
-#[derive(Reflect)]
-pub struct Bag(Vec<TetriminoKind>);
+/// How the sequence of tetriminos is generated.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    Default,
+    TryFromPrimitive,
+    strum::Display,
+    strum::EnumString,
+)]
+#[strum(serialize_all = "kebab-case", ascii_case_insensitive)]
+#[repr(u8)]
+pub enum Randomizer {
+    /// Each bag contains every tetrimino once, in random order.
+    #[default]
+    SevenBag,
+    /// Each bag contains every tetrimino twice, in random order.
+    FourteenBag,
+    /// Every tetrimino is picked at random, regardless of the previous ones.
+    Random,
+    /// Tetriminos are picked at random, picking again once when getting the same as the previous
+    /// one (as in the NES version).
+    Classic,
+}
+
+/// The randomizer handing out tetriminos, a "7-bag" unless specified otherwise.
+///
+/// The bag draws from its own seeded RNG, so two bags created with the same seed produce the same
+/// sequence of tetriminos. Only the seed is saved with replays, so the RNG must be portable: unlike
+/// `StdRng`, ChaCha12 is guaranteed to give the same output across platforms and versions of `rand`
+/// (and it is what `StdRng` currently uses, so sequences from earlier seeds are kept).
+#[derive(Clone)]
+pub struct Bag {
+    pieces: VecDeque<TetriminoKind>,
+    rng: ChaCha12Rng,
+    randomizer: Randomizer,
+    /// The last tetrimino generated by the randomizer.
+    last: Option<TetriminoKind>,
+}
 
 impl Bag {
-    pub fn new() -> Self {
-        let mut bag = Self(Vec::with_capacity(7));
-        bag.refill();
+    pub fn new(seed: u64) -> Self {
+        Self::with_queue(seed, [])
+    }
+
+    /// Create a bag that hands out the given pieces first, before starting to randomize.
+    pub fn with_queue(seed: u64, queue: impl IntoIterator<Item = TetriminoKind>) -> Self {
+        Self::with_randomizer(Randomizer::default(), seed, queue)
+    }
+
+    /// Create a bag using the given randomizer once the given pieces have been handed out.
+    pub fn with_randomizer(
+        randomizer: Randomizer,
+        seed: u64,
+        queue: impl IntoIterator<Item = TetriminoKind>,
+    ) -> Self {
+        let mut bag = Self {
+            pieces: queue.into_iter().collect(),
+            rng: ChaCha12Rng::seed_from_u64(seed),
+            randomizer,
+            last: None,
+        };
+        if bag.pieces.is_empty() {
+            bag.refill();
+        }
         bag
     }
 
     pub fn refill(&mut self) {
-        use rand::seq::SliceRandom;
-        self.0.clear();
-        self.0.extend_from_slice(TetriminoKind::all());
-        self.0.shuffle(&mut thread_rng());
+        use rand::{seq::SliceRandom, Rng};
+        let all = TetriminoKind::all();
+        match self.randomizer {
+            Randomizer::SevenBag | Randomizer::FourteenBag => {
+                let mut pieces = all.to_vec();
+                if self.randomizer == Randomizer::FourteenBag {
+                    pieces.extend_from_slice(all);
+                }
+                pieces.shuffle(&mut self.rng);
+                self.pieces.extend(pieces);
+            }
+            Randomizer::Random => {
+                self.pieces.push_back(all[self.rng.gen_range(0..all.len())]);
+            }
+            Randomizer::Classic => {
+                let mut piece = all[self.rng.gen_range(0..all.len())];
+                if Some(piece) == self.last {
+                    piece = all[self.rng.gen_range(0..all.len())];
+                }
+                self.pieces.push_back(piece);
+            }
+        }
+        self.last = self.pieces.back().copied();
     }
 
     pub fn pop_next(&mut self) -> TetriminoKind {
         let next = self
-            .0
-            .pop()
+            .pieces
+            .pop_front()
             .expect("There should be at least one Tetrimino left in the bag!");
-        if self.0.last().is_none() {
+        if self.pieces.is_empty() {
             self.refill();
         }
 
@@ -182,15 +271,53 @@ impl Bag {
     }
 
     pub fn peek_next(&self) -> TetriminoKind {
-        self.0
-            .last()
+        self.pieces
+            .front()
             .copied()
             .expect("There should be at least one Tetrimino left in the bag!")
     }
+
+    /// The next `n` pieces that will come out of the bag.
+    pub fn peek(&self, n: usize) -> Vec<TetriminoKind> {
+        let mut bag = self.clone();
+        (0..n).map(|_| bag.pop_next()).collect()
+    }
+
+    /// Put a piece back, so that it is the next one to come out of the bag.
+    pub fn push_front(&mut self, kind: TetriminoKind) {
+        self.pieces.push_front(kind);
+    }
 }
 
 impl Default for Bag {
     fn default() -> Self {
-        Self::new()
+        Self::new(rand::random())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sequence(randomizer: Randomizer, seed: u64) -> String {
+        let mut bag = Bag::with_randomizer(randomizer, seed, []);
+        (0..14).map(|_| bag.pop_next().letter()).collect()
+    }
+
+    /// Replays only store the seed, so the same seed must always give the same pieces.
+    #[test]
+    fn stable_sequences() {
+        assert_eq!(sequence(Randomizer::SevenBag, 42), "ZTSOJILJZTOLSI");
+        assert_eq!(sequence(Randomizer::FourteenBag, 42), "OZLISJTSOJTZLI");
+        assert_eq!(sequence(Randomizer::Random, 42), "LLJTOSOZLTLIOL");
+        assert_eq!(sequence(Randomizer::Classic, 42), "LJTOSOZLTLIOLO");
+    }
+
+    #[test]
+    fn queue_comes_first() {
+        let mut bag = Bag::with_queue(42, [TetriminoKind::T, TetriminoKind::T]);
+        assert_eq!(bag.pop_next(), TetriminoKind::T);
+        bag.push_front(TetriminoKind::I);
+        assert_eq!(bag.peek(2), [TetriminoKind::I, TetriminoKind::T]);
     }
 }
@@ -0,0 +1,367 @@
+//! Hand-made puzzles, loaded from `assets/puzzles` and from the puzzles saved by the editor in the
+//! data directory, and which of them have been solved.
+//!
+//! A puzzle is a RON file describing the board to start from, the pieces to play and the goal to
+//! reach:
+//!
+//! ```ron
+//! (
+//!     name: "T-spin double",
+//!     board: [
+//!         "XXX..XXXXX",
+//!         "XXXX.XXXXX",
+//!     ],
+//!     queue: "T",
+//!     goal: TSpinDouble,
+//! )
+//! ```
+//!
+//! The board is given from top to bottom, with `.` for empty cells, `X` for garbage and piece
+//! letters for the blocks of locked pieces.
+
+use std::{
+    collections::BTreeSet,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use bevy::{
+    asset::{
+        io::{AssetSource, Reader},
+        AssetLoader, AssetPath, AsyncReadExt, LoadContext, LoadedFolder,
+    },
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game::{fumen::Setup, puzzle::Goal, MATRIX_WIDTH, VISIBLE_HEIGHT},
+    model::{Cell, Pos, TetriminoKind},
+    storage::{self, invalid_data, read_string, read_varint, take, write_string, write_varint},
+};
+
+const MAGIC: &[u8; 4] = b"BTPZ";
+const VERSION: u8 = 1;
+/// The asset source reading from the data directory.
+const USER_SOURCE: &str = "user";
+/// The folder puzzles are loaded from, in the assets and in the data directory alike.
+const FOLDER: &str = "puzzles";
+pub const EXTENSION: &str = "puzzle.ron";
+
+/// Register the asset source of the data directory, where the editor saves puzzles.
+///
+/// Asset sources have to be registered before the `AssetPlugin` is added.
+pub fn register_source(app: &mut App) {
+    // Loading a folder that doesn't exist fails
+    let folder = saved_puzzle_dir();
+    if let Err(e) = fs::create_dir_all(&folder) {
+        warn!("Failed to create {}: {e}", folder.display());
+    }
+    let dir = storage::data_dir();
+    app.register_asset_source(
+        USER_SOURCE,
+        AssetSource::build().with_reader(AssetSource::get_default_reader(
+            dir.to_string_lossy().into_owned(),
+        )),
+    );
+}
+
+pub fn plugin(app: &mut App) {
+    app.init_asset::<Puzzle>()
+        .register_asset_loader(PuzzleLoader)
+        .insert_resource(PuzzleProgress::load())
+        .add_systems(Startup, load_puzzles);
+}
+
+/// A position to solve.
+#[derive(Asset, TypePath, Debug, Clone)]
+pub struct Puzzle {
+    pub name: String,
+    pub description: String,
+    pub setup: Setup,
+    pub goal: Goal,
+}
+
+impl Puzzle {
+    /// Write the puzzle in the format it is loaded from.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let file = PuzzleFile::from(self);
+        let ron = ron::ser::to_string_pretty(&file, ron::ser::PrettyConfig::default())
+            .map_err(io::Error::other)?;
+        if let Some(dir) = path.as_ref().parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, ron)
+    }
+}
+
+/// All the puzzles: those of the game's assets and those saved by the player.
+#[derive(Resource, Debug)]
+pub struct PuzzleFolders {
+    folders: Vec<Handle<LoadedFolder>>,
+    /// Puzzles saved with the editor since the folders were loaded.
+    saved: Vec<Handle<Puzzle>>,
+}
+
+impl PuzzleFolders {
+    /// The puzzles that finished loading, sorted by path.
+    pub fn loaded<'a>(
+        &self,
+        folders: &Assets<LoadedFolder>,
+        puzzles: &'a Assets<Puzzle>,
+    ) -> Vec<(String, &'a Puzzle)> {
+        let handles = self
+            .folders
+            .iter()
+            .filter_map(|folder| folders.get(folder))
+            .flat_map(|folder| &folder.handles)
+            .map(|handle| (handle.path(), handle.id().typed::<Puzzle>()))
+            .chain(self.saved.iter().map(|handle| (handle.path(), handle.id())));
+        let mut loaded: Vec<(String, &Puzzle)> = handles
+            .filter_map(|(path, id)| Some((path?.to_string(), puzzles.get(id)?)))
+            .collect();
+        loaded.sort_by(|(a, _), (b, _)| a.cmp(b));
+        // A puzzle saved before its folder finished loading shows up twice
+        loaded.dedup_by(|(a, _), (b, _)| a == b);
+        loaded
+    }
+
+    /// Load a puzzle just saved in the data directory, at the given path.
+    pub fn add_saved(&mut self, assets: &AssetServer, path: &Path) {
+        let Some(file_name) = path.file_name() else {
+            return;
+        };
+        let path = format!("{USER_SOURCE}://{FOLDER}/{}", file_name.to_string_lossy());
+        self.saved.push(assets.load(path));
+    }
+}
+
+/// The folder the editor saves puzzles to.
+pub fn saved_puzzle_dir() -> PathBuf {
+    storage::data_dir().join(FOLDER)
+}
+
+fn load_puzzles(mut commands: Commands, assets: Res<AssetServer>) {
+    commands.insert_resource(PuzzleFolders {
+        folders: vec![
+            assets.load_folder(FOLDER),
+            assets.load_folder(AssetPath::from(FOLDER).with_source(USER_SOURCE)),
+        ],
+        saved: Vec::new(),
+    });
+}
+
+/// A puzzle as it's written in its file.
+#[derive(Serialize, Deserialize)]
+struct PuzzleFile {
+    name: String,
+    #[serde(default)]
+    description: String,
+    board: Vec<String>,
+    queue: String,
+    #[serde(default)]
+    hold: Option<char>,
+    goal: Goal,
+}
+
+impl From<&Puzzle> for PuzzleFile {
+    fn from(puzzle: &Puzzle) -> Self {
+        let top = puzzle.setup.cells.iter().map(|(pos, _)| pos.y).max();
+        let board = top
+            .map(|top| {
+                (0..=top)
+                    .rev()
+                    .map(|y| {
+                        (0..MATRIX_WIDTH as i8)
+                            .map(|x| {
+                                let cell = puzzle.setup.cells.iter().find_map(|(pos, cell)| {
+                                    (*pos == Pos::new(x, y)).then_some(*cell)
+                                });
+                                match cell {
+                                    None => '.',
+                                    Some(Cell::Garbage) => 'X',
+                                    Some(Cell::Mino(kind)) => kind.letter(),
+                                }
+                            })
+                            .collect()
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self {
+            name: puzzle.name.clone(),
+            description: puzzle.description.clone(),
+            board,
+            queue: puzzle
+                .setup
+                .queue
+                .iter()
+                .map(TetriminoKind::letter)
+                .collect(),
+            hold: puzzle.setup.hold.map(|kind| kind.letter()),
+            goal: puzzle.goal,
+        }
+    }
+}
+
+impl TryFrom<PuzzleFile> for Puzzle {
+    type Error = PuzzleError;
+
+    fn try_from(file: PuzzleFile) -> Result<Self, Self::Error> {
+        if file.board.len() > VISIBLE_HEIGHT as usize {
+            return Err(PuzzleError::BoardTooBig);
+        }
+        let mut cells = Vec::new();
+        for (y, row) in file.board.iter().rev().enumerate() {
+            if row.chars().count() > MATRIX_WIDTH as usize {
+                return Err(PuzzleError::BoardTooBig);
+            }
+            for (x, c) in row.chars().enumerate() {
+                let cell = match c {
+                    '.' | ' ' => continue,
+                    'X' | 'G' => Cell::Garbage,
+                    c => Cell::Mino(
+                        TetriminoKind::from_letter(c).ok_or(PuzzleError::InvalidCell(c))?,
+                    ),
+                };
+                cells.push((Pos::new(x as i8, y as i8), cell));
+            }
+        }
+        let piece = |c: char| TetriminoKind::from_letter(c).ok_or(PuzzleError::InvalidPiece(c));
+
+        Ok(Self {
+            name: file.name,
+            description: file.description,
+            setup: Setup {
+                cells,
+                hold: file.hold.map(piece).transpose()?,
+                queue: file.queue.chars().map(piece).collect::<Result<_, _>>()?,
+            },
+            goal: file.goal,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum PuzzleError {
+    Io(io::Error),
+    Ron(ron::error::SpannedError),
+    BoardTooBig,
+    InvalidCell(char),
+    InvalidPiece(char),
+}
+
+impl fmt::Display for PuzzleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PuzzleError::Io(e) => write!(f, "{e}"),
+            PuzzleError::Ron(e) => write!(f, "{e}"),
+            PuzzleError::BoardTooBig => write!(f, "the board doesn't fit in the matrix"),
+            PuzzleError::InvalidCell(c) => write!(f, "invalid cell '{c}'"),
+            PuzzleError::InvalidPiece(c) => write!(f, "invalid piece '{c}'"),
+        }
+    }
+}
+
+impl std::error::Error for PuzzleError {}
+
+struct PuzzleLoader;
+
+impl AssetLoader for PuzzleLoader {
+    type Asset = Puzzle;
+    type Settings = ();
+    type Error = PuzzleError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Puzzle, PuzzleError> {
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .await
+            .map_err(PuzzleError::Io)?;
+        let file: PuzzleFile = ron::de::from_bytes(&bytes).map_err(PuzzleError::Ron)?;
+        file.try_into()
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &[EXTENSION]
+    }
+}
+
+/// The puzzles that have been solved, by asset path.
+#[derive(Resource, Debug, Default)]
+pub struct PuzzleProgress {
+    solved: BTreeSet<String>,
+}
+
+impl PuzzleProgress {
+    fn path() -> PathBuf {
+        storage::data_dir().join("puzzles.bin")
+    }
+
+    /// Load the progress, starting from scratch if it can't be read.
+    pub fn load() -> Self {
+        let path = Self::path();
+        let progress = match fs::read(&path) {
+            Ok(bytes) => Self::from_bytes(&bytes),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Self::default(),
+            Err(e) => Err(e),
+        };
+        progress.unwrap_or_else(|e| {
+            warn!(
+                "Failed to load puzzle progress from {}: {e}",
+                path.display()
+            );
+            Self::default()
+        })
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = Self::path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_bytes())
+    }
+
+    pub fn is_solved(&self, puzzle: &str) -> bool {
+        self.solved.contains(puzzle)
+    }
+
+    pub fn mark_solved(&mut self, puzzle: String) {
+        self.solved.insert(puzzle);
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        write_varint(&mut bytes, self.solved.len() as u64);
+        for puzzle in &self.solved {
+            write_string(&mut bytes, puzzle);
+        }
+        bytes
+    }
+
+    fn from_bytes(mut bytes: &[u8]) -> io::Result<Self> {
+        if take(&mut bytes, MAGIC.len())? != MAGIC {
+            return Err(invalid_data("not a puzzle progress file"));
+        }
+        let version = take(&mut bytes, 1)?[0];
+        if version != VERSION {
+            return Err(invalid_data(format!(
+                "unsupported puzzle progress file version {version}"
+            )));
+        }
+        let mut progress = Self::default();
+        for _ in 0..read_varint(&mut bytes)? {
+            progress.solved.insert(read_string(&mut bytes)?);
+        }
+        Ok(progress)
+    }
+}
@@ -0,0 +1,253 @@
+//! Rebinding the controls: each action can be bound to any number of keys and gamepad buttons, as
+//! long as no input is bound to two actions.
+//!
+//! The controls are saved when leaving the screen.
+
+use std::time::Duration;
+
+use bevy::{color::palettes, prelude::*};
+
+use crate::{
+    game::input::{Action, Binding, Controls},
+    AppSet,
+};
+
+use super::{
+    menu_input::{gamepad_just_pressed, MenuInput},
+    Screen,
+};
+
+pub fn plugin(app: &mut App) {
+    app.init_resource::<ControlsEditor>()
+        .add_systems(OnEnter(Screen::Controls), enter_controls)
+        .add_systems(OnExit(Screen::Controls), save_controls)
+        .add_systems(
+            Update,
+            (
+                handle_input.in_set(AppSet::RecordInput),
+                update_list
+                    .run_if(
+                        resource_changed::<ControlsEditor>.or_else(resource_changed::<Controls>),
+                    )
+                    .in_set(AppSet::Update),
+            )
+                .run_if(in_state(Screen::Controls)),
+        );
+}
+
+/// How long to wait for an input to bind before giving up. Any key can be bound, Escape included,
+/// so this is the only way to cancel.
+const LISTEN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The highlighted action and binding, and whether the next input pressed gets bound to the action.
+#[derive(Resource, Debug, Default)]
+struct ControlsEditor {
+    selected: usize,
+    /// The binding of the action to remove, among those it has.
+    binding: usize,
+    /// Time left to press the input to bind, while waiting for one.
+    listening: Option<Timer>,
+    /// Why the last input couldn't be bound.
+    conflict: Option<String>,
+}
+
+#[derive(Component)]
+struct ControlsListText;
+
+#[derive(Component)]
+struct ConflictText;
+
+fn enter_controls(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    mut editor: ResMut<ControlsEditor>,
+) {
+    let title_style = TextStyle {
+        font: assets.load("fonts/BungeeSpice-Regular.ttf"),
+        font_size: 40.0,
+        color: Color::WHITE,
+    };
+    let text_style = TextStyle {
+        font: assets.load("fonts/JetBrainsMonoNLNerdFont-Regular.ttf"),
+        font_size: 18.0,
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn((
+            Name::new("Controls"),
+            StateScoped(Screen::Controls),
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(20.0),
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .with_children(|children| {
+            children.spawn(TextBundle::from_section("Controls", title_style));
+            children.spawn((TextBundle::from_sections([]), ControlsListText));
+            children.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        color: palettes::css::TOMATO.into(),
+                        ..text_style.clone()
+                    },
+                ),
+                ConflictText,
+            ));
+            children.spawn(TextBundle::from_section(
+                "[Up/Down] select  [Left/Right] pick binding  [Enter] add binding  [Backspace] remove \
+                 binding  [R] reset all  [Esc] back",
+                text_style,
+            ));
+        });
+
+    *editor = ControlsEditor::default();
+}
+
+fn save_controls(controls: Res<Controls>) {
+    match controls.save() {
+        Ok(()) => info!("Saved controls"),
+        Err(e) => error!("Failed to save controls: {e}"),
+    }
+}
+
+fn handle_input(
+    time: Res<Time>,
+    menu: Res<MenuInput>,
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    buttons: Res<ButtonInput<GamepadButton>>,
+    mut editor: ResMut<ControlsEditor>,
+    mut controls: ResMut<Controls>,
+    mut next_screen: ResMut<NextState<Screen>>,
+) {
+    let action = Action::ALL[editor.selected];
+
+    if let Some(timer) = &mut editor.listening {
+        if timer.tick(time.delta()).finished() {
+            editor.listening = None;
+            return;
+        }
+        let pressed = keys
+            .get_just_pressed()
+            .next()
+            .copied()
+            .map(Binding::Key)
+            .or_else(|| {
+                buttons
+                    .get_just_pressed()
+                    .next()
+                    .map(|button| Binding::Button(button.button_type))
+            });
+        let Some(binding) = pressed else {
+            return;
+        };
+        editor.listening = None;
+        match controls.bind(action, binding) {
+            Ok(()) => info!("Bound {binding} to {}", action.label()),
+            Err(other) => {
+                editor.conflict = Some(format!("{binding} is already bound to {}", other.label()));
+            }
+        }
+        return;
+    }
+
+    let len = Action::ALL.len();
+    let bindings: Vec<Binding> = controls.bindings(action).iter().collect();
+    if menu.up {
+        editor.selected = (editor.selected + len - 1) % len;
+        editor.binding = 0;
+        editor.conflict = None;
+    } else if menu.down {
+        editor.selected = (editor.selected + 1) % len;
+        editor.binding = 0;
+        editor.conflict = None;
+    } else if menu.left && !bindings.is_empty() {
+        editor.binding = (editor.binding + bindings.len() - 1) % bindings.len();
+    } else if menu.right && !bindings.is_empty() {
+        editor.binding = (editor.binding + 1) % bindings.len();
+    } else if menu.confirm {
+        editor.listening = Some(Timer::new(LISTEN_TIMEOUT, TimerMode::Once));
+        editor.conflict = None;
+    } else if keys.just_pressed(KeyCode::Backspace)
+        || gamepad_just_pressed(&gamepads, &buttons, GamepadButtonType::West)
+    {
+        if let Some(binding) = bindings.get(editor.binding) {
+            controls.unbind(action, *binding);
+            info!("Unbound {binding} from {}", action.label());
+            editor.binding = editor.binding.min(bindings.len().saturating_sub(2));
+        }
+    } else if keys.just_pressed(KeyCode::KeyR) {
+        *controls = Controls::default();
+        editor.binding = 0;
+        editor.conflict = None;
+    } else if menu.back {
+        next_screen.set(Screen::MainMenu);
+    }
+}
+
+fn update_list(
+    editor: Res<ControlsEditor>,
+    controls: Res<Controls>,
+    assets: Res<AssetServer>,
+    mut list: Query<&mut Text, (With<ControlsListText>, Without<ConflictText>)>,
+    mut conflict: Query<&mut Text, (With<ConflictText>, Without<ControlsListText>)>,
+) {
+    let (Ok(mut list), Ok(mut conflict)) = (list.get_single_mut(), conflict.get_single_mut())
+    else {
+        return;
+    };
+    let style = TextStyle {
+        font: assets.load("fonts/JetBrainsMonoNLNerdFont-Regular.ttf"),
+        font_size: 20.0,
+        color: Color::WHITE,
+    };
+
+    list.sections = Action::ALL
+        .iter()
+        .enumerate()
+        .map(|(index, action)| {
+            let selected = index == editor.selected;
+            let bindings = match &editor.listening {
+                Some(timer) if selected => format!(
+                    "Press a key or button... ({:.0}s)",
+                    timer.remaining_secs().ceil()
+                ),
+                _ => {
+                    let bindings: Vec<String> = controls
+                        .bindings(*action)
+                        .iter()
+                        .enumerate()
+                        .map(|(index, binding)| {
+                            if selected && index == editor.binding {
+                                format!("[{binding}]")
+                            } else {
+                                binding.to_string()
+                            }
+                        })
+                        .collect();
+                    if bindings.is_empty() {
+                        "(unbound)".to_string()
+                    } else {
+                        bindings.join(", ")
+                    }
+                }
+            };
+            let mut style = style.clone();
+            if selected {
+                style.color = palettes::css::YELLOW.into();
+            }
+            TextSection::new(format!("{:<14} {bindings:<40}\n", action.label()), style)
+        })
+        .collect();
+    conflict.sections[0].value = editor.conflict.clone().unwrap_or_default();
+}
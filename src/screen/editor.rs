@@ -0,0 +1,411 @@
+//! Building setups by hand: painting the board, choosing the pieces and the goal, then saving the
+//! result as a puzzle or playing it right away.
+
+use bevy::{
+    input::{
+        common_conditions::input_just_pressed,
+        keyboard::{Key, KeyboardInput},
+        ButtonState,
+    },
+    prelude::*,
+    sprite::Anchor,
+    window::PrimaryWindow,
+};
+
+use crate::{
+    game::{
+        fumen::Setup, mode::GameMode, puzzle::Goal, stats::GameStats, GameConfig, MATRIX_WIDTH,
+        VISIBLE_HEIGHT,
+    },
+    layout::{HudAnchor, LayoutSlot},
+    model::{Cell, Pos, TetriminoKind},
+    puzzles::{self, Puzzle, PuzzleFolders},
+    skin::CurrentSkin,
+    storage, AppSet,
+};
+
+use super::{ReturnScreen, Screen};
+
+/// The goals that can be picked for a setup.
+const GOALS: [Goal; 6] = [
+    Goal::ClearLines(1),
+    Goal::ClearLines(2),
+    Goal::ClearLines(4),
+    Goal::TSpinDouble,
+    Goal::PerfectClear,
+    Goal::Survive(20),
+];
+
+const BRUSH_KEYS: [KeyCode; 7] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+];
+
+const EMPTY_COLOR: Color = Color::srgb(0.1, 0.1, 0.1);
+
+pub fn plugin(app: &mut App) {
+    app.init_resource::<Editor>()
+        .add_systems(OnEnter(Screen::Editor), enter_editor)
+        .add_systems(
+            Update,
+            (
+                (
+                    paint,
+                    choose_brush,
+                    edit_queue,
+                    cycle_hold.run_if(input_just_pressed(KeyCode::KeyH)),
+                    cycle_goal.run_if(input_just_pressed(KeyCode::Tab)),
+                    clear_board.run_if(input_just_pressed(KeyCode::Delete)),
+                    save.run_if(ctrl_pressed.and_then(input_just_pressed(KeyCode::KeyS))),
+                    play.run_if(input_just_pressed(KeyCode::Enter)),
+                    back.run_if(input_just_pressed(KeyCode::Escape)),
+                )
+                    .in_set(AppSet::RecordInput),
+                update_cells
+                    .run_if(resource_changed::<Editor>.or_else(resource_changed::<CurrentSkin>))
+                    .in_set(AppSet::Update),
+                update_panel
+                    .run_if(resource_changed::<Editor>)
+                    .in_set(AppSet::Update),
+            )
+                .run_if(in_state(Screen::Editor)),
+        );
+}
+
+/// The setup being edited. It is kept when leaving the editor, to come back to it after playing.
+#[derive(Resource, Debug)]
+struct Editor {
+    /// The visible part of the matrix, indexed by [`Pos::to_index`].
+    cells: Vec<Option<Cell>>,
+    hold: Option<TetriminoKind>,
+    queue: Vec<TetriminoKind>,
+    /// Index of the goal in [`GOALS`].
+    goal: usize,
+    /// What gets painted with the left mouse button.
+    brush: Cell,
+    /// Feedback about the last save or attempt to play.
+    status: String,
+}
+
+impl Default for Editor {
+    fn default() -> Self {
+        Self {
+            cells: vec![None; MATRIX_WIDTH as usize * VISIBLE_HEIGHT as usize],
+            hold: None,
+            queue: Vec::new(),
+            goal: 0,
+            brush: Cell::Garbage,
+            status: String::new(),
+        }
+    }
+}
+
+impl Editor {
+    fn setup(&self) -> Setup {
+        Setup {
+            cells: self
+                .cells
+                .iter()
+                .enumerate()
+                .filter_map(|(index, cell)| Some((Pos::from_index(index), (*cell)?)))
+                .collect(),
+            hold: self.hold,
+            queue: self.queue.clone(),
+        }
+    }
+
+    fn goal(&self) -> Goal {
+        GOALS[self.goal]
+    }
+}
+
+/// The parent of the cell sprites, placed like the matrix of a game.
+#[derive(Component)]
+struct EditorBoard;
+
+#[derive(Component)]
+struct EditorCell(Pos);
+
+#[derive(Component)]
+struct EditorPanel;
+
+fn enter_editor(mut commands: Commands, assets: Res<AssetServer>, mut editor: ResMut<Editor>) {
+    commands
+        .spawn((
+            Name::new("Editor board"),
+            StateScoped(Screen::Editor),
+            EditorBoard,
+            LayoutSlot::Matrix,
+            SpatialBundle::default(),
+        ))
+        .with_children(|children| {
+            for index in 0..editor.cells.len() {
+                let pos = Pos::from_index(index);
+                children.spawn((
+                    EditorCell(pos),
+                    SpriteBundle {
+                        sprite: Sprite {
+                            // Leave a gap between cells so that the grid shows
+                            custom_size: Some(Vec2::splat(0.95)),
+                            anchor: Anchor::BottomLeft,
+                            color: EMPTY_COLOR,
+                            ..default()
+                        },
+                        transform: pos.into(),
+                        ..default()
+                    },
+                ));
+            }
+        });
+
+    let title_style = TextStyle {
+        font: assets.load("fonts/BungeeSpice-Regular.ttf"),
+        font_size: 40.0,
+        color: Color::WHITE,
+    };
+    let text_style = TextStyle {
+        font: assets.load("fonts/JetBrainsMonoNLNerdFont-Regular.ttf"),
+        font_size: 20.0,
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn((
+            Name::new("Editor panel"),
+            StateScoped(Screen::Editor),
+            HudAnchor::Right(VISIBLE_HEIGHT as f32),
+            NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(20.0),
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .with_children(|children| {
+            children.spawn(TextBundle::from_section("Editor", title_style));
+            children.spawn((
+                TextBundle::from_section("", text_style.clone()),
+                EditorPanel,
+            ));
+            children.spawn(TextBundle::from_section(
+                "[Left click] paint  [Right click] erase\n\
+                 [1-7] piece brush  [0] garbage brush\n\
+                 [O I T L J S Z] add to queue  [Backspace] remove\n\
+                 [H] hold  [Tab] goal  [Delete] clear board\n\
+                 [Ctrl+S] save  [Enter] play  [Esc] back",
+                TextStyle {
+                    font_size: 18.0,
+                    ..text_style
+                },
+            ));
+        });
+
+    // Make sure the board and the panel get filled in
+    editor.set_changed();
+}
+
+fn ctrl_pressed(input: Res<ButtonInput<KeyCode>>) -> bool {
+    input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight])
+}
+
+/// Paint the cell under the cursor with the brush, or erase it.
+fn paint(
+    mouse: Res<ButtonInput<MouseButton>>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    camera: Query<(&Camera, &GlobalTransform)>,
+    board: Query<&GlobalTransform, With<EditorBoard>>,
+    mut editor: ResMut<Editor>,
+) {
+    let cell = if mouse.pressed(MouseButton::Left) {
+        Some(editor.brush)
+    } else if mouse.pressed(MouseButton::Right) {
+        None
+    } else {
+        return;
+    };
+    let (Ok(window), Ok((camera, camera_transform)), Ok(board)) =
+        (window.get_single(), camera.get_single(), board.get_single())
+    else {
+        return;
+    };
+    let Some(world) = window
+        .cursor_position()
+        .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor))
+    else {
+        return;
+    };
+
+    let local = board
+        .affine()
+        .inverse()
+        .transform_point3(world.extend(0.0))
+        .floor();
+    if local.x < 0.0
+        || local.x >= MATRIX_WIDTH as f32
+        || local.y < 0.0
+        || local.y >= VISIBLE_HEIGHT as f32
+    {
+        return;
+    }
+    let index = Pos::new(local.x as i8, local.y as i8).to_index();
+    // Only touch the resource when something changes, so the board isn't redrawn every frame
+    if editor.cells[index] != cell {
+        editor.cells[index] = cell;
+    }
+}
+
+fn choose_brush(input: Res<ButtonInput<KeyCode>>, mut editor: ResMut<Editor>) {
+    if input.just_pressed(KeyCode::Digit0) {
+        editor.brush = Cell::Garbage;
+    }
+    for (key, kind) in BRUSH_KEYS.iter().zip(TetriminoKind::all()) {
+        if input.just_pressed(*key) {
+            editor.brush = Cell::Mino(*kind);
+        }
+    }
+}
+
+/// Type piece letters to add them to the queue.
+fn edit_queue(
+    mut events: EventReader<KeyboardInput>,
+    input: Res<ButtonInput<KeyCode>>,
+    mut editor: ResMut<Editor>,
+) {
+    // Leave shortcuts such as Ctrl+S alone
+    let ctrl = input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    for event in events.read() {
+        if event.state != ButtonState::Pressed || ctrl {
+            continue;
+        }
+        match &event.logical_key {
+            Key::Character(chars) => {
+                let kinds = chars
+                    .chars()
+                    .filter_map(|c| TetriminoKind::from_letter(c.to_ascii_uppercase()));
+                editor.queue.extend(kinds);
+            }
+            Key::Backspace => {
+                editor.queue.pop();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Go through no hold piece, then every kind of piece.
+fn cycle_hold(mut editor: ResMut<Editor>) {
+    let all = TetriminoKind::all();
+    editor.hold = match editor.hold {
+        None => all.first().copied(),
+        Some(kind) => all
+            .iter()
+            .position(|other| *other == kind)
+            .and_then(|index| all.get(index + 1))
+            .copied(),
+    };
+}
+
+fn cycle_goal(mut editor: ResMut<Editor>) {
+    editor.goal = (editor.goal + 1) % GOALS.len();
+}
+
+fn clear_board(mut editor: ResMut<Editor>) {
+    editor.cells.fill(None);
+}
+
+/// Save the setup as a puzzle in the data directory, and add it to the puzzle list.
+fn save(mut editor: ResMut<Editor>, assets: Res<AssetServer>, mut folders: ResMut<PuzzleFolders>) {
+    let puzzle = Puzzle {
+        name: "Custom".to_string(),
+        description: String::new(),
+        setup: editor.setup(),
+        goal: editor.goal(),
+    };
+    let path = storage::timestamped_path(&puzzles::saved_puzzle_dir(), puzzles::EXTENSION);
+    editor.status = match puzzle.save(&path) {
+        Ok(()) => {
+            info!("Saved setup to {}", path.display());
+            folders.add_saved(&assets, &path);
+            format!("Saved to {}", path.display())
+        }
+        Err(e) => {
+            warn!("Failed to save setup to {}: {e}", path.display());
+            format!("Failed to save: {e}")
+        }
+    };
+}
+
+fn play(
+    mut commands: Commands,
+    mut editor: ResMut<Editor>,
+    mut config: ResMut<GameConfig>,
+    mut next_screen: ResMut<NextState<Screen>>,
+) {
+    let setup = editor.setup();
+    if editor
+        .goal()
+        .is_out_of_pieces(Some(&setup), &GameStats::default())
+    {
+        editor.status = "Add pieces to the queue first".to_string();
+        return;
+    }
+    editor.status.clear();
+    config.mode = GameMode::Puzzle;
+    config.setup = Some(setup);
+    config.goal = Some(editor.goal());
+    commands.insert_resource(ReturnScreen(Screen::Editor));
+    next_screen.set(Screen::Gameplay);
+}
+
+fn back(
+    mut commands: Commands,
+    mut config: ResMut<GameConfig>,
+    mut next_screen: ResMut<NextState<Screen>>,
+) {
+    // Go back to playing normal games
+    config.mode = GameMode::default();
+    config.setup = None;
+    config.goal = None;
+    commands.remove_resource::<ReturnScreen>();
+    next_screen.set(Screen::MainMenu);
+}
+
+fn update_cells(
+    editor: Res<Editor>,
+    skin: Res<CurrentSkin>,
+    mut cells: Query<(&EditorCell, &mut Sprite)>,
+) {
+    for (EditorCell(pos), mut sprite) in &mut cells {
+        sprite.color = match editor.cells[pos.to_index()] {
+            None => EMPTY_COLOR,
+            Some(cell) => skin.cell_color(cell),
+        };
+    }
+}
+
+fn update_panel(editor: Res<Editor>, mut panel: Query<&mut Text, With<EditorPanel>>) {
+    let Ok(mut text) = panel.get_single_mut() else {
+        return;
+    };
+    let brush = match editor.brush {
+        Cell::Garbage => "Garbage".to_string(),
+        Cell::Mino(kind) => kind.letter().to_string(),
+    };
+    let hold = editor
+        .hold
+        .map_or("-".to_string(), |kind| kind.letter().to_string());
+    let queue: String = editor.queue.iter().map(TetriminoKind::letter).collect();
+    text.sections[0].value = format!(
+        "Brush: {brush}\nHold:  {hold}\nQueue: {}\nGoal:  {}\n\n{}",
+        if queue.is_empty() { "-" } else { &queue },
+        editor.goal(),
+        editor.status
+    );
+}
@@ -1,12 +1,39 @@
 use bevy::prelude::*;
 
-use super::Screen;
+use crate::{game::mode::GameOver, AppSet};
+
+use super::{results::FinishedGame, Screen};
 
 pub fn plugin(app: &mut App) {
     app.add_systems(OnEnter(Screen::Gameplay), enter_playing);
     app.add_systems(OnExit(Screen::Gameplay), exit_playing);
+    app.add_systems(OnEnter(Screen::Restart), restart);
+    app.add_systems(
+        Update,
+        game_over
+            .run_if(in_state(Screen::Gameplay).and_then(on_event::<GameOver>()))
+            .in_set(AppSet::Update),
+    );
 }
 
 fn enter_playing(mut _cmd: Commands) {}
 
 fn exit_playing(mut _cmd: Commands) {}
+
+/// Start a new game with the same config, now that the previous one has been cleaned up.
+fn restart(mut next_screen: ResMut<NextState<Screen>>) {
+    next_screen.set(Screen::Gameplay);
+}
+
+/// Show the results of the game once it's over.
+fn game_over(
+    mut commands: Commands,
+    mut events: EventReader<GameOver>,
+    mut next_screen: ResMut<NextState<Screen>>,
+) {
+    let Some(GameOver(result)) = events.read().last() else {
+        return;
+    };
+    commands.insert_resource(FinishedGame(result.clone()));
+    next_screen.set(Screen::Results);
+}
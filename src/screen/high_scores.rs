@@ -0,0 +1,174 @@
+use bevy::{color::palettes, prelude::*};
+
+use crate::{
+    game::{mode::GameMode, GameConfig},
+    highscores::{HighScores, TABLE_LEN},
+    AppSet,
+};
+
+use super::{
+    format_date, format_ticks,
+    menu_input::{menu_back, menu_confirmed, MenuInput},
+    Screen,
+};
+
+pub fn plugin(app: &mut App) {
+    app.init_resource::<HighScoresView>()
+        .add_systems(OnEnter(Screen::HighScores), enter_high_scores)
+        .add_systems(
+            Update,
+            (
+                (
+                    switch_mode,
+                    play.run_if(menu_confirmed),
+                    back.run_if(menu_back),
+                )
+                    .in_set(AppSet::RecordInput),
+                update_table
+                    .run_if(resource_changed::<HighScoresView>)
+                    .in_set(AppSet::Update),
+            )
+                .run_if(in_state(Screen::HighScores)),
+        );
+}
+
+/// Which table is shown on the high-score screen.
+#[derive(Resource, Debug, Default)]
+pub(super) struct HighScoresView {
+    pub mode: GameMode,
+    /// The rank of the result that was just entered, if any.
+    pub highlight: Option<usize>,
+}
+
+impl HighScoresView {
+    pub fn new(mode: GameMode) -> Self {
+        Self {
+            mode,
+            highlight: None,
+        }
+    }
+}
+
+#[derive(Component)]
+struct HighScoresTitle;
+
+#[derive(Component)]
+struct HighScoresTable;
+
+fn enter_high_scores(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    mut view: ResMut<HighScoresView>,
+) {
+    let title_style = TextStyle {
+        font: assets.load("fonts/BungeeSpice-Regular.ttf"),
+        font_size: 40.0,
+        color: Color::WHITE,
+    };
+    let text_style = TextStyle {
+        font: assets.load("fonts/JetBrainsMonoNLNerdFont-Regular.ttf"),
+        font_size: 20.0,
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn((
+            Name::new("High scores"),
+            StateScoped(Screen::HighScores),
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(20.0),
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .with_children(|children| {
+            children.spawn((TextBundle::from_section("", title_style), HighScoresTitle));
+            children.spawn((
+                TextBundle::from_sections(
+                    (0..=TABLE_LEN).map(|_| TextSection::new("", text_style.clone())),
+                ),
+                HighScoresTable,
+            ));
+            children.spawn(TextBundle::from_section(
+                "[</>] mode  [Enter] play  [Esc] back",
+                TextStyle {
+                    font_size: 18.0,
+                    ..text_style
+                },
+            ));
+        });
+
+    // Make sure the table gets filled in
+    view.set_changed();
+}
+
+fn switch_mode(menu: Res<MenuInput>, mut view: ResMut<HighScoresView>) {
+    let index = GameMode::ALL
+        .iter()
+        .position(|mode| *mode == view.mode)
+        .unwrap_or_default();
+    let len = GameMode::ALL.len();
+    if menu.left {
+        *view = HighScoresView::new(GameMode::ALL[(index + len - 1) % len]);
+    } else if menu.right {
+        *view = HighScoresView::new(GameMode::ALL[(index + 1) % len]);
+    }
+}
+
+fn play(
+    view: Res<HighScoresView>,
+    mut config: ResMut<GameConfig>,
+    mut next_screen: ResMut<NextState<Screen>>,
+) {
+    config.mode = view.mode;
+    next_screen.set(Screen::Gameplay);
+}
+
+fn back(mut next_screen: ResMut<NextState<Screen>>) {
+    next_screen.set(Screen::MainMenu);
+}
+
+fn update_table(
+    view: Res<HighScoresView>,
+    high_scores: Res<HighScores>,
+    mut title: Query<&mut Text, (With<HighScoresTitle>, Without<HighScoresTable>)>,
+    mut table: Query<&mut Text, (With<HighScoresTable>, Without<HighScoresTitle>)>,
+) {
+    let (Ok(mut title), Ok(mut table)) = (title.get_single_mut(), table.get_single_mut()) else {
+        return;
+    };
+    title.sections[0].value = format!("< {} >", view.mode);
+
+    let entries = high_scores.table(view.mode);
+    table.sections[0].value = format!(
+        "    {:<12} {:>8} {:>5} {:>3} {:>9}  {:<10}\n",
+        "NAME", "SCORE", "LINES", "LVL", "TIME", "DATE"
+    );
+    for (rank, section) in table.sections[1..].iter_mut().enumerate() {
+        section.value = match entries.get(rank) {
+            Some(entry) => format!(
+                "{:>2}. {:<12} {:>8} {:>5} {:>3} {:>9}  {:<10}\n",
+                rank + 1,
+                entry.name,
+                entry.score,
+                entry.lines,
+                entry.level,
+                format_ticks(entry.ticks),
+                format_date(entry.date),
+            ),
+            None => format!("{:>2}. {:-<12}\n", rank + 1, ""),
+        };
+        section.style.color = if view.highlight == Some(rank) {
+            palettes::css::YELLOW.into()
+        } else {
+            Color::WHITE
+        };
+    }
+}
@@ -0,0 +1,242 @@
+//! The main menu: picking a mode to play, or going to one of the other screens.
+//!
+//! The menu can be navigated with the arrow keys, the D-pad of any gamepad, or the mouse.
+
+use bevy::{app::AppExit, color::palettes, prelude::*};
+
+use crate::{
+    game::{mode::GameMode, GameConfig},
+    AppSet,
+};
+
+use super::{
+    menu_input::{gamepad_just_pressed, MenuInput},
+    Screen,
+};
+
+pub fn plugin(app: &mut App) {
+    app.init_resource::<MenuSelection>()
+        .add_systems(OnEnter(Screen::MainMenu), enter_main_menu)
+        .add_systems(
+            Update,
+            (
+                (hover, navigate, choose)
+                    .chain()
+                    .in_set(AppSet::RecordInput),
+                update_items
+                    .run_if(resource_changed::<MenuSelection>)
+                    .in_set(AppSet::Update),
+            )
+                .run_if(in_state(Screen::MainMenu)),
+        );
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+enum MenuItem {
+    Play(GameMode),
+    Puzzles,
+    Editor,
+    HighScores,
+    Replays,
+    Settings,
+    Controls,
+    Quit,
+}
+
+impl MenuItem {
+    const ALL: [MenuItem; 10] = [
+        MenuItem::Play(GameMode::Marathon),
+        MenuItem::Play(GameMode::Sprint),
+        MenuItem::Play(GameMode::Ultra),
+        MenuItem::Puzzles,
+        MenuItem::Editor,
+        MenuItem::HighScores,
+        MenuItem::Replays,
+        MenuItem::Settings,
+        MenuItem::Controls,
+        MenuItem::Quit,
+    ];
+
+    fn label(&self) -> String {
+        match self {
+            MenuItem::Play(mode) => mode.to_string(),
+            MenuItem::Puzzles => "Puzzles".to_string(),
+            MenuItem::Editor => "Editor".to_string(),
+            MenuItem::HighScores => "High scores".to_string(),
+            MenuItem::Replays => "Replays".to_string(),
+            MenuItem::Settings => "Settings".to_string(),
+            MenuItem::Controls => "Controls".to_string(),
+            MenuItem::Quit => "Quit".to_string(),
+        }
+    }
+}
+
+/// The index of the highlighted item, kept when coming back to the menu.
+#[derive(Resource, Debug, Default)]
+struct MenuSelection(usize);
+
+impl MenuSelection {
+    /// Move the selection by one item up (-1) or down (1).
+    fn step(&mut self, direction: isize) {
+        let len = MenuItem::ALL.len() as isize;
+        self.0 = (self.0 as isize + direction).rem_euclid(len) as usize;
+    }
+}
+
+fn enter_main_menu(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    mut selection: ResMut<MenuSelection>,
+) {
+    let font = assets.load("fonts/BungeeSpice-Regular.ttf");
+    let item_style = TextStyle {
+        font: font.clone(),
+        font_size: 32.0,
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn((
+            Name::new("Main menu"),
+            StateScoped(Screen::MainMenu),
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(8.0),
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .with_children(|children| {
+            children.spawn(
+                TextBundle::from_section(
+                    "Betris",
+                    TextStyle {
+                        font,
+                        font_size: 78.0,
+                        color: Color::WHITE,
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::bottom(Val::Px(20.0)),
+                    ..default()
+                }),
+            );
+            for item in MenuItem::ALL {
+                children
+                    .spawn((
+                        Name::new(item.label()),
+                        ButtonBundle {
+                            background_color: Color::NONE.into(),
+                            ..default()
+                        },
+                        item,
+                    ))
+                    .with_children(|button| {
+                        button.spawn(TextBundle::from_section(item.label(), item_style.clone()));
+                    });
+            }
+            children.spawn(
+                TextBundle::from_section(
+                    "[Up/Down] select  [Enter] choose",
+                    TextStyle {
+                        font: assets.load("fonts/JetBrainsMonoNLNerdFont-Regular.ttf"),
+                        font_size: 18.0,
+                        color: Color::WHITE,
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::top(Val::Px(20.0)),
+                    ..default()
+                }),
+            );
+        });
+
+    // Make sure the selected item gets highlighted
+    selection.set_changed();
+}
+
+/// Select the item under the mouse cursor.
+fn hover(
+    items: Query<(&Interaction, &MenuItem), Changed<Interaction>>,
+    mut selection: ResMut<MenuSelection>,
+) {
+    for (interaction, item) in &items {
+        if *interaction == Interaction::None {
+            continue;
+        }
+        let index = MenuItem::ALL.iter().position(|i| i == item);
+        if let Some(index) = index.filter(|index| *index != selection.0) {
+            selection.0 = index;
+        }
+    }
+}
+
+fn navigate(menu: Res<MenuInput>, mut selection: ResMut<MenuSelection>) {
+    if menu.vertical() != 0 {
+        selection.step(menu.vertical());
+    }
+}
+
+/// Go where the selected item leads, when it is clicked or confirmed.
+fn choose(
+    menu: Res<MenuInput>,
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    buttons: Res<ButtonInput<GamepadButton>>,
+    items: Query<(&Interaction, &MenuItem), Changed<Interaction>>,
+    selection: Res<MenuSelection>,
+    mut config: ResMut<GameConfig>,
+    mut next_screen: ResMut<NextState<Screen>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let confirmed = menu.confirm
+        || keys.just_pressed(KeyCode::Space)
+        || gamepad_just_pressed(&gamepads, &buttons, GamepadButtonType::Start)
+        || items
+            .iter()
+            .any(|(interaction, _)| *interaction == Interaction::Pressed);
+    if !confirmed {
+        return;
+    }
+
+    match MenuItem::ALL[selection.0] {
+        MenuItem::Play(mode) => {
+            config.mode = mode;
+            next_screen.set(Screen::Gameplay);
+        }
+        MenuItem::Puzzles => next_screen.set(Screen::Puzzles),
+        MenuItem::Editor => next_screen.set(Screen::Editor),
+        MenuItem::HighScores => next_screen.set(Screen::HighScores),
+        MenuItem::Replays => next_screen.set(Screen::Replays),
+        MenuItem::Settings => next_screen.set(Screen::Settings),
+        MenuItem::Controls => next_screen.set(Screen::Controls),
+        MenuItem::Quit => {
+            exit.send(AppExit::Success);
+        }
+    }
+}
+
+fn update_items(
+    selection: Res<MenuSelection>,
+    items: Query<(&MenuItem, &Children)>,
+    mut texts: Query<&mut Text>,
+) {
+    let selected = MenuItem::ALL[selection.0];
+    for (item, children) in &items {
+        let color = if *item == selected {
+            palettes::css::YELLOW.into()
+        } else {
+            Color::WHITE
+        };
+        let mut texts = texts.iter_many_mut(children);
+        while let Some(mut text) = texts.fetch_next() {
+            text.sections[0].style.color = color;
+        }
+    }
+}
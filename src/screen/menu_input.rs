@@ -0,0 +1,81 @@
+//! Menu navigation, shared by all the screens so that they can all be used with either the keyboard
+//! or a gamepad: the arrow keys, D-pad or left stick move around, Enter or the South button
+//! confirms, and Escape or the East button goes back.
+
+use bevy::{input::InputSystem, prelude::*};
+
+use crate::game::input::stick_direction;
+
+pub fn plugin(app: &mut App) {
+    app.init_resource::<MenuInput>()
+        .add_systems(PreUpdate, read_menu_input.after(InputSystem));
+}
+
+/// The menu inputs pressed this frame.
+#[derive(Resource, Debug, Default)]
+pub struct MenuInput {
+    pub up: bool,
+    pub down: bool,
+    pub left: bool,
+    pub right: bool,
+    pub confirm: bool,
+    pub back: bool,
+    /// Where the left stick was pushed last frame, so that holding it only counts once.
+    stick: IVec2,
+}
+
+impl MenuInput {
+    /// The vertical direction pressed this frame: up (-1), down (1) or neither (0).
+    pub fn vertical(&self) -> isize {
+        self.down as isize - self.up as isize
+    }
+
+    /// The horizontal direction pressed this frame: left (-1), right (1) or neither (0).
+    pub fn horizontal(&self) -> isize {
+        self.right as isize - self.left as isize
+    }
+}
+
+fn read_menu_input(
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    buttons: Res<ButtonInput<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    mut menu: ResMut<MenuInput>,
+) {
+    let stick = stick_direction(&gamepads, &axes);
+    let pushed = |direction| stick == direction && menu.stick != direction;
+    let pressed =
+        |key, button| keys.just_pressed(key) || gamepad_just_pressed(&gamepads, &buttons, button);
+
+    *menu = MenuInput {
+        up: pressed(KeyCode::ArrowUp, GamepadButtonType::DPadUp) || pushed(IVec2::Y),
+        down: pressed(KeyCode::ArrowDown, GamepadButtonType::DPadDown) || pushed(IVec2::NEG_Y),
+        left: pressed(KeyCode::ArrowLeft, GamepadButtonType::DPadLeft) || pushed(IVec2::NEG_X),
+        right: pressed(KeyCode::ArrowRight, GamepadButtonType::DPadRight) || pushed(IVec2::X),
+        confirm: pressed(KeyCode::Enter, GamepadButtonType::South),
+        back: pressed(KeyCode::Escape, GamepadButtonType::East),
+        stick,
+    };
+}
+
+/// Whether confirm was pressed this frame.
+pub fn menu_confirmed(menu: Res<MenuInput>) -> bool {
+    menu.confirm
+}
+
+/// Whether back was pressed this frame.
+pub fn menu_back(menu: Res<MenuInput>) -> bool {
+    menu.back
+}
+
+/// Whether the given button was just pressed on any gamepad.
+pub fn gamepad_just_pressed(
+    gamepads: &Gamepads,
+    buttons: &ButtonInput<GamepadButton>,
+    button: GamepadButtonType,
+) -> bool {
+    gamepads
+        .iter()
+        .any(|gamepad| buttons.just_pressed(GamepadButton::new(gamepad, button)))
+}
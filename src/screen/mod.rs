@@ -1,21 +1,150 @@
 use bevy::prelude::*;
 
+use crate::game::TICK_RATE;
+
+mod controls;
+mod editor;
 mod gameplay;
+mod high_scores;
+mod main_menu;
+mod menu_input;
+mod name_entry;
+mod pause;
+mod puzzles;
+mod replay;
+mod replays;
+mod results;
+mod settings;
 mod splash;
 
 pub fn plugin(app: &mut App) {
     app.init_state::<Screen>()
+        .add_computed_state::<InGame>()
+        .add_sub_state::<Pause>()
+        .add_computed_state::<GamePaused>()
+        .add_computed_state::<InSettings>()
         .enable_state_scoped_entities::<Screen>()
-        .add_plugins((splash::plugin, gameplay::plugin));
+        .enable_state_scoped_entities::<InGame>()
+        .enable_state_scoped_entities::<Pause>()
+        .enable_state_scoped_entities::<InSettings>()
+        .add_plugins((
+            splash::plugin,
+            main_menu::plugin,
+            gameplay::plugin,
+            pause::plugin,
+            replay::plugin,
+            replays::plugin,
+            results::plugin,
+            settings::plugin,
+            name_entry::plugin,
+            high_scores::plugin,
+            puzzles::plugin,
+            editor::plugin,
+            controls::plugin,
+            menu_input::plugin,
+        ));
 
     // Skip the splash screen in dev mode and go straight to the playing screen
     #[cfg(feature = "dev")]
     app.insert_state(Screen::Gameplay);
+
+    // `--replay <file>` goes straight to watching the given replay
+    replay::watch_from_args(app);
 }
 
 #[derive(States, Debug, Clone, PartialEq, Eq, Hash, Default)]
 pub enum Screen {
     #[default]
     Splash,
+    MainMenu,
     Gameplay,
+    /// Left right away for `Gameplay`, to start the game again.
+    Restart,
+    Replay,
+    Replays,
+    Results,
+    NameEntry,
+    HighScores,
+    Puzzles,
+    Editor,
+    Settings,
+    Controls,
+}
+
+/// The screen to go back to once a game without high scores (e.g. a puzzle) is over.
+#[derive(Resource, Debug, Clone, Deref)]
+struct ReturnScreen(Screen);
+
+/// Whether a game is being simulated, either because it is being played or replayed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InGame;
+
+impl ComputedStates for InGame {
+    type SourceStates = Screen;
+
+    fn compute(screen: Screen) -> Option<Self> {
+        matches!(screen, Screen::Gameplay | Screen::Replay).then_some(InGame)
+    }
+}
+
+/// Whether the game being played is paused, with the pause menu shown over it.
+#[derive(SubStates, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[source(Screen = Screen::Gameplay)]
+pub enum Pause {
+    #[default]
+    Running,
+    Paused,
+    /// Paused, with the settings shown instead of the pause menu.
+    Settings,
+}
+
+/// Whether the game being played is paused, whichever menu is shown over it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GamePaused;
+
+impl ComputedStates for GamePaused {
+    type SourceStates = Pause;
+
+    fn compute(pause: Pause) -> Option<Self> {
+        (pause != Pause::Running).then_some(GamePaused)
+    }
+}
+
+/// Whether the settings are being edited, either from the main menu or from the pause menu.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InSettings;
+
+impl ComputedStates for InSettings {
+    type SourceStates = (Screen, Option<Pause>);
+
+    fn compute((screen, pause): (Screen, Option<Pause>)) -> Option<Self> {
+        (screen == Screen::Settings || pause == Some(Pause::Settings)).then_some(InSettings)
+    }
+}
+
+/// Format a number of simulation ticks as `m:ss.cc`.
+pub fn format_ticks(ticks: u64) -> String {
+    let centis = ticks * 100 / TICK_RATE as u64;
+    format!(
+        "{}:{:02}.{:02}",
+        centis / 6000,
+        (centis / 100) % 60,
+        centis % 100
+    )
+}
+
+/// Format a Unix timestamp as `YYYY-MM-DD` (UTC).
+fn format_date(timestamp: u64) -> String {
+    // Days to civil date conversion, from http://howardhinnant.github.io/date_algorithms.html
+    let days = (timestamp / 86400) as i64 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    format!("{year:04}-{month:02}-{day:02}")
 }
@@ -0,0 +1,173 @@
+use bevy::{
+    input::{
+        keyboard::{Key, KeyboardInput},
+        ButtonState,
+    },
+    prelude::*,
+};
+
+use crate::{
+    game::mode::GameResult,
+    highscores::{HighScore, HighScores},
+    AppSet,
+};
+
+use super::{format_ticks, high_scores::HighScoresView, menu_input::gamepad_just_pressed, Screen};
+
+const MAX_NAME_LEN: usize = 12;
+/// The name used when confirming from a gamepad, with no name typed in.
+const DEFAULT_NAME: &str = "Player";
+
+pub fn plugin(app: &mut App) {
+    app.add_systems(OnEnter(Screen::NameEntry), enter_name_entry)
+        .add_systems(
+            Update,
+            (
+                type_name.in_set(AppSet::RecordInput),
+                update_name.in_set(AppSet::Update),
+            )
+                .run_if(in_state(Screen::NameEntry).and_then(resource_exists::<PlayerName>)),
+        );
+}
+
+/// A result that made it into the high scores, waiting for the player's name.
+#[derive(Resource)]
+pub(super) struct NewHighScore(pub GameResult);
+
+#[derive(Resource, Deref, DerefMut)]
+struct PlayerName(String);
+
+#[derive(Component)]
+struct NameText;
+
+fn enter_name_entry(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    new_high_score: Option<Res<NewHighScore>>,
+    high_scores: Res<HighScores>,
+    mut next_screen: ResMut<NextState<Screen>>,
+) {
+    let Some(NewHighScore(result)) = new_high_score.as_deref() else {
+        warn!("No high score to enter a name for!");
+        next_screen.set(Screen::MainMenu);
+        return;
+    };
+    commands.insert_resource(PlayerName(high_scores.last_name.clone()));
+
+    let title_style = TextStyle {
+        font: assets.load("fonts/BungeeSpice-Regular.ttf"),
+        font_size: 40.0,
+        color: Color::WHITE,
+    };
+    let text_style = TextStyle {
+        font: assets.load("fonts/JetBrainsMonoNLNerdFont-Regular.ttf"),
+        font_size: 24.0,
+        color: Color::WHITE,
+    };
+    let summary = if result.mode.ranked_by_time() {
+        format!("{}  {}", result.mode, format_ticks(result.ticks))
+    } else {
+        format!("{}  {} points", result.mode, result.score)
+    };
+
+    commands
+        .spawn((
+            Name::new("Name entry"),
+            StateScoped(Screen::NameEntry),
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(20.0),
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .with_children(|children| {
+            children.spawn(TextBundle::from_section("New high score!", title_style));
+            children.spawn(TextBundle::from_section(summary, text_style.clone()));
+            children.spawn((
+                TextBundle::from_sections([
+                    TextSection::new("Name: ", text_style.clone()),
+                    TextSection::new("", text_style.clone()),
+                ]),
+                NameText,
+            ));
+            children.spawn(TextBundle::from_section(
+                "[Enter/Pad South] confirm",
+                TextStyle {
+                    font_size: 18.0,
+                    ..text_style
+                },
+            ));
+        });
+}
+
+/// Type the name on the keyboard. Players on a gamepad can only confirm the name they are given.
+fn type_name(
+    mut commands: Commands,
+    mut events: EventReader<KeyboardInput>,
+    gamepads: Res<Gamepads>,
+    buttons: Res<ButtonInput<GamepadButton>>,
+    mut name: ResMut<PlayerName>,
+    new_high_score: Res<NewHighScore>,
+    mut high_scores: ResMut<HighScores>,
+    mut next_screen: ResMut<NextState<Screen>>,
+) {
+    let mut confirmed = gamepad_just_pressed(&gamepads, &buttons, GamepadButtonType::South);
+    if confirmed && name.trim().is_empty() {
+        **name = DEFAULT_NAME.to_string();
+    }
+    for event in events.read() {
+        if event.state != ButtonState::Pressed {
+            continue;
+        }
+        match &event.logical_key {
+            Key::Character(chars) => {
+                for c in chars.chars().filter(|c| !c.is_control()) {
+                    if name.chars().count() < MAX_NAME_LEN {
+                        name.push(c);
+                    }
+                }
+            }
+            Key::Space if name.chars().count() < MAX_NAME_LEN => name.push(' '),
+            Key::Backspace => {
+                name.pop();
+            }
+            Key::Enter if !name.trim().is_empty() => {
+                confirmed = true;
+                break;
+            }
+            _ => (),
+        }
+    }
+
+    if confirmed {
+        let result = &new_high_score.0;
+        let player = name.trim().to_string();
+        info!("New {} high score for {player}", result.mode);
+        let rank = high_scores.insert(result.mode, HighScore::new(result, player.clone()));
+        high_scores.last_name = player;
+        if let Err(e) = high_scores.save() {
+            warn!("Failed to save high scores: {e}");
+        }
+
+        commands.insert_resource(HighScoresView {
+            mode: result.mode,
+            highlight: rank,
+        });
+        commands.remove_resource::<NewHighScore>();
+        commands.remove_resource::<PlayerName>();
+        next_screen.set(Screen::HighScores);
+    }
+}
+
+fn update_name(name: Res<PlayerName>, mut text: Query<&mut Text, With<NameText>>) {
+    if let Ok(mut text) = text.get_single_mut() {
+        text.sections[1].value = format!("{}_", **name);
+    }
+}
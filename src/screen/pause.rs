@@ -0,0 +1,262 @@
+//! The pause menu, shown over the game while it is paused.
+//!
+//! Pausing stops the virtual clock, so that neither the simulation nor the animations move on, and
+//! hides the board so that it can't be studied at leisure. The game also pauses by itself when the
+//! window loses focus or a gamepad is unplugged.
+
+use bevy::{
+    color::palettes, input::gamepad::GamepadConnectionEvent, prelude::*, window::WindowFocused,
+};
+
+use crate::AppSet;
+
+use super::{menu_input::MenuInput, GamePaused, InGame, Pause, ReturnScreen, Screen};
+
+pub fn plugin(app: &mut App) {
+    app.init_resource::<PauseSelection>()
+        .add_systems(OnEnter(GamePaused), freeze)
+        .add_systems(OnExit(GamePaused), unfreeze)
+        .add_systems(OnEnter(Pause::Paused), enter_pause_menu)
+        .add_systems(
+            Update,
+            (
+                (hover, navigate, choose)
+                    .chain()
+                    .in_set(AppSet::RecordInput),
+                update_items
+                    .run_if(resource_changed::<PauseSelection>)
+                    .in_set(AppSet::Update),
+            )
+                .run_if(in_state(Pause::Paused)),
+        )
+        .add_systems(
+            Update,
+            auto_pause
+                .run_if(in_state(Pause::Running).and_then(
+                    on_event::<WindowFocused>().or_else(on_event::<GamepadConnectionEvent>()),
+                ))
+                .in_set(AppSet::RecordInput),
+        );
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+enum PauseItem {
+    Resume,
+    Restart,
+    Settings,
+    Quit,
+}
+
+impl PauseItem {
+    const ALL: [PauseItem; 4] = [
+        PauseItem::Resume,
+        PauseItem::Restart,
+        PauseItem::Settings,
+        PauseItem::Quit,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            PauseItem::Resume => "Resume",
+            PauseItem::Restart => "Restart",
+            PauseItem::Settings => "Settings",
+            PauseItem::Quit => "Quit to menu",
+        }
+    }
+}
+
+/// The index of the highlighted item, back to "Resume" every time the game is paused.
+#[derive(Resource, Debug, Default)]
+struct PauseSelection(usize);
+
+impl PauseSelection {
+    /// Move the selection by one item up (-1) or down (1).
+    fn step(&mut self, direction: isize) {
+        let len = PauseItem::ALL.len() as isize;
+        self.0 = (self.0 as isize + direction).rem_euclid(len) as usize;
+    }
+}
+
+fn freeze(
+    mut time: ResMut<Time<Virtual>>,
+    mut board: Query<&mut Visibility, With<StateScoped<InGame>>>,
+    mut selection: ResMut<PauseSelection>,
+) {
+    info!("Pausing the game");
+    time.pause();
+    for mut visibility in &mut board {
+        *visibility = Visibility::Hidden;
+    }
+    *selection = PauseSelection::default();
+}
+
+fn unfreeze(
+    mut time: ResMut<Time<Virtual>>,
+    mut board: Query<&mut Visibility, With<StateScoped<InGame>>>,
+) {
+    time.unpause();
+    for mut visibility in &mut board {
+        *visibility = Visibility::Inherited;
+    }
+}
+
+fn enter_pause_menu(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    mut selection: ResMut<PauseSelection>,
+) {
+    let font = assets.load("fonts/BungeeSpice-Regular.ttf");
+    let item_style = TextStyle {
+        font: font.clone(),
+        font_size: 32.0,
+        color: Color::WHITE,
+    };
+    commands
+        .spawn((
+            Name::new("Pause menu"),
+            StateScoped(Pause::Paused),
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(8.0),
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .with_children(|children| {
+            children.spawn(
+                TextBundle::from_section(
+                    "Paused",
+                    TextStyle {
+                        font,
+                        font_size: 40.0,
+                        color: Color::WHITE,
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::bottom(Val::Px(20.0)),
+                    ..default()
+                }),
+            );
+            for item in PauseItem::ALL {
+                children
+                    .spawn((
+                        Name::new(item.label()),
+                        ButtonBundle {
+                            background_color: Color::NONE.into(),
+                            ..default()
+                        },
+                        item,
+                    ))
+                    .with_children(|button| {
+                        button.spawn(TextBundle::from_section(item.label(), item_style.clone()));
+                    });
+            }
+            children.spawn(
+                TextBundle::from_section(
+                    "[Up/Down] select  [Enter] choose  [Esc/Start] resume",
+                    TextStyle {
+                        font: assets.load("fonts/JetBrainsMonoNLNerdFont-Regular.ttf"),
+                        font_size: 18.0,
+                        color: Color::WHITE,
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::top(Val::Px(20.0)),
+                    ..default()
+                }),
+            );
+        });
+
+    // Make sure the selected item gets highlighted
+    selection.set_changed();
+}
+
+/// Pause the game when the player switches to another window, or loses their gamepad.
+fn auto_pause(
+    mut focus_events: EventReader<WindowFocused>,
+    mut gamepad_events: EventReader<GamepadConnectionEvent>,
+    mut next_pause: ResMut<NextState<Pause>>,
+) {
+    let unfocused = focus_events.read().any(|event| !event.focused);
+    let unplugged = gamepad_events.read().any(|event| event.disconnected());
+    if unfocused || unplugged {
+        next_pause.set(Pause::Paused);
+    }
+}
+
+/// Select the item under the mouse cursor.
+fn hover(
+    items: Query<(&Interaction, &PauseItem), Changed<Interaction>>,
+    mut selection: ResMut<PauseSelection>,
+) {
+    for (interaction, item) in &items {
+        if *interaction == Interaction::None {
+            continue;
+        }
+        let index = PauseItem::ALL.iter().position(|i| i == item);
+        if let Some(index) = index.filter(|index| *index != selection.0) {
+            selection.0 = index;
+        }
+    }
+}
+
+fn navigate(menu: Res<MenuInput>, mut selection: ResMut<PauseSelection>) {
+    if menu.vertical() != 0 {
+        selection.step(menu.vertical());
+    }
+}
+
+/// Do what the selected item says, when it is clicked or confirmed.
+fn choose(
+    menu: Res<MenuInput>,
+    keys: Res<ButtonInput<KeyCode>>,
+    items: Query<(&Interaction, &PauseItem), Changed<Interaction>>,
+    selection: Res<PauseSelection>,
+    return_screen: Option<Res<ReturnScreen>>,
+    mut next_pause: ResMut<NextState<Pause>>,
+    mut next_screen: ResMut<NextState<Screen>>,
+) {
+    let confirmed = menu.confirm
+        || keys.just_pressed(KeyCode::Space)
+        || items
+            .iter()
+            .any(|(interaction, _)| *interaction == Interaction::Pressed);
+    if !confirmed {
+        return;
+    }
+
+    match PauseItem::ALL[selection.0] {
+        PauseItem::Resume => next_pause.set(Pause::Running),
+        PauseItem::Restart => next_screen.set(Screen::Restart),
+        PauseItem::Settings => next_pause.set(Pause::Settings),
+        // Games started from another screen (e.g. a puzzle) go back to it
+        PauseItem::Quit => {
+            next_screen.set(return_screen.map_or(Screen::MainMenu, |screen| screen.0.clone()))
+        }
+    }
+}
+
+fn update_items(
+    selection: Res<PauseSelection>,
+    items: Query<(&PauseItem, &Children)>,
+    mut texts: Query<&mut Text>,
+) {
+    let selected = PauseItem::ALL[selection.0];
+    for (item, children) in &items {
+        let color = if *item == selected {
+            palettes::css::YELLOW.into()
+        } else {
+            Color::WHITE
+        };
+        let mut texts = texts.iter_many_mut(children);
+        while let Some(mut text) = texts.fetch_next() {
+            text.sections[0].style.color = color;
+        }
+    }
+}
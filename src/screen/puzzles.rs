@@ -0,0 +1,243 @@
+use bevy::{
+    asset::LoadedFolder, color::palettes, input::common_conditions::input_just_pressed, prelude::*,
+};
+
+use crate::{
+    game::{
+        mode::{GameMode, GameOver},
+        GameConfig,
+    },
+    puzzles::{Puzzle, PuzzleFolders, PuzzleProgress},
+    AppSet,
+};
+
+use super::{
+    menu_input::{menu_back, menu_confirmed, MenuInput},
+    Pause, ReturnScreen, Screen,
+};
+
+pub fn plugin(app: &mut App) {
+    app.init_resource::<PuzzleSelection>()
+        .add_systems(OnEnter(Screen::Puzzles), enter_puzzles)
+        .add_systems(
+            Update,
+            (
+                (select, play.run_if(menu_confirmed), back.run_if(menu_back))
+                    .in_set(AppSet::RecordInput),
+                update_list
+                    .run_if(
+                        resource_changed::<PuzzleSelection>
+                            .or_else(on_event::<AssetEvent<LoadedFolder>>())
+                            .or_else(on_event::<AssetEvent<Puzzle>>()),
+                    )
+                    .in_set(AppSet::Update),
+            )
+                .run_if(in_state(Screen::Puzzles)),
+        )
+        .add_systems(
+            Update,
+            (
+                restart
+                    .run_if(in_state(Pause::Running).and_then(input_just_pressed(KeyCode::KeyR))),
+                record_solved.run_if(on_event::<GameOver>()),
+            )
+                .in_set(AppSet::Update)
+                .run_if(in_state(Screen::Gameplay).and_then(resource_exists::<CurrentPuzzle>)),
+        );
+}
+
+/// The puzzle highlighted in the list.
+#[derive(Resource, Debug, Default)]
+struct PuzzleSelection(usize);
+
+/// The puzzle being played.
+#[derive(Resource, Debug)]
+struct CurrentPuzzle {
+    path: String,
+    /// Whether to start it again right away when coming back to the puzzle screen.
+    restart: bool,
+}
+
+#[derive(Component)]
+struct PuzzleList;
+
+fn enter_puzzles(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    current: Option<ResMut<CurrentPuzzle>>,
+    mut selection: ResMut<PuzzleSelection>,
+    mut next_screen: ResMut<NextState<Screen>>,
+) {
+    if let Some(mut current) = current {
+        if current.restart {
+            current.restart = false;
+            next_screen.set(Screen::Gameplay);
+            return;
+        }
+    }
+
+    let title_style = TextStyle {
+        font: assets.load("fonts/BungeeSpice-Regular.ttf"),
+        font_size: 40.0,
+        color: Color::WHITE,
+    };
+    let text_style = TextStyle {
+        font: assets.load("fonts/JetBrainsMonoNLNerdFont-Regular.ttf"),
+        font_size: 20.0,
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn((
+            Name::new("Puzzles"),
+            StateScoped(Screen::Puzzles),
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(20.0),
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .with_children(|children| {
+            children.spawn(TextBundle::from_section("Puzzles", title_style));
+            children.spawn((TextBundle::from_sections([]), PuzzleList));
+            children.spawn(TextBundle::from_section(
+                "[Up/Down] select  [Enter] play  [R] restart in game  [Esc] back",
+                TextStyle {
+                    font_size: 18.0,
+                    ..text_style
+                },
+            ));
+        });
+
+    // Make sure the list gets filled in
+    selection.set_changed();
+}
+
+fn select(
+    menu: Res<MenuInput>,
+    folders: Res<PuzzleFolders>,
+    loaded_folders: Res<Assets<LoadedFolder>>,
+    puzzles: Res<Assets<Puzzle>>,
+    mut selection: ResMut<PuzzleSelection>,
+) {
+    let len = folders.loaded(&loaded_folders, &puzzles).len();
+    if len == 0 {
+        return;
+    }
+    if menu.up {
+        selection.0 = (selection.0 + len - 1) % len;
+    } else if menu.down {
+        selection.0 = (selection.0 + 1) % len;
+    }
+}
+
+fn play(
+    mut commands: Commands,
+    selection: Res<PuzzleSelection>,
+    folders: Res<PuzzleFolders>,
+    loaded_folders: Res<Assets<LoadedFolder>>,
+    puzzles: Res<Assets<Puzzle>>,
+    mut config: ResMut<GameConfig>,
+    mut next_screen: ResMut<NextState<Screen>>,
+) {
+    let loaded = folders.loaded(&loaded_folders, &puzzles);
+    let Some((path, puzzle)) = loaded.get(selection.0) else {
+        return;
+    };
+    info!("Starting puzzle {path}");
+    config.mode = GameMode::Puzzle;
+    config.setup = Some(puzzle.setup.clone());
+    config.goal = Some(puzzle.goal);
+    commands.insert_resource(CurrentPuzzle {
+        path: path.clone(),
+        restart: false,
+    });
+    commands.insert_resource(ReturnScreen(Screen::Puzzles));
+    next_screen.set(Screen::Gameplay);
+}
+
+fn back(
+    mut commands: Commands,
+    mut config: ResMut<GameConfig>,
+    mut next_screen: ResMut<NextState<Screen>>,
+) {
+    // Go back to playing normal games
+    config.mode = GameMode::default();
+    config.setup = None;
+    config.goal = None;
+    commands.remove_resource::<CurrentPuzzle>();
+    commands.remove_resource::<ReturnScreen>();
+    next_screen.set(Screen::MainMenu);
+}
+
+fn update_list(
+    selection: Res<PuzzleSelection>,
+    folders: Res<PuzzleFolders>,
+    loaded_folders: Res<Assets<LoadedFolder>>,
+    puzzles: Res<Assets<Puzzle>>,
+    progress: Res<PuzzleProgress>,
+    assets: Res<AssetServer>,
+    mut list: Query<&mut Text, With<PuzzleList>>,
+) {
+    let Ok(mut list) = list.get_single_mut() else {
+        return;
+    };
+    let style = TextStyle {
+        font: assets.load("fonts/JetBrainsMonoNLNerdFont-Regular.ttf"),
+        font_size: 20.0,
+        color: Color::WHITE,
+    };
+
+    let loaded = folders.loaded(&loaded_folders, &puzzles);
+    if loaded.is_empty() {
+        list.sections = vec![TextSection::new("Loading puzzles...", style)];
+        return;
+    }
+    list.sections = loaded
+        .iter()
+        .enumerate()
+        .map(|(index, (path, puzzle))| {
+            let solved = if progress.is_solved(path) { "*" } else { " " };
+            let mut value = format!("{solved} {:<24} {}\n", puzzle.name, puzzle.goal);
+            let mut style = style.clone();
+            if index == selection.0 {
+                style.color = palettes::css::YELLOW.into();
+                if !puzzle.description.is_empty() {
+                    value += &format!("    {}\n", puzzle.description);
+                }
+            }
+            TextSection::new(value, style)
+        })
+        .collect();
+}
+
+/// Start the current puzzle again.
+fn restart(mut current: ResMut<CurrentPuzzle>, mut next_screen: ResMut<NextState<Screen>>) {
+    info!("Restarting puzzle {}", current.path);
+    current.restart = true;
+    next_screen.set(Screen::Puzzles);
+}
+
+fn record_solved(
+    mut events: EventReader<GameOver>,
+    current: Res<CurrentPuzzle>,
+    mut progress: ResMut<PuzzleProgress>,
+) {
+    let Some(GameOver(result)) = events.read().last() else {
+        return;
+    };
+    if result.completed && !progress.is_solved(&current.path) {
+        info!("Solved puzzle {}", current.path);
+        progress.mark_solved(current.path.clone());
+        if let Err(e) = progress.save() {
+            warn!("Failed to save puzzle progress: {e}");
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use bevy::prelude::*;
+
+use crate::{
+    args::Args,
+    game::{
+        replay::{Replay, ReplayPlayer},
+        SimulationControl, SimulationTick, TICK_RATE,
+    },
+    AppSet,
+};
+
+use super::{format_ticks, menu_input::menu_back, Screen};
+
+/// The playback speeds that can be cycled through.
+const SPEEDS: [f32; 5] = [0.25, 0.5, 1.0, 2.0, 4.0];
+/// How far the arrow keys seek.
+const SEEK_STEP: u64 = 5 * TICK_RATE as u64;
+
+pub fn plugin(app: &mut App) {
+    app.add_systems(OnEnter(Screen::Replay), enter_replay)
+        .add_systems(OnExit(Screen::Replay), exit_replay)
+        .add_systems(
+            Update,
+            (
+                (handle_controls, back.run_if(menu_back)).in_set(AppSet::RecordInput),
+                update_status.in_set(AppSet::Update),
+            )
+                .run_if(in_state(Screen::Replay)),
+        );
+}
+
+/// Start watching the replay given on the command line, if any.
+pub fn watch_from_args(app: &mut App) {
+    let Some(path) = app.world().resource::<Args>().replay.clone() else {
+        return;
+    };
+    match Replay::load(&path) {
+        Ok(replay) => {
+            app.insert_resource(ReplayPlayer::new(replay))
+                .insert_state(Screen::Replay);
+        }
+        Err(e) => error!("Failed to load replay {path}: {e}"),
+    }
+}
+
+#[derive(Component)]
+struct ReplayStatus;
+
+fn enter_replay(mut commands: Commands, assets: Res<AssetServer>) {
+    let text_style = TextStyle {
+        font: assets.load("fonts/JetBrainsMonoNLNerdFont-Regular.ttf"),
+        font_size: 18.0,
+        color: Color::WHITE,
+    };
+
+    commands.spawn((
+        Name::new("Replay status"),
+        StateScoped(Screen::Replay),
+        TextBundle::from_sections([
+            TextSection::new("", text_style.clone()),
+            TextSection::new(
+                "\n[Space] pause  [.] step  [-/+] speed  [</>] seek  [Home] restart  [Esc] back",
+                text_style,
+            ),
+        ])
+        .with_no_wrap()
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(5.0),
+            left: Val::Px(5.0),
+            ..default()
+        }),
+        ReplayStatus,
+    ));
+}
+
+fn exit_replay(mut time: ResMut<Time<Virtual>>) {
+    time.set_relative_speed(1.0);
+}
+
+fn handle_controls(
+    input: Res<ButtonInput<KeyCode>>,
+    mut player: ResMut<ReplayPlayer>,
+    mut control: ResMut<SimulationControl>,
+    mut time: ResMut<Time<Virtual>>,
+    tick: Res<SimulationTick>,
+) {
+    if input.just_pressed(KeyCode::Space) {
+        control.paused = !control.paused;
+    }
+    if input.just_pressed(KeyCode::Period) && control.paused {
+        control.steps += 1;
+    }
+
+    let speed = time.relative_speed();
+    let index = SPEEDS.iter().position(|s| *s == speed).unwrap_or(2);
+    if input.just_pressed(KeyCode::Minus) {
+        time.set_relative_speed(SPEEDS[index.saturating_sub(1)]);
+    } else if input.just_pressed(KeyCode::Equal) {
+        time.set_relative_speed(SPEEDS[(index + 1).min(SPEEDS.len() - 1)]);
+    }
+
+    if input.just_pressed(KeyCode::ArrowLeft) {
+        player.seek_to(tick.saturating_sub(SEEK_STEP));
+    } else if input.just_pressed(KeyCode::ArrowRight) {
+        player.seek_to(**tick + SEEK_STEP);
+    } else if input.just_pressed(KeyCode::Home) {
+        player.seek_to(0);
+    }
+}
+
+fn back(mut next_screen: ResMut<NextState<Screen>>) {
+    next_screen.set(Screen::Replays);
+}
+
+fn update_status(
+    player: Res<ReplayPlayer>,
+    control: Res<SimulationControl>,
+    time: Res<Time<Virtual>>,
+    tick: Res<SimulationTick>,
+    mut text: Query<&mut Text, With<ReplayStatus>>,
+) {
+    if let Ok(mut text) = text.get_single_mut() {
+        text.sections[0].value = format!(
+            "REPLAY  {} / {}  x{}{}",
+            format_ticks(**tick),
+            format_ticks(player.replay.len()),
+            time.relative_speed(),
+            if control.paused { "  PAUSED" } else { "" }
+        );
+    }
+}
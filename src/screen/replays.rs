@@ -0,0 +1,218 @@
+//! Browsing the saved replays, most recent first, to pick one to watch.
+
+use std::{fs, path::PathBuf};
+
+use bevy::{color::palettes, prelude::*};
+
+use crate::{
+    game::{
+        mode::GameMode,
+        replay::{replay_dir, Replay, ReplayPlayer, EXTENSION},
+    },
+    AppSet,
+};
+
+use super::{
+    format_date, format_ticks,
+    menu_input::{menu_back, menu_confirmed, MenuInput},
+    Screen,
+};
+
+/// Number of replays listed at once.
+const PAGE_LEN: usize = 15;
+
+pub fn plugin(app: &mut App) {
+    app.add_systems(OnEnter(Screen::Replays), enter_replays)
+        .add_systems(OnExit(Screen::Replays), exit_replays)
+        .add_systems(
+            Update,
+            (
+                (select, watch.run_if(menu_confirmed), back.run_if(menu_back))
+                    .in_set(AppSet::RecordInput),
+                update_list
+                    .run_if(resource_changed::<ReplayList>)
+                    .in_set(AppSet::Update),
+            )
+                .run_if(in_state(Screen::Replays)),
+        );
+}
+
+/// A saved replay.
+#[derive(Debug)]
+struct ReplayEntry {
+    path: PathBuf,
+    /// When the replay was saved, as a Unix timestamp.
+    date: u64,
+    mode: GameMode,
+    ticks: u64,
+}
+
+/// The saved replays, and the one that is highlighted.
+#[derive(Resource, Debug, Default)]
+struct ReplayList {
+    entries: Vec<ReplayEntry>,
+    selected: usize,
+}
+
+impl ReplayList {
+    /// Read the replays in the replay directory, skipping the ones that can't be read.
+    fn load() -> Self {
+        let files = fs::read_dir(replay_dir())
+            .into_iter()
+            .flatten()
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == EXTENSION));
+        let mut entries: Vec<ReplayEntry> = files
+            .filter_map(|path| {
+                let replay = Replay::load(&path)
+                    .inspect_err(|e| warn!("Failed to load replay {}: {e}", path.display()))
+                    .ok()?;
+                // Replays are named after the time they were saved at, with a suffix if several
+                // were saved within the same second
+                let stem = path.file_stem()?.to_str()?;
+                let date = stem.split('-').next()?.parse().unwrap_or_default();
+                Some(ReplayEntry {
+                    date,
+                    mode: replay.mode,
+                    ticks: replay.len(),
+                    path,
+                })
+            })
+            .collect();
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.date));
+        Self {
+            entries,
+            selected: 0,
+        }
+    }
+}
+
+#[derive(Component)]
+struct ReplayListText;
+
+fn enter_replays(mut commands: Commands, assets: Res<AssetServer>) {
+    let title_style = TextStyle {
+        font: assets.load("fonts/BungeeSpice-Regular.ttf"),
+        font_size: 40.0,
+        color: Color::WHITE,
+    };
+    let text_style = TextStyle {
+        font: assets.load("fonts/JetBrainsMonoNLNerdFont-Regular.ttf"),
+        font_size: 20.0,
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn((
+            Name::new("Replays"),
+            StateScoped(Screen::Replays),
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(20.0),
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .with_children(|children| {
+            children.spawn(TextBundle::from_section("Replays", title_style));
+            children.spawn((TextBundle::from_sections([]), ReplayListText));
+            children.spawn(TextBundle::from_section(
+                "[Up/Down] select  [Enter] watch  [Esc] back",
+                TextStyle {
+                    font_size: 18.0,
+                    ..text_style
+                },
+            ));
+        });
+
+    commands.insert_resource(ReplayList::load());
+}
+
+fn exit_replays(mut commands: Commands) {
+    commands.remove_resource::<ReplayList>();
+}
+
+fn select(menu: Res<MenuInput>, mut list: ResMut<ReplayList>) {
+    let len = list.entries.len();
+    if len == 0 {
+        return;
+    }
+    if menu.up {
+        list.selected = (list.selected + len - 1) % len;
+    } else if menu.down {
+        list.selected = (list.selected + 1) % len;
+    }
+}
+
+fn watch(
+    mut commands: Commands,
+    list: Res<ReplayList>,
+    mut next_screen: ResMut<NextState<Screen>>,
+) {
+    let Some(entry) = list.entries.get(list.selected) else {
+        return;
+    };
+    match Replay::load(&entry.path) {
+        Ok(replay) => {
+            commands.insert_resource(ReplayPlayer::new(replay));
+            next_screen.set(Screen::Replay);
+        }
+        Err(e) => error!("Failed to load replay {}: {e}", entry.path.display()),
+    }
+}
+
+fn back(mut next_screen: ResMut<NextState<Screen>>) {
+    next_screen.set(Screen::MainMenu);
+}
+
+fn update_list(
+    list: Res<ReplayList>,
+    assets: Res<AssetServer>,
+    mut text: Query<&mut Text, With<ReplayListText>>,
+) {
+    let Ok(mut text) = text.get_single_mut() else {
+        return;
+    };
+    let style = TextStyle {
+        font: assets.load("fonts/JetBrainsMonoNLNerdFont-Regular.ttf"),
+        font_size: 20.0,
+        color: Color::WHITE,
+    };
+
+    if list.entries.is_empty() {
+        text.sections = vec![TextSection::new("No replays yet", style)];
+        return;
+    }
+    // Scroll so that the selected replay is always shown
+    let first = list.selected.saturating_sub(PAGE_LEN - 1);
+    text.sections = list
+        .entries
+        .iter()
+        .enumerate()
+        .skip(first)
+        .take(PAGE_LEN)
+        .map(|(index, entry)| {
+            let time = entry.date % 86400;
+            let value = format!(
+                "{} {:02}:{:02}  {:<10} {:>9}\n",
+                format_date(entry.date),
+                time / 3600,
+                time / 60 % 60,
+                entry.mode.to_string(),
+                format_ticks(entry.ticks),
+            );
+            let mut style = style.clone();
+            if index == list.selected {
+                style.color = palettes::css::YELLOW.into();
+            }
+            TextSection::new(value, style)
+        })
+        .collect();
+}
@@ -0,0 +1,164 @@
+use bevy::prelude::*;
+
+use crate::{
+    game::{
+        clear::Spin,
+        mode::{GameMode, GameResult},
+    },
+    highscores::HighScores,
+    model::TetriminoKind,
+    AppSet,
+};
+
+use super::{
+    format_ticks, high_scores::HighScoresView, menu_input::menu_confirmed,
+    name_entry::NewHighScore, ReturnScreen, Screen,
+};
+
+pub fn plugin(app: &mut App) {
+    app.add_systems(OnEnter(Screen::Results), enter_results)
+        .add_systems(
+            Update,
+            next.in_set(AppSet::RecordInput).run_if(
+                in_state(Screen::Results)
+                    .and_then(resource_exists::<FinishedGame>)
+                    .and_then(menu_confirmed),
+            ),
+        );
+}
+
+/// The game whose results are shown.
+#[derive(Resource)]
+pub(super) struct FinishedGame(pub GameResult);
+
+fn enter_results(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    finished: Option<Res<FinishedGame>>,
+    mut next_screen: ResMut<NextState<Screen>>,
+) {
+    let Some(FinishedGame(result)) = finished.as_deref() else {
+        warn!("No game to show the results of!");
+        next_screen.set(Screen::MainMenu);
+        return;
+    };
+
+    let title_style = TextStyle {
+        font: assets.load("fonts/BungeeSpice-Regular.ttf"),
+        font_size: 40.0,
+        color: Color::WHITE,
+    };
+    let text_style = TextStyle {
+        font: assets.load("fonts/JetBrainsMonoNLNerdFont-Regular.ttf"),
+        font_size: 20.0,
+        color: Color::WHITE,
+    };
+    let has_goal = result.mode.ranked_by_time() || result.mode == GameMode::Puzzle;
+    let title = match (result.completed, has_goal) {
+        (true, _) => format!("{} complete!", result.mode),
+        (false, true) => format!("{} failed", result.mode),
+        (false, false) => format!("{} over", result.mode),
+    };
+
+    commands
+        .spawn((
+            Name::new("Results"),
+            StateScoped(Screen::Results),
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(20.0),
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .with_children(|children| {
+            children.spawn(TextBundle::from_section(title, title_style));
+            children.spawn(TextBundle::from_section(
+                format_stats(result),
+                text_style.clone(),
+            ));
+            children.spawn(TextBundle::from_section(
+                "[Enter] continue",
+                TextStyle {
+                    font_size: 18.0,
+                    ..text_style
+                },
+            ));
+        });
+}
+
+fn format_stats(result: &GameResult) -> String {
+    let stats = &result.stats;
+    let row = |label: &str, value: String| format!("{label:<18}{value:>12}\n");
+    let clears = |spin, lines| stats.clears(spin, lines).to_string();
+
+    let mut text = String::new();
+    text += &row("Score", result.score.to_string());
+    text += &row("Lines", result.lines.to_string());
+    text += &row("Level", result.level.to_string());
+    text += &row("Time", format_ticks(result.ticks));
+    text += "\n";
+    text += &row("Pieces", stats.pieces.to_string());
+    text += &row(
+        "Pieces/second",
+        format!("{:.2}", stats.pieces_per_second(result.ticks)),
+    );
+    text += &row("Keys/piece", format!("{:.2}", stats.keys_per_piece()));
+    text += &row("Finesse faults", stats.finesse_faults.to_string());
+    text += "\n";
+    text += &row("Singles", clears(Spin::None, 1));
+    text += &row("Doubles", clears(Spin::None, 2));
+    text += &row("Triples", clears(Spin::None, 3));
+    text += &row("Tetrises", clears(Spin::None, 4));
+    text += &row(
+        "Mini T-spins 0/1/2",
+        (0..=2)
+            .map(|lines| clears(Spin::Mini, lines))
+            .collect::<Vec<_>>()
+            .join("/"),
+    );
+    text += &row(
+        "T-spins 0/1/2/3",
+        (0..=3)
+            .map(|lines| clears(Spin::Full, lines))
+            .collect::<Vec<_>>()
+            .join("/"),
+    );
+    text += &row("Max combo", stats.max_combo.to_string());
+    text += &row("Max back-to-back", stats.max_back_to_back.to_string());
+    text += "\n";
+    text += &TetriminoKind::all()
+        .iter()
+        .map(|kind| format!("{kind:?}:{}", stats.distribution[*kind as usize]))
+        .collect::<Vec<_>>()
+        .join(" ");
+    text
+}
+
+/// Go back to where the game was started from if it has no high scores (e.g. a puzzle). Otherwise,
+/// ask for the player's name if the game made it into the high scores, or show them.
+fn next(
+    mut commands: Commands,
+    finished: Res<FinishedGame>,
+    high_scores: Res<HighScores>,
+    return_screen: Option<Res<ReturnScreen>>,
+    mut next_screen: ResMut<NextState<Screen>>,
+) {
+    let result = &finished.0;
+    if !result.mode.has_high_scores() {
+        next_screen.set(return_screen.map_or(Screen::MainMenu, |screen| screen.0.clone()));
+    } else if high_scores.rank(result).is_some() {
+        commands.insert_resource(NewHighScore(result.clone()));
+        next_screen.set(Screen::NameEntry);
+    } else {
+        commands.insert_resource(HighScoresView::new(result.mode));
+        next_screen.set(Screen::HighScores);
+    }
+    commands.remove_resource::<FinishedGame>();
+}
@@ -0,0 +1,310 @@
+//! Editing the settings, from the main menu or from the pause menu.
+//!
+//! Changes apply as they are made (except for the handling and training, which are only picked up by new games),
+//! and are saved when leaving the screen.
+
+use bevy::{asset::LoadedFolder, color::palettes, prelude::*};
+
+use crate::{
+    settings::{ClearEffect, Settings, WindowMode},
+    skin::{loaded_skins, CurrentSkin, Skin, SkinFolder},
+    AppSet,
+};
+
+use super::{
+    menu_input::{menu_back, MenuInput},
+    InSettings, Pause, Screen,
+};
+
+pub fn plugin(app: &mut App) {
+    app.init_resource::<SettingsSelection>()
+        .add_systems(OnEnter(InSettings), enter_settings)
+        .add_systems(OnExit(InSettings), save_settings)
+        .add_systems(
+            Update,
+            (
+                (navigate, back.run_if(menu_back)).in_set(AppSet::RecordInput),
+                update_list
+                    .run_if(
+                        resource_changed::<SettingsSelection>
+                            .or_else(resource_changed::<Settings>)
+                            .or_else(resource_changed::<CurrentSkin>),
+                    )
+                    .in_set(AppSet::Update),
+            )
+                .run_if(in_state(InSettings)),
+        );
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SettingsItem {
+    LockDelay,
+    SoftDropFactor,
+    LineClearDelay,
+    GhostOpacity,
+    Previews,
+    Skin,
+    ClearEffect,
+    ScreenShake,
+    MasterVolume,
+    MusicVolume,
+    EffectsVolume,
+    WindowMode,
+    FinesseTraining,
+}
+
+impl SettingsItem {
+    const ALL: [SettingsItem; 13] = [
+        SettingsItem::LockDelay,
+        SettingsItem::SoftDropFactor,
+        SettingsItem::LineClearDelay,
+        SettingsItem::GhostOpacity,
+        SettingsItem::Previews,
+        SettingsItem::Skin,
+        SettingsItem::ClearEffect,
+        SettingsItem::ScreenShake,
+        SettingsItem::MasterVolume,
+        SettingsItem::MusicVolume,
+        SettingsItem::EffectsVolume,
+        SettingsItem::WindowMode,
+        SettingsItem::FinesseTraining,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            SettingsItem::LockDelay => "Lock delay",
+            SettingsItem::SoftDropFactor => "Soft drop speed",
+            SettingsItem::LineClearDelay => "Line clear delay",
+            SettingsItem::GhostOpacity => "Ghost opacity",
+            SettingsItem::Previews => "Previews",
+            SettingsItem::Skin => "Skin",
+            SettingsItem::ClearEffect => "Clear effect",
+            SettingsItem::ScreenShake => "Screen shake",
+            SettingsItem::MasterVolume => "Master volume",
+            SettingsItem::MusicVolume => "Music volume",
+            SettingsItem::EffectsVolume => "Effects volume",
+            SettingsItem::WindowMode => "Window mode",
+            SettingsItem::FinesseTraining => "Finesse training",
+        }
+    }
+
+    fn value(&self, settings: &Settings, skin: &Skin) -> String {
+        let percent = |value: u8| format!("{value}%");
+        match self {
+            SettingsItem::LockDelay => format!("{} ms", settings.handling.lock_delay),
+            SettingsItem::SoftDropFactor => format!("{}x", settings.handling.soft_drop_factor),
+            SettingsItem::LineClearDelay => format!("{} ms", settings.handling.line_clear_delay),
+            SettingsItem::GhostOpacity if settings.visuals.ghost_opacity == 0 => "Off".into(),
+            SettingsItem::GhostOpacity => percent(settings.visuals.ghost_opacity),
+            SettingsItem::Previews => settings.visuals.previews.to_string(),
+            SettingsItem::Skin => skin.name.clone(),
+            SettingsItem::ClearEffect => settings.visuals.clear_effect.to_string(),
+            SettingsItem::ScreenShake if settings.visuals.screen_shake == 0 => "Off".into(),
+            SettingsItem::ScreenShake => percent(settings.visuals.screen_shake),
+            SettingsItem::MasterVolume => percent(settings.audio.master),
+            SettingsItem::MusicVolume => percent(settings.audio.music),
+            SettingsItem::EffectsVolume => percent(settings.audio.effects),
+            SettingsItem::WindowMode => settings.video.window_mode.to_string(),
+            SettingsItem::FinesseTraining if settings.training.finesse => "On".into(),
+            SettingsItem::FinesseTraining => "Off".into(),
+        }
+    }
+
+    /// Change the value of the setting by one step down (-1) or up (1), picking skins among the
+    /// given ones.
+    fn adjust(&self, settings: &mut Settings, direction: i8, skins: &[String]) {
+        let step_percent = |value: &mut u8| {
+            *value = value.saturating_add_signed(10 * direction).min(100);
+        };
+        match self {
+            SettingsItem::LockDelay => {
+                let delay = &mut settings.handling.lock_delay;
+                *delay = delay
+                    .saturating_add_signed(50 * direction as i16)
+                    .clamp(100, 2000);
+            }
+            SettingsItem::SoftDropFactor => {
+                let factor = &mut settings.handling.soft_drop_factor;
+                *factor = factor.saturating_add_signed(direction).clamp(1, 40);
+            }
+            SettingsItem::LineClearDelay => {
+                let delay = &mut settings.handling.line_clear_delay;
+                *delay = delay.saturating_add_signed(50 * direction as i16).min(1000);
+            }
+            SettingsItem::GhostOpacity => step_percent(&mut settings.visuals.ghost_opacity),
+            SettingsItem::Previews => {
+                let previews = &mut settings.visuals.previews;
+                *previews = previews.saturating_add_signed(direction).min(5);
+            }
+            SettingsItem::Skin => {
+                if skins.is_empty() {
+                    return;
+                }
+                let index = skins
+                    .iter()
+                    .position(|skin| *skin == settings.visuals.skin)
+                    .unwrap_or_default();
+                let index = (index as isize + direction as isize).rem_euclid(skins.len() as isize);
+                settings.visuals.skin = skins[index as usize].clone();
+            }
+            SettingsItem::ScreenShake => step_percent(&mut settings.visuals.screen_shake),
+            SettingsItem::MasterVolume => step_percent(&mut settings.audio.master),
+            SettingsItem::MusicVolume => step_percent(&mut settings.audio.music),
+            SettingsItem::EffectsVolume => step_percent(&mut settings.audio.effects),
+            SettingsItem::ClearEffect => {
+                let effects = ClearEffect::ALL;
+                let index = effects
+                    .iter()
+                    .position(|effect| *effect == settings.visuals.clear_effect)
+                    .unwrap_or_default();
+                let index =
+                    (index as isize + direction as isize).rem_euclid(effects.len() as isize);
+                settings.visuals.clear_effect = effects[index as usize];
+            }
+            SettingsItem::WindowMode => {
+                let modes = WindowMode::ALL;
+                let index = modes
+                    .iter()
+                    .position(|mode| *mode == settings.video.window_mode)
+                    .unwrap_or_default();
+                let index = (index as isize + direction as isize).rem_euclid(modes.len() as isize);
+                settings.video.window_mode = modes[index as usize];
+            }
+            SettingsItem::FinesseTraining => {
+                settings.training.finesse = !settings.training.finesse;
+            }
+        }
+    }
+}
+
+/// The index of the highlighted setting.
+#[derive(Resource, Debug, Default)]
+struct SettingsSelection(usize);
+
+#[derive(Component)]
+struct SettingsListText;
+
+fn enter_settings(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    mut selection: ResMut<SettingsSelection>,
+) {
+    let title_style = TextStyle {
+        font: assets.load("fonts/BungeeSpice-Regular.ttf"),
+        font_size: 40.0,
+        color: Color::WHITE,
+    };
+    let text_style = TextStyle {
+        font: assets.load("fonts/JetBrainsMonoNLNerdFont-Regular.ttf"),
+        font_size: 18.0,
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn((
+            Name::new("Settings"),
+            StateScoped(InSettings),
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(20.0),
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .with_children(|children| {
+            children.spawn(TextBundle::from_section("Settings", title_style));
+            children.spawn((TextBundle::from_sections([]), SettingsListText));
+            children.spawn(
+                TextBundle::from_section(
+                    "[Up/Down] select  [Left/Right] change  [Esc] back\n\
+                     Handling changes apply from the next game",
+                    text_style,
+                )
+                .with_text_justify(JustifyText::Center),
+            );
+        });
+
+    *selection = SettingsSelection::default();
+}
+
+fn save_settings(settings: Res<Settings>) {
+    match settings.save() {
+        Ok(()) => info!("Saved settings"),
+        Err(e) => error!("Failed to save settings: {e}"),
+    }
+}
+
+fn navigate(
+    menu: Res<MenuInput>,
+    mut selection: ResMut<SettingsSelection>,
+    mut settings: ResMut<Settings>,
+    folder: Res<SkinFolder>,
+    folders: Res<Assets<LoadedFolder>>,
+    skins: Res<Assets<Skin>>,
+) {
+    let len = SettingsItem::ALL.len();
+    if menu.up {
+        selection.0 = (selection.0 + len - 1) % len;
+    } else if menu.down {
+        selection.0 = (selection.0 + 1) % len;
+    } else if menu.horizontal() != 0 {
+        let skins: Vec<String> = loaded_skins(&folder, &folders, &skins)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        SettingsItem::ALL[selection.0].adjust(&mut settings, menu.horizontal() as i8, &skins);
+    }
+}
+
+/// Go back to the menu the settings were opened from.
+fn back(
+    screen: Res<State<Screen>>,
+    mut next_screen: ResMut<NextState<Screen>>,
+    mut next_pause: ResMut<NextState<Pause>>,
+) {
+    if *screen.get() == Screen::Settings {
+        next_screen.set(Screen::MainMenu);
+    } else {
+        next_pause.set(Pause::Paused);
+    }
+}
+
+fn update_list(
+    selection: Res<SettingsSelection>,
+    settings: Res<Settings>,
+    skin: Res<CurrentSkin>,
+    assets: Res<AssetServer>,
+    mut text: Query<&mut Text, With<SettingsListText>>,
+) {
+    let Ok(mut text) = text.get_single_mut() else {
+        return;
+    };
+    let style = TextStyle {
+        font: assets.load("fonts/JetBrainsMonoNLNerdFont-Regular.ttf"),
+        font_size: 20.0,
+        color: Color::WHITE,
+    };
+
+    text.sections = SettingsItem::ALL
+        .iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let value = format!(
+                "{:<16} {:>12}\n",
+                item.label(),
+                item.value(&settings, &skin)
+            );
+            let mut style = style.clone();
+            if index == selection.0 {
+                style.color = palettes::css::YELLOW.into();
+            }
+            TextSection::new(value, style)
+        })
+        .collect();
+}
@@ -0,0 +1,253 @@
+//! The player's preferences, kept across sessions in a TOML file in the user config directory.
+//!
+//! Missing entries take their default value, so that files written by older versions of the game
+//! can still be read.
+
+use std::{fs, io, path::PathBuf};
+
+use bevy::{
+    prelude::*,
+    window::{self, PrimaryWindow},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    args::Args,
+    game::{input::Controls, GameConfig},
+    screen::Screen,
+    storage,
+};
+
+/// Version of the settings file format.
+const VERSION: u32 = 1;
+
+pub fn plugin(app: &mut App) {
+    app.insert_resource(Settings::load())
+        // Replaces the default bindings the game starts with
+        .insert_resource(Controls::load())
+        .add_systems(OnEnter(Screen::Gameplay), apply_handling)
+        .add_systems(
+            Update,
+            apply_window_mode.run_if(resource_changed::<Settings>),
+        );
+}
+
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    /// Version of the format the file was written with.
+    pub version: u32,
+    pub handling: Handling,
+    pub visuals: Visuals,
+    pub audio: Audio,
+    pub video: Video,
+    pub training: Training,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            version: VERSION,
+            handling: Handling::default(),
+            visuals: Visuals::default(),
+            audio: Audio::default(),
+            video: Video::default(),
+            training: Training::default(),
+        }
+    }
+}
+
+impl Settings {
+    fn path() -> PathBuf {
+        storage::config_dir().join("settings.toml")
+    }
+
+    /// Load the settings, falling back to the defaults if they can't be read.
+    pub fn load() -> Self {
+        let path = Self::path();
+        let settings = match fs::read_to_string(&path) {
+            Ok(contents) => toml::from_str::<Self>(&contents).map_err(|e| e.to_string()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Self::default(),
+            Err(e) => Err(e.to_string()),
+        };
+        match settings {
+            Ok(mut settings) => {
+                if settings.version > VERSION {
+                    warn!(
+                        "Settings were saved by a newer version of the game (format {}), some of \
+                         them may be ignored",
+                        settings.version
+                    );
+                }
+                settings.version = VERSION;
+                settings
+            }
+            Err(e) => {
+                warn!("Failed to load settings from {}: {e}", path.display());
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = Self::path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let contents = toml::to_string_pretty(self).map_err(|e| io::Error::other(e.to_string()))?;
+        fs::write(path, contents)
+    }
+}
+
+/// How pieces behave under the player's control.
+///
+/// These change how a game plays out, so they are part of the `GameConfig` (and recorded in
+/// replays) rather than read live: changes take effect from the next game.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(default)]
+pub struct Handling {
+    /// How long a piece can rest on a surface before locking, in milliseconds.
+    pub lock_delay: u16,
+    /// How many times faster pieces fall during soft drop.
+    pub soft_drop_factor: u8,
+    /// How long cleared lines stay in the matrix before the blocks above fall, in milliseconds.
+    pub line_clear_delay: u16,
+}
+
+impl Default for Handling {
+    fn default() -> Self {
+        Self {
+            lock_delay: 500,
+            soft_drop_factor: 20,
+            line_clear_delay: 1000,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Visuals {
+    /// Opacity of the ghost piece in percent, hidden at 0.
+    pub ghost_opacity: u8,
+    /// Number of upcoming pieces shown.
+    pub previews: u8,
+    /// Name of the skin file, in `assets/skins`.
+    pub skin: String,
+    pub clear_effect: ClearEffect,
+    /// How much the screen shakes with line clears, in percent.
+    pub screen_shake: u8,
+}
+
+impl Default for Visuals {
+    fn default() -> Self {
+        Self {
+            ghost_opacity: 20,
+            previews: 1,
+            skin: "classic".to_string(),
+            clear_effect: ClearEffect::default(),
+            screen_shake: 100,
+        }
+    }
+}
+
+/// Volumes in percent, muted at 0.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Audio {
+    pub master: u8,
+    pub music: u8,
+    pub effects: u8,
+}
+
+impl Default for Audio {
+    fn default() -> Self {
+        Self {
+            master: 100,
+            music: 50,
+            effects: 100,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct Video {
+    pub window_mode: WindowMode,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct Training {
+    /// Whether pieces placed with finesse faults have to be placed again.
+    pub finesse: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default, strum::Display)]
+#[serde(rename_all = "kebab-case")]
+pub enum WindowMode {
+    #[default]
+    Windowed,
+    Borderless,
+    Fullscreen,
+}
+
+impl WindowMode {
+    pub const ALL: [WindowMode; 3] = [
+        WindowMode::Windowed,
+        WindowMode::Borderless,
+        WindowMode::Fullscreen,
+    ];
+}
+
+/// How cleared lines disappear.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default, strum::Display)]
+#[serde(rename_all = "kebab-case")]
+pub enum ClearEffect {
+    /// The blocks turn white and fade out.
+    #[default]
+    Fade,
+    /// The blocks blink before disappearing.
+    Flash,
+    /// The blocks shrink away one by one.
+    Dissolve,
+    /// The blocks burst into pieces.
+    Shatter,
+    /// A light sweeps across the lines.
+    Sweep,
+}
+
+impl ClearEffect {
+    pub const ALL: [ClearEffect; 5] = [
+        ClearEffect::Fade,
+        ClearEffect::Flash,
+        ClearEffect::Dissolve,
+        ClearEffect::Shatter,
+        ClearEffect::Sweep,
+    ];
+}
+
+impl From<WindowMode> for window::WindowMode {
+    fn from(mode: WindowMode) -> Self {
+        match mode {
+            WindowMode::Windowed => window::WindowMode::Windowed,
+            WindowMode::Borderless => window::WindowMode::BorderlessFullscreen,
+            WindowMode::Fullscreen => window::WindowMode::Fullscreen,
+        }
+    }
+}
+
+/// Start games with the handling and training of the settings.
+fn apply_handling(settings: Res<Settings>, args: Res<Args>, mut config: ResMut<GameConfig>) {
+    config.handling = settings.handling;
+    config.finesse_training = settings.training.finesse || args.finesse_training;
+}
+
+fn apply_window_mode(settings: Res<Settings>, mut window: Query<&mut Window, With<PrimaryWindow>>) {
+    let Ok(mut window) = window.get_single_mut() else {
+        return;
+    };
+    let mode = settings.video.window_mode.into();
+    if window.mode != mode {
+        window.mode = mode;
+    }
+}
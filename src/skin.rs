@@ -0,0 +1,467 @@
+//! Skins: how the minos, the ghost piece and the playfield look, loaded from `assets/skins`.
+//!
+//! A skin is a RON file, where every entry is optional and defaults to the classic look:
+//!
+//! ```ron
+//! (
+//!     name: "Guideline",
+//!     minos: Bevel,
+//!     colors: { 'I': "#00f0f0", 'O': "#f0f000" },
+//!     garbage: "#808080",
+//!     locked: Piece,
+//!     ghost: Outline,
+//!     wall: "#606060",
+//!     background: "#101018",
+//! )
+//! ```
+//!
+//! `minos` is either `Flat`, `Bevel` (shaded procedurally), or `Atlas(texture: "skins/x.png",
+//! tile_size: 16)` for a texture with one tile per kind of piece, in the order O, I, T, L, J, S, Z,
+//! then one for garbage. `locked` is either `Piece` for the blocks left by pieces to keep their
+//! colors, or `Color("#808080")` to recolor them once they lock. `ghost` is one of `Translucent`,
+//! `Outline` or `Hidden`.
+//!
+//! Skins are picked in the settings, and reloaded as they are edited when the `file_watcher`
+//! feature is enabled.
+
+use std::{collections::BTreeMap, fmt};
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext, LoadedFolder},
+    color::palettes,
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        texture::ImageSampler,
+    },
+};
+use serde::Deserialize;
+
+use crate::{
+    model::{Cell, TetriminoKind},
+    settings::Settings,
+};
+
+/// Size of the procedurally shaded textures, in pixels.
+const TEXTURE_SIZE: u32 = 16;
+/// Number of tiles in a skin texture: one per kind of piece, then garbage.
+const ATLAS_TILES: u32 = 8;
+
+pub fn plugin(app: &mut App) {
+    app.init_asset::<Skin>()
+        .register_asset_loader(SkinLoader)
+        .init_resource::<CurrentSkin>()
+        .add_systems(Startup, (load_skins, create_textures))
+        .add_systems(
+            Update,
+            select_skin
+                .run_if(resource_changed::<Settings>.or_else(on_event::<AssetEvent<Skin>>())),
+        )
+        .add_systems(PostUpdate, apply_skin);
+}
+
+/// A sprite drawn by the skin.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Skinned {
+    /// A mino of a piece in play or on display.
+    Mino(TetriminoKind),
+    Ghost(TetriminoKind),
+    /// A block of the matrix.
+    Block(Cell),
+    Wall,
+}
+
+#[derive(Asset, TypePath, Debug, Clone)]
+pub struct Skin {
+    pub name: String,
+    pub minos: MinoStyle,
+    colors: [Color; 7],
+    garbage: Color,
+    /// The color of blocks left by locked pieces, if they don't keep the color of their piece.
+    locked: Option<Color>,
+    pub ghost: GhostStyle,
+    wall: Color,
+    background: Color,
+}
+
+impl Default for Skin {
+    fn default() -> Self {
+        use palettes::css::*;
+
+        Self {
+            name: "Classic".to_string(),
+            minos: MinoStyle::Flat,
+            colors: [YELLOW, BLUE, PURPLE, ORANGE, MIDNIGHT_BLUE, GREEN, RED].map(Color::from),
+            garbage: GRAY.into(),
+            locked: Some(GRAY.into()),
+            ghost: GhostStyle::Translucent,
+            wall: Color::WHITE,
+            background: Color::BLACK,
+        }
+    }
+}
+
+impl Skin {
+    pub fn color(&self, kind: TetriminoKind) -> Color {
+        self.colors[kind as usize]
+    }
+
+    /// The color of a cell, as drawn in a flat style.
+    pub fn cell_color(&self, cell: Cell) -> Color {
+        match cell {
+            Cell::Mino(kind) => self.color(kind),
+            Cell::Garbage => self.garbage,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub enum MinoStyle {
+    #[default]
+    Flat,
+    /// Shaded to look like bevelled tiles.
+    Bevel,
+    /// Drawn from the tiles of a texture.
+    Atlas {
+        texture: Handle<Image>,
+        layout: Handle<TextureAtlasLayout>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+pub enum GhostStyle {
+    /// Drawn like the piece, with the opacity of the settings.
+    #[default]
+    Translucent,
+    /// Only the outline of the minos.
+    Outline,
+    Hidden,
+}
+
+/// A skin as it's written in its file.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct SkinFile {
+    name: Option<String>,
+    minos: MinoStyleFile,
+    colors: BTreeMap<char, String>,
+    garbage: Option<String>,
+    locked: Option<LockedFile>,
+    ghost: GhostStyle,
+    wall: Option<String>,
+    background: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+enum MinoStyleFile {
+    #[default]
+    Flat,
+    Bevel,
+    Atlas {
+        texture: String,
+        tile_size: u32,
+    },
+}
+
+#[derive(Deserialize)]
+enum LockedFile {
+    Piece,
+    Color(String),
+}
+
+#[derive(Debug)]
+pub enum SkinError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+    InvalidPiece(char),
+    InvalidColor(String),
+}
+
+impl fmt::Display for SkinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkinError::Io(e) => write!(f, "{e}"),
+            SkinError::Ron(e) => write!(f, "{e}"),
+            SkinError::InvalidPiece(c) => write!(f, "invalid piece '{c}'"),
+            SkinError::InvalidColor(color) => write!(f, "invalid color \"{color}\""),
+        }
+    }
+}
+
+impl std::error::Error for SkinError {}
+
+fn parse_color(hex: &str) -> Result<Color, SkinError> {
+    Srgba::hex(hex)
+        .map(Color::from)
+        .map_err(|_| SkinError::InvalidColor(hex.to_string()))
+}
+
+struct SkinLoader;
+
+impl AssetLoader for SkinLoader {
+    type Asset = Skin;
+    type Settings = ();
+    type Error = SkinError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Skin, SkinError> {
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .await
+            .map_err(SkinError::Io)?;
+        // Entries are optional, without having to be written as `Some(...)`
+        let file: SkinFile = ron::Options::default()
+            .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME)
+            .from_bytes(&bytes)
+            .map_err(SkinError::Ron)?;
+
+        let mut skin = Skin::default();
+        if let Some(name) = file.name {
+            skin.name = name;
+        }
+        skin.minos = match file.minos {
+            MinoStyleFile::Flat => MinoStyle::Flat,
+            MinoStyleFile::Bevel => MinoStyle::Bevel,
+            MinoStyleFile::Atlas { texture, tile_size } => MinoStyle::Atlas {
+                texture: load_context.load(texture),
+                layout: load_context.add_labeled_asset(
+                    "layout".to_string(),
+                    TextureAtlasLayout::from_grid(
+                        UVec2::splat(tile_size),
+                        ATLAS_TILES,
+                        1,
+                        None,
+                        None,
+                    ),
+                ),
+            },
+        };
+        for (letter, color) in &file.colors {
+            let kind =
+                TetriminoKind::from_letter(*letter).ok_or(SkinError::InvalidPiece(*letter))?;
+            skin.colors[kind as usize] = parse_color(color)?;
+        }
+        if let Some(garbage) = &file.garbage {
+            skin.garbage = parse_color(garbage)?;
+        }
+        match &file.locked {
+            Some(LockedFile::Piece) => skin.locked = None,
+            Some(LockedFile::Color(color)) => skin.locked = Some(parse_color(color)?),
+            None => {}
+        }
+        skin.ghost = file.ghost;
+        if let Some(wall) = &file.wall {
+            skin.wall = parse_color(wall)?;
+        }
+        if let Some(background) = &file.background {
+            skin.background = parse_color(background)?;
+        }
+        Ok(skin)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["skin.ron"]
+    }
+}
+
+/// All the skins of the `skins` asset folder.
+#[derive(Resource, Deref)]
+pub struct SkinFolder(Handle<LoadedFolder>);
+
+/// The skin picked in the settings, or the classic one until it is loaded.
+#[derive(Resource, Debug, Default, Deref)]
+pub struct CurrentSkin(Skin);
+
+/// The textures for the procedurally shaded styles, tinted with the color of each mino.
+#[derive(Resource)]
+struct SkinTextures {
+    bevel: Handle<Image>,
+    outline: Handle<Image>,
+}
+
+fn load_skins(mut commands: Commands, assets: Res<AssetServer>) {
+    commands.insert_resource(SkinFolder(assets.load_folder("skins")));
+}
+
+/// The loaded skins, by name of their file (e.g. `classic` for `skins/classic.skin.ron`).
+pub fn loaded_skins<'a>(
+    folder: &SkinFolder,
+    folders: &Assets<LoadedFolder>,
+    skins: &'a Assets<Skin>,
+) -> Vec<(String, &'a Skin)> {
+    let Some(folder) = folders.get(&**folder) else {
+        return Vec::new();
+    };
+    let mut loaded: Vec<(String, &Skin)> = folder
+        .handles
+        .iter()
+        .filter_map(|handle| {
+            let skin = skins.get(handle.id().try_typed::<Skin>().ok()?)?;
+            let file = handle.path()?.path().file_name()?.to_str()?;
+            Some((file.strip_suffix(".skin.ron")?.to_string(), skin))
+        })
+        .collect();
+    loaded.sort_by(|(a, _), (b, _)| a.cmp(b));
+    loaded
+}
+
+/// Build a texture from a function giving the brightness and opacity of each pixel.
+fn texture(pixel: impl Fn(u32, u32) -> (f32, f32)) -> Image {
+    let mut data = Vec::with_capacity((TEXTURE_SIZE * TEXTURE_SIZE * 4) as usize);
+    for y in 0..TEXTURE_SIZE {
+        for x in 0..TEXTURE_SIZE {
+            let (brightness, alpha) = pixel(x, y);
+            let value = (brightness * 255.0) as u8;
+            data.extend([value, value, value, (alpha * 255.0) as u8]);
+        }
+    }
+    let mut image = Image::new(
+        Extent3d {
+            width: TEXTURE_SIZE,
+            height: TEXTURE_SIZE,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    image.sampler = ImageSampler::nearest();
+    image
+}
+
+fn create_textures(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    const EDGE: u32 = 2;
+    let last = TEXTURE_SIZE - 1;
+    let bevel = texture(|x, y| {
+        // Lit from the top left, with a dark border between minos
+        let brightness = if x == 0 || y == 0 || x == last || y == last {
+            0.35
+        } else if x < EDGE || y < EDGE {
+            1.0
+        } else if x > last - EDGE || y > last - EDGE {
+            0.55
+        } else {
+            0.8
+        };
+        (brightness, 1.0)
+    });
+    let outline = texture(|x, y| {
+        let edge = x < EDGE || y < EDGE || x > last - EDGE || y > last - EDGE;
+        (1.0, if edge { 1.0 } else { 0.0 })
+    });
+    commands.insert_resource(SkinTextures {
+        bevel: images.add(bevel),
+        outline: images.add(outline),
+    });
+}
+
+/// Follow the skin of the settings, and changes to its file.
+fn select_skin(
+    settings: Res<Settings>,
+    folder: Res<SkinFolder>,
+    folders: Res<Assets<LoadedFolder>>,
+    skins: Res<Assets<Skin>>,
+    mut current: ResMut<CurrentSkin>,
+) {
+    let skin = loaded_skins(&folder, &folders, &skins)
+        .into_iter()
+        .find_map(|(name, skin)| (name == settings.visuals.skin).then(|| skin.clone()));
+    // Keep the current skin until the selected one is loaded
+    if let Some(skin) = skin {
+        current.0 = skin;
+    }
+}
+
+/// Draw the sprites as the skin says, when they are spawned and when the skin changes.
+fn apply_skin(
+    mut commands: Commands,
+    skin: Res<CurrentSkin>,
+    settings: Res<Settings>,
+    textures: Option<Res<SkinTextures>>,
+    mut clear_color: ResMut<ClearColor>,
+    mut sprites: Query<(Entity, Ref<Skinned>, &mut Sprite, &mut Handle<Image>)>,
+) {
+    let Some(textures) = textures else {
+        return;
+    };
+    let restyle_all = skin.is_changed() || settings.is_changed();
+    if skin.is_changed() {
+        clear_color.0 = skin.background;
+    }
+
+    for (entity, skinned, mut sprite, mut texture) in &mut sprites {
+        if !restyle_all && !skinned.is_added() {
+            continue;
+        }
+        // The atlas tile of the cell, if drawn from an atlas
+        let mut tile = None;
+        let (color, image) = match *skinned {
+            Skinned::Wall => (skin.wall, Handle::default()),
+            Skinned::Ghost(kind) => {
+                let opacity = settings.visuals.ghost_opacity as f32 / 100.0;
+                match skin.ghost {
+                    GhostStyle::Hidden => (Color::NONE, Handle::default()),
+                    // Opacity 0 hides the ghost whatever the skin
+                    GhostStyle::Outline if opacity > 0.0 => {
+                        (skin.color(kind), textures.outline.clone())
+                    }
+                    GhostStyle::Outline => (Color::NONE, Handle::default()),
+                    GhostStyle::Translucent => {
+                        let (color, image) =
+                            mino_look(&skin, &textures, Cell::Mino(kind), &mut tile);
+                        (color.with_alpha(opacity), image)
+                    }
+                }
+            }
+            Skinned::Mino(kind) => mino_look(&skin, &textures, Cell::Mino(kind), &mut tile),
+            Skinned::Block(cell) => {
+                let (color, image) = mino_look(&skin, &textures, cell, &mut tile);
+                match (cell, skin.locked) {
+                    (Cell::Mino(_), Some(locked)) => (locked, image),
+                    _ => (color, image),
+                }
+            }
+        };
+        sprite.color = color;
+        *texture = image;
+        match (tile, &skin.minos) {
+            (Some(index), MinoStyle::Atlas { layout, .. }) => {
+                commands.entity(entity).insert(TextureAtlas {
+                    layout: layout.clone(),
+                    index,
+                });
+            }
+            _ => {
+                commands.entity(entity).remove::<TextureAtlas>();
+            }
+        }
+    }
+}
+
+/// The color and texture of a mino of the given cell, setting the atlas tile to draw if any.
+fn mino_look(
+    skin: &Skin,
+    textures: &SkinTextures,
+    cell: Cell,
+    tile: &mut Option<usize>,
+) -> (Color, Handle<Image>) {
+    match &skin.minos {
+        MinoStyle::Flat => (skin.cell_color(cell), Handle::default()),
+        MinoStyle::Bevel => (skin.cell_color(cell), textures.bevel.clone()),
+        MinoStyle::Atlas { texture, .. } => {
+            *tile = Some(match cell {
+                Cell::Mino(kind) => kind as usize,
+                Cell::Garbage => ATLAS_TILES as usize - 1,
+            });
+            // The tiles have their own colors
+            (Color::WHITE, texture.clone())
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! Locations of the files the game reads and writes, and helpers to encode them.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Longest string read from a file, in bytes.
+const MAX_STRING_LEN: usize = 1024;
+
+/// Directory where the game keeps its data (replays, high scores...).
+pub fn data_dir() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("betris")
+}
+
+/// Directory where the game keeps the player's preferences.
+pub fn config_dir() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("betris")
+}
+
+/// A path in `dir` for a new file named after the current time, with a counter appended if other
+/// files were saved there in the same second.
+pub fn timestamped_path(dir: &Path, extension: &str) -> PathBuf {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let mut path = dir.join(format!("{timestamp}.{extension}"));
+    for n in 1.. {
+        if !path.exists() {
+            break;
+        }
+        path = dir.join(format!("{timestamp}-{n}.{extension}"));
+    }
+    path
+}
+
+pub fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+/// Read the next `n` bytes.
+pub fn take<'a>(bytes: &mut &'a [u8], n: usize) -> io::Result<&'a [u8]> {
+    if bytes.len() < n {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    let (head, tail) = bytes.split_at(n);
+    *bytes = tail;
+    Ok(head)
+}
+
+pub fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+pub fn read_varint(bytes: &mut &[u8]) -> io::Result<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let byte = take(bytes, 1)?[0];
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(invalid_data("varint too long"))
+}
+
+/// Read a length or a count, which must be at most `max`, so that a corrupt file can't make us
+/// allocate unbounded amounts of memory.
+pub fn read_len(bytes: &mut &[u8], max: usize) -> io::Result<usize> {
+    let len = read_varint(bytes)?;
+    if len > max as u64 {
+        return Err(invalid_data(format!(
+            "length {len} is over the limit of {max}"
+        )));
+    }
+    Ok(len as usize)
+}
+
+pub fn write_string(bytes: &mut Vec<u8>, s: &str) {
+    write_varint(bytes, s.len() as u64);
+    bytes.extend_from_slice(s.as_bytes());
+}
+
+pub fn read_string(bytes: &mut &[u8]) -> io::Result<String> {
+    let len = read_len(bytes, MAX_STRING_LEN)?;
+    String::from_utf8(take(bytes, len)?.to_vec()).map_err(|e| invalid_data(e.to_string()))
+}